@@ -5,11 +5,21 @@
 @group(0) @binding(0) var<storage, read> vertices: array<vec3<f32>>;
 @group(0) @binding(1) var<storage, read_write> new_vertices: array<vec3<f32>>;
 @group(0) @binding(2) var<uniform> num_vertices: u32;
-@group(0) @binding(3) var<storage, read> noise_params_shape: array<vec4<f32>,3>;
-@group(0) @binding(4) var<storage, read> noise_params_ridge: array<vec4<f32>,3>;
-@group(0) @binding(5) var<storage, read> noise_params_ridge2: array<vec4<f32>,3>;
+@group(0) @binding(3) var<uniform> noise_params_shape: array<vec4<f32>,3>;
+@group(0) @binding(4) var<uniform> noise_params_ridge: array<vec4<f32>,3>;
+@group(0) @binding(5) var<uniform> noise_params_ridge2: array<vec4<f32>,3>;
 @group(0) @binding(6) var<storage, read_write> normal_accumulators: array<NormalAccumulator>;
 @group(0) @binding(7) var<uniform> max_strength: f32;
+// Two entries per vertex: the crater, shape, ridge and ridge 2 heights, then the perturbed position
+@group(0) @binding(12) var<storage, read_write> layer_cache: array<vec4<f32>>;
+@group(0) @binding(13) var<uniform> dirty_layers: u32;
+
+// Must match the LAYER_* constants in compute.rs
+const LAYER_CRATERS: u32 = 1u;
+const LAYER_SHAPE_NOISE: u32 = 2u;
+const LAYER_RIDGE_NOISE: u32 = 4u;
+const LAYER_RIDGE_NOISE2: u32 = 8u;
+const LAYER_PERTURB: u32 = 16u;
  
 @compute @workgroup_size(64)
 fn main(@builtin(global_invocation_id) global_id: vec3<u32>) {
@@ -21,22 +31,33 @@ fn main(@builtin(global_invocation_id) global_id: vec3<u32>) {
 
         let vertexPos = vertices[index];
         let elevationMultiplier = 0.01;
-        
-        let craterDepth = calculateCraterDepth(vertexPos);
-        
-        let shapeNoise = simpleNoise(vertexPos, noise_params_shape);
-        
-        // Ridge noise
-        let ridgeNoise = smoothedRidgidNoise(vertexPos, noise_params_ridge);
-        let ridge2 = smoothedRidgidNoise(vertexPos, noise_params_ridge2);
-        
-        let noiseSum = (shapeNoise + ridgeNoise + ridge2) * elevationMultiplier;
-        let finalHeight = 1 + craterDepth + noiseSum;
-        
-        let height = length(vertexPos);
-        let offset = perturb(vertexPos);
-        var newPos = vertexPos + offset * max_strength;
-        newPos = normalize(newPos) * height;
+
+        // Only the dirty layers are evaluated, the others keep their heights from the previous run
+        var heights = layer_cache[index * 2u];
+        if ((dirty_layers & LAYER_CRATERS) != 0u) {
+            heights.x = calculateCraterDepth(vertexPos);
+        }
+        if ((dirty_layers & LAYER_SHAPE_NOISE) != 0u) {
+            heights.y = simpleNoise(vertexPos, noise_params_shape);
+        }
+        if ((dirty_layers & LAYER_RIDGE_NOISE) != 0u) {
+            heights.z = smoothedRidgidNoise(vertexPos, noise_params_ridge);
+        }
+        if ((dirty_layers & LAYER_RIDGE_NOISE2) != 0u) {
+            heights.w = smoothedRidgidNoise(vertexPos, noise_params_ridge2);
+        }
+        layer_cache[index * 2u] = heights;
+
+        var newPos = layer_cache[index * 2u + 1u].xyz;
+        if ((dirty_layers & LAYER_PERTURB) != 0u) {
+            let height = length(vertexPos);
+            let offset = perturb(vertexPos);
+            newPos = normalize(vertexPos + offset * max_strength) * height;
+            layer_cache[index * 2u + 1u] = vec4<f32>(newPos, 0.0);
+        }
+
+        let noiseSum = (heights.y + heights.z + heights.w) * elevationMultiplier;
+        let finalHeight = 1 + heights.x + noiseSum;
         
         new_vertices[index] = newPos * finalHeight;
         
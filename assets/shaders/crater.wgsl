@@ -11,11 +11,49 @@ struct Crater {
 @group(0) @binding(9) var<uniform> rim_steepness: f32;
 @group(0) @binding(10) var<uniform> rim_width: f32;
 @group(0) @binding(11) var<storage, read> craters: array<Crater>;
+// Cube-map cell list built by CraterGrid in crater_grid.rs: crater_grid[cell]..crater_grid[cell + 1]
+// is the range holding the indices of the craters that can reach that cell. A zero last offset means the
+// grid didn't fit in the buffer and every crater is visited.
+@group(0) @binding(14) var<storage, read> crater_grid: array<u32>;
+
+// Must match CRATER_GRID_SIZE in crater_grid.rs
+const CRATER_GRID_SIZE: u32 = 8u;
+const CRATER_GRID_CELLS: u32 = 6u * CRATER_GRID_SIZE * CRATER_GRID_SIZE;
+
+fn craterCell(pos: vec3<f32>) -> u32 {
+    let a = abs(pos);
+    var face: u32;
+    var uv: vec2<f32>;
+    if (a.x >= a.y && a.x >= a.z) {
+        face = select(1u, 0u, pos.x > 0.0);
+        uv = vec2(pos.y, pos.z) / a.x;
+    } else if (a.y >= a.z) {
+        face = select(3u, 2u, pos.y > 0.0);
+        uv = vec2(pos.x, pos.z) / a.y;
+    } else {
+        face = select(5u, 4u, pos.z > 0.0);
+        uv = vec2(pos.x, pos.y) / a.z;
+    }
+    let cell = vec2<u32>(clamp((uv + 1.0) / 2.0 * f32(CRATER_GRID_SIZE), vec2(0.0), vec2(f32(CRATER_GRID_SIZE - 1u))));
+    return face * CRATER_GRID_SIZE * CRATER_GRID_SIZE + cell.y * CRATER_GRID_SIZE + cell.x;
+}
 
 fn calculateCraterDepth(vertexPos: vec3<f32>) -> f32 {
     var craterHeight: f32 = 0.0;
+    if (num_craters == 0u) {
+        return craterHeight;
+    }
 
-    for (var i: u32 = 0; i < num_craters; i = i + 1) {
+    let useGrid = crater_grid[CRATER_GRID_CELLS] != 0u;
+    var start: u32 = 0u;
+    var end: u32 = num_craters;
+    if (useGrid) {
+        let cell = craterCell(vertexPos);
+        start = crater_grid[cell];
+        end = crater_grid[cell + 1u];
+    }
+    for (var j: u32 = start; j < end; j = j + 1) {
+        let i = select(j, crater_grid[j], useGrid);
         let centre = vec3(craters[i].centre[0],craters[i].centre[1],craters[i].centre[2]);
         let x = length(vertexPos - centre) / craters[i].radius;
 
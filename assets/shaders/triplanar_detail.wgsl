@@ -0,0 +1,73 @@
+#import bevy_pbr::{
+    pbr_fragment::pbr_input_from_standard_material,
+    pbr_functions::alpha_discard,
+    mesh_functions::get_world_from_local,
+}
+
+#ifdef PREPASS_PIPELINE
+#import bevy_pbr::{
+    prepass_io::{VertexOutput, FragmentOutput},
+    pbr_deferred_functions::deferred_output,
+}
+#else
+#import bevy_pbr::{
+    forward_io::{VertexOutput, FragmentOutput},
+    pbr_functions::{apply_pbr_lighting, main_pass_post_lighting_processing},
+}
+#endif
+
+struct TriplanarDetailParams {
+    scale: f32,
+    strength: f32,
+    sharpness: f32,
+    enabled: u32,
+}
+
+@group(2) @binding(100) var<uniform> detail: TriplanarDetailParams;
+@group(2) @binding(101) var detail_texture: texture_2d<f32>;
+@group(2) @binding(102) var detail_sampler: sampler;
+
+@fragment
+fn fragment(
+    in: VertexOutput,
+    @builtin(front_facing) is_front: bool,
+) -> FragmentOutput {
+    var pbr_input = pbr_input_from_standard_material(in, is_front);
+
+    if (detail.enabled != 0u) {
+        // Sample in object space so the detail sticks to the asteroid while it rotates.
+        // The asteroid transform only rotates, translates and scales uniformly,
+        // so the inverse of its linear part is the transpose divided by the squared scale.
+        let world_from_local = get_world_from_local(in.instance_index);
+        let linear = mat3x3<f32>(world_from_local[0].xyz, world_from_local[1].xyz, world_from_local[2].xyz);
+        let scale_sq = dot(linear[0], linear[0]);
+        let local_from_world = transpose(linear) * (1.0 / scale_sq);
+
+        let local_pos = local_from_world * (in.world_position.xyz - world_from_local[3].xyz);
+        let local_normal = normalize(local_from_world * in.world_normal);
+
+        var weights = pow(abs(local_normal), vec3<f32>(detail.sharpness));
+        weights = weights / (weights.x + weights.y + weights.z);
+
+        let p = local_pos * detail.scale;
+        let sample_x = textureSample(detail_texture, detail_sampler, p.yz).rgb;
+        let sample_y = textureSample(detail_texture, detail_sampler, p.xz).rgb;
+        let sample_z = textureSample(detail_texture, detail_sampler, p.xy).rgb;
+        let detail_color = sample_x * weights.x + sample_y * weights.y + sample_z * weights.z;
+
+        let tint = mix(vec3<f32>(1.0), detail_color * 2.0, detail.strength);
+        pbr_input.material.base_color = vec4<f32>(pbr_input.material.base_color.rgb * tint, pbr_input.material.base_color.a);
+    }
+
+    pbr_input.material.base_color = alpha_discard(pbr_input.material, pbr_input.material.base_color);
+
+#ifdef PREPASS_PIPELINE
+    let out = deferred_output(in, pbr_input);
+#else
+    var out: FragmentOutput;
+    out.color = apply_pbr_lighting(pbr_input);
+    out.color = main_pass_post_lighting_processing(pbr_input, out.color);
+#endif
+
+    return out;
+}
@@ -0,0 +1,358 @@
+use std::collections::HashMap;
+use std::f32::consts::TAU;
+use std::thread;
+
+use bevy::prelude::*;
+
+use crate::asteroid_material::AsteroidMaterialHandle;
+use crate::asteroid_mesh_builder::{generate_mesh, Asteroid};
+use crate::export_mesh::ExportMesh;
+use crate::generation_jobs::{GenerationJobFinished, GenerationJobs, GenerationJobsPlugin, JobId};
+use crate::settings::asteroid_settings::AsteroidSettings;
+use crate::settings::field_settings::{FieldLayout, FieldSettings};
+use crate::generator::generate_with_sphere;
+use crate::sphere_mesh::SphereMesh;
+use crate::utils::{derive_seed, PRNG};
+use crate::RngSeed;
+
+/// Generates the shapes of a field through `GenerationJobs`, which is added with its defaults unless the app
+/// added it first.
+pub struct AsteroidFieldPlugin;
+
+impl Plugin for AsteroidFieldPlugin {
+    fn build(&self, app: &mut App) {
+        if !app.is_plugin_added::<GenerationJobsPlugin>() {
+            app.add_plugins(GenerationJobsPlugin::default());
+        }
+
+        app.insert_resource(FieldSettings::default())
+            .insert_resource(AsteroidField::default())
+            .insert_resource(FieldShapeJobs::default())
+            .observe(generate_field)
+            .observe(collect_field_shape)
+            .add_systems(Update, update_field_visibility);
+    }
+}
+
+#[derive(Event)]
+pub struct GenerateFieldClicked;
+
+/// Marks the entity every field asteroid is parented to, scaled so the whole field fits the view.
+#[derive(Component)]
+pub struct FieldRoot;
+
+/// One generated shape, placed any number of times.
+#[derive(Clone)]
+pub struct FieldShape {
+    pub seed: u64,
+    pub settings: AsteroidSettings,
+    /// Unit-radius mesh in generator units.
+    pub mesh: ExportMesh,
+}
+
+/// One placed asteroid. Translation and size are in the scale settings unit.
+#[derive(Clone, Copy)]
+pub struct FieldInstance {
+    pub shape: usize,
+    pub translation: Vec3,
+    pub rotation: Quat,
+    pub size: f32,
+}
+
+/// The last generated field, kept for export.
+#[derive(Resource, Default, Clone)]
+pub struct AsteroidField {
+    pub base_seed: u64,
+    /// Settings every shape was varied from.
+    pub asteroid_settings: AsteroidSettings,
+    pub settings: FieldSettings,
+    pub shapes: Vec<FieldShape>,
+    pub instances: Vec<FieldInstance>,
+}
+
+/// The field being generated, placed once the job of every shape has finished. Clicks while it runs are ignored.
+#[derive(Resource, Default)]
+pub struct FieldShapeJobs {
+    field: AsteroidField,
+    /// Seed and varied settings of each shape, see `get_shape_inputs`.
+    inputs: Vec<(u64, AsteroidSettings)>,
+    meshes: Vec<Option<ExportMesh>>,
+    jobs: HashMap<JobId, usize>,
+}
+
+impl FieldShapeJobs {
+    pub fn is_running(&self) -> bool {
+        !self.jobs.is_empty()
+    }
+}
+
+impl AsteroidField {
+    pub fn is_empty(&self) -> bool {
+        self.instances.is_empty()
+    }
+
+    /// Number of placed asteroids using each shape.
+    pub fn get_shape_usage(&self) -> Vec<usize> {
+        let mut usage = vec![0; self.shapes.len()];
+        for instance in &self.instances {
+            usage[instance.shape] += 1;
+        }
+        usage
+    }
+}
+
+fn generate_field(
+    _trigger: Trigger<GenerateFieldClicked>,
+    mut shape_jobs: ResMut<FieldShapeJobs>,
+    mut jobs: ResMut<GenerationJobs>,
+    field_settings: Res<FieldSettings>,
+    settings: Res<AsteroidSettings>,
+    seed: Res<RngSeed>,
+) {
+    if shape_jobs.is_running() {
+        return;
+    }
+
+    let inputs = get_shape_inputs(&settings, &field_settings, seed.0);
+    let shape_jobs = &mut *shape_jobs;
+    shape_jobs.jobs = inputs
+        .iter()
+        .enumerate()
+        .map(|(index, (shape_seed, shape_settings))| {
+            (jobs.submit(shape_settings.clone(), *shape_seed, field_settings.resolution), index)
+        })
+        .collect();
+    shape_jobs.meshes = vec![None; inputs.len()];
+    shape_jobs.inputs = inputs;
+    shape_jobs.field = AsteroidField {
+        base_seed: seed.0,
+        asteroid_settings: settings.clone(),
+        settings: field_settings.clone(),
+        ..default()
+    };
+}
+
+fn collect_field_shape(
+    trigger: Trigger<GenerationJobFinished>,
+    mut commands: Commands,
+    mut shape_jobs: ResMut<FieldShapeJobs>,
+    mut field: ResMut<AsteroidField>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    material: Res<AsteroidMaterialHandle>,
+    field_roots: Query<Entity, With<FieldRoot>>,
+) {
+    let ev = trigger.event();
+    let shape_jobs = &mut *shape_jobs;
+    let Some(index) = shape_jobs.jobs.remove(&ev.id) else {
+        return;
+    };
+    shape_jobs.meshes[index] = Some(ev.mesh.to_export_mesh());
+    if shape_jobs.is_running() {
+        return;
+    }
+
+    let shapes = shape_jobs
+        .inputs
+        .drain(..)
+        .zip(shape_jobs.meshes.drain(..))
+        .filter_map(|((seed, settings), mesh)| Some(FieldShape { seed, settings, mesh: mesh? }))
+        .collect();
+    let pending = std::mem::take(&mut shape_jobs.field);
+    *field = place_instances(pending.asteroid_settings, pending.settings, pending.base_seed, shapes);
+
+    for entity in field_roots.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+
+    // Every instance of a shape shares its mesh and the material, so they are drawn in one batch
+    let handles: Vec<Handle<Mesh>> = field
+        .shapes
+        .iter()
+        .map(|shape| {
+            let vertices = shape.mesh.positions.iter().map(|&p| Vec3::from(p)).collect();
+            let normals = shape.mesh.normals.iter().map(|&n| Vec3::from(n)).collect();
+            meshes.add(generate_mesh(vertices, shape.mesh.indices.clone(), normals))
+        })
+        .collect();
+
+    let view_scale = 3.0 / field.settings.get_extent().max(f32::EPSILON);
+    commands
+        .spawn((SpatialBundle::from_transform(Transform::from_scale(Vec3::splat(view_scale))), FieldRoot))
+        .with_children(|parent| {
+            for instance in &field.instances {
+                parent.spawn(MaterialMeshBundle {
+                    mesh: handles[instance.shape].clone(),
+                    material: material.0.clone(),
+                    transform: Transform {
+                        translation: instance.translation,
+                        rotation: instance.rotation,
+                        scale: Vec3::splat(instance.size),
+                    },
+                    ..default()
+                });
+            }
+        });
+
+    println!(
+        "Generated a field of {} asteroids from {} shapes",
+        field.instances.len(),
+        field.shapes.len()
+    );
+}
+
+/// Shows either the field or the single asteroid.
+fn update_field_visibility(
+    field_settings: Res<FieldSettings>,
+    mut field_roots: Query<&mut Visibility, (With<FieldRoot>, Without<Asteroid>)>,
+    mut asteroids: Query<&mut Visibility, (With<Asteroid>, Without<FieldRoot>)>,
+) {
+    let show_field = field_settings.show_in_viewer && !field_roots.is_empty();
+    for mut visibility in field_roots.iter_mut() {
+        *visibility = if show_field { Visibility::Inherited } else { Visibility::Hidden };
+    }
+    for mut visibility in asteroids.iter_mut() {
+        *visibility = if show_field { Visibility::Hidden } else { Visibility::Inherited };
+    }
+}
+
+/// Generates the shapes on the CPU and places the instances. Everything derives from `base_seed`,
+/// so the same settings always give the same field. Angles and sizes go through `sin`, `cos` and `powf`,
+/// so instance transforms may differ in the last bit between platforms.
+pub fn build_field(settings: &AsteroidSettings, field_settings: &FieldSettings, base_seed: u64) -> AsteroidField {
+    let sphere_mesh = SphereMesh::new(field_settings.resolution as usize);
+
+    let shapes = thread::scope(|scope| {
+        let workers: Vec<_> = get_shape_inputs(settings, field_settings, base_seed)
+            .into_iter()
+            .map(|(seed, settings)| {
+                let sphere_mesh = &sphere_mesh;
+                scope.spawn(move || FieldShape {
+                    mesh: generate_with_sphere(&settings, seed, sphere_mesh).to_export_mesh(),
+                    seed,
+                    settings,
+                })
+            })
+            .collect();
+        workers
+            .into_iter()
+            .map(|worker| worker.join().expect("shape generation panicked"))
+            .collect::<Vec<_>>()
+    });
+
+    place_instances(settings.clone(), field_settings.clone(), base_seed, shapes)
+}
+
+/// Seed and varied settings of every unique shape.
+fn get_shape_inputs(settings: &AsteroidSettings, field_settings: &FieldSettings, base_seed: u64) -> Vec<(u64, AsteroidSettings)> {
+    (0..get_shape_count(field_settings) as u64)
+        .map(|index| {
+            let seed = derive_seed(base_seed, index);
+            (seed, vary_settings(settings, field_settings, seed))
+        })
+        .collect()
+}
+
+/// Places `field_settings.count` instances of the generated shapes.
+fn place_instances(
+    settings: AsteroidSettings,
+    field_settings: FieldSettings,
+    base_seed: u64,
+    shapes: Vec<FieldShape>,
+) -> AsteroidField {
+    // Placement uses its own stream, independent of the shape generation
+    let mut prng = PRNG::new(derive_seed(base_seed, u64::MAX));
+    let instances = (0..field_settings.count)
+        .map(|_| FieldInstance {
+            shape: prng.index(shapes.len()),
+            translation: sample_position(&mut prng, &field_settings),
+            rotation: sample_rotation(&mut prng),
+            size: sample_size(&mut prng, &field_settings),
+        })
+        .collect();
+
+    AsteroidField {
+        base_seed,
+        asteroid_settings: settings,
+        settings: field_settings,
+        shapes,
+        instances,
+    }
+}
+
+fn get_shape_count(field_settings: &FieldSettings) -> usize {
+    field_settings.unique_shapes.clamp(1, field_settings.count.max(1)) as usize
+}
+
+/// Scales the main shape parameters by a random factor within the variation ranges.
+fn vary_settings(settings: &AsteroidSettings, field_settings: &FieldSettings, seed: u64) -> AsteroidSettings {
+    let mut prng = PRNG::new(seed);
+    let mut vary = |value: &mut f32, variation: f32| {
+        *value *= 1.0 + variation * (prng.get_value() * 2.0 - 1.0);
+    };
+
+    let mut settings = settings.clone();
+    let noise = field_settings.noise_variation;
+    vary(&mut settings.simple_noise_settings.scale, noise);
+    vary(&mut settings.simple_noise_settings.elevation, noise);
+    vary(&mut settings.ridge_noise_settings.scale, noise);
+    vary(&mut settings.ridge_noise_settings.elevation, noise);
+    vary(&mut settings.ridge_noise_settings2.elevation, noise);
+    vary(&mut settings.peturb_strength, noise);
+
+    let craters = field_settings.crater_variation;
+    vary(&mut settings.crater_settings.num_craters, craters);
+    vary(&mut settings.crater_settings.crater_size_max, craters);
+    settings.crater_settings.crater_size_max = settings
+        .crater_settings
+        .crater_size_max
+        .max(settings.crater_settings.crater_size_min);
+    settings
+}
+
+fn sample_position(prng: &mut PRNG, field_settings: &FieldSettings) -> Vec3 {
+    let inner = field_settings.inner_radius.min(field_settings.outer_radius);
+    let outer = field_settings.outer_radius.max(field_settings.inner_radius);
+    let height = prng.gaussian() * field_settings.thickness / 2.0;
+
+    match field_settings.layout {
+        FieldLayout::Belt => {
+            // Uniform over the area, not the radius, so the inner edge isn't denser
+            let radius = (inner * inner + prng.get_value() * (outer * outer - inner * inner)).sqrt();
+            let angle = prng.get_value() * TAU;
+            Vec3::new(radius * angle.cos(), height, radius * angle.sin())
+        }
+        FieldLayout::Ring => {
+            let radius = (inner + outer) / 2.0 + prng.gaussian() * (outer - inner) / 6.0;
+            let angle = prng.get_value() * TAU;
+            Vec3::new(radius * angle.cos(), height, radius * angle.sin())
+        }
+        FieldLayout::Cluster => {
+            let sigma = outer / 2.0;
+            Vec3::new(prng.gaussian(), prng.gaussian(), prng.gaussian()) * sigma
+        }
+    }
+}
+
+/// Uniformly distributed orientation, Shoemake's method.
+fn sample_rotation(prng: &mut PRNG) -> Quat {
+    let u1 = prng.get_value();
+    let u2 = prng.get_value() * TAU;
+    let u3 = prng.get_value() * TAU;
+    let a = (1.0 - u1).sqrt();
+    let b = u1.sqrt();
+    Quat::from_xyzw(a * u2.sin(), a * u2.cos(), b * u3.sin(), b * u3.cos()).normalize()
+}
+
+/// Truncated power law between the min and max size.
+fn sample_size(prng: &mut PRNG, field_settings: &FieldSettings) -> f32 {
+    let min = field_settings.min_size.max(f32::EPSILON);
+    let max = field_settings.max_size.max(min);
+    let q = field_settings.size_exponent;
+    let u = prng.get_value();
+
+    if q.abs() < 1e-3 {
+        return min * (max / min).powf(u);
+    }
+    let (a, b) = (min.powf(-q), max.powf(-q));
+    (a + u * (b - a)).powf(-1.0 / q)
+}
@@ -0,0 +1,184 @@
+use std::fs;
+use std::path::Path;
+
+use bevy::asset::{Asset, Assets, Handle};
+use bevy::pbr::{ExtendedMaterial, MaterialExtension, MaterialPlugin, StandardMaterial};
+use bevy::prelude::*;
+use bevy::render::render_asset::RenderAssetUsages;
+use bevy::render::render_resource::{AsBindGroup, ShaderRef, ShaderType};
+use bevy::render::texture::{CompressedImageFormats, ImageAddressMode, ImageSampler, ImageSamplerDescriptor, ImageType};
+use bevy::tasks::{block_on, futures_lite::future, AsyncComputeTaskPool, Task};
+
+use crate::compute_events::MaterialSettingsChanged;
+use crate::settings::material_settings::MaterialSettings;
+
+pub type AsteroidMaterial = ExtendedMaterial<StandardMaterial, TriplanarDetail>;
+
+pub struct AsteroidMaterialPlugin;
+
+/// The single material shared by the displayed asteroid, edited in place from the settings window.
+#[derive(Resource)]
+pub struct AsteroidMaterialHandle(pub Handle<AsteroidMaterial>);
+
+/// Detail texture of the material, read and decoded in the background. The path is kept after a failed load,
+/// so the same file isn't read again until the path changes.
+#[derive(Resource, Default)]
+pub struct DetailTexture {
+    path: String,
+    enabled: bool,
+    task: Option<Task<Result<Image, String>>>,
+    error: Option<String>,
+}
+
+impl DetailTexture {
+    /// Why the texture at the current path could not be loaded.
+    pub fn get_error(&self) -> Option<&str> {
+        self.error.as_deref()
+    }
+}
+
+#[derive(Asset, AsBindGroup, Reflect, Debug, Clone, Default)]
+pub struct TriplanarDetail {
+    #[uniform(100)]
+    pub params: TriplanarDetailParams,
+    #[texture(101)]
+    #[sampler(102)]
+    pub detail_texture: Option<Handle<Image>>,
+}
+
+#[derive(ShaderType, Reflect, Debug, Clone, Default)]
+pub struct TriplanarDetailParams {
+    pub scale: f32,
+    pub strength: f32,
+    pub sharpness: f32,
+    pub enabled: u32,
+}
+
+impl MaterialExtension for TriplanarDetail {
+    fn fragment_shader() -> ShaderRef {
+        "shaders/triplanar_detail.wgsl".into()
+    }
+
+    fn deferred_fragment_shader() -> ShaderRef {
+        "shaders/triplanar_detail.wgsl".into()
+    }
+}
+
+impl Plugin for AsteroidMaterialPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugins(MaterialPlugin::<AsteroidMaterial>::default())
+            .insert_resource(DetailTexture::default())
+            .add_systems(Startup, initialize_material)
+            .add_systems(Update, receive_detail_texture)
+            .observe(update_asteroid_material);
+    }
+}
+
+fn initialize_material(mut commands: Commands, mut materials: ResMut<Assets<AsteroidMaterial>>) {
+    let material_settings = MaterialSettings::default();
+    let handle = materials.add(AsteroidMaterial {
+        base: base_material(&material_settings),
+        extension: TriplanarDetail::default(),
+    });
+    commands.insert_resource(AsteroidMaterialHandle(handle));
+}
+
+pub fn base_material(material_settings: &MaterialSettings) -> StandardMaterial {
+    StandardMaterial {
+        base_color: material_settings.get_base_color(),
+        perceptual_roughness: material_settings.roughness,
+        metallic: material_settings.metallic,
+        reflectance: material_settings.reflectance,
+        ..default()
+    }
+}
+
+fn update_asteroid_material(
+    trigger: Trigger<MaterialSettingsChanged>,
+    material_handle: Res<AsteroidMaterialHandle>,
+    mut materials: ResMut<Assets<AsteroidMaterial>>,
+    mut detail_texture: ResMut<DetailTexture>,
+) {
+    let mut material_settings = trigger.event().0.clone();
+    material_settings.sanitize();
+    let material_settings = &material_settings;
+
+    let Some(material) = materials.get_mut(&material_handle.0) else {
+        return;
+    };
+
+    let base = &mut material.base;
+    base.base_color = material_settings.get_base_color();
+    base.perceptual_roughness = material_settings.roughness;
+    base.metallic = material_settings.metallic;
+    base.reflectance = material_settings.reflectance;
+
+    let detail = &mut material.extension;
+    let texture_path = &material_settings.detail_texture_path;
+    detail_texture.enabled = material_settings.detail_enabled;
+    if detail_texture.path != *texture_path {
+        detail.detail_texture = None;
+        detail_texture.path = texture_path.clone();
+        detail_texture.error = None;
+        // Replacing the task drops the load of the previous path
+        detail_texture.task = (!texture_path.is_empty()).then(|| {
+            let path = texture_path.clone();
+            AsyncComputeTaskPool::get().spawn(async move { load_detail_texture(Path::new(&path)) })
+        });
+    }
+
+    detail.params = TriplanarDetailParams {
+        scale: material_settings.detail_scale,
+        strength: material_settings.detail_strength,
+        sharpness: material_settings.detail_sharpness,
+        enabled: (material_settings.detail_enabled && detail.detail_texture.is_some()) as u32,
+    };
+}
+
+fn receive_detail_texture(
+    material_handle: Res<AsteroidMaterialHandle>,
+    mut materials: ResMut<Assets<AsteroidMaterial>>,
+    mut images: ResMut<Assets<Image>>,
+    mut detail_texture: ResMut<DetailTexture>,
+) {
+    let Some(task) = detail_texture.task.as_mut() else {
+        return;
+    };
+    let Some(result) = block_on(future::poll_once(task)) else {
+        return;
+    };
+    detail_texture.task = None;
+
+    match result {
+        Ok(image) => {
+            let Some(material) = materials.get_mut(&material_handle.0) else {
+                return;
+            };
+            material.extension.detail_texture = Some(images.add(image));
+            material.extension.params.enabled = detail_texture.enabled as u32;
+        }
+        Err(err) => detail_texture.error = Some(err),
+    }
+}
+
+fn load_detail_texture(path: &Path) -> Result<Image, String> {
+    let bytes = fs::read(path).map_err(|err| err.to_string())?;
+    let extension = path
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .unwrap_or("png");
+
+    Image::from_buffer(
+        &bytes,
+        ImageType::Extension(extension),
+        CompressedImageFormats::NONE,
+        true,
+        ImageSampler::Descriptor(ImageSamplerDescriptor {
+            address_mode_u: ImageAddressMode::Repeat,
+            address_mode_v: ImageAddressMode::Repeat,
+            ..ImageSamplerDescriptor::linear()
+        }),
+        RenderAssetUsages::RENDER_WORLD,
+    )
+    .map_err(|err| err.to_string())
+}
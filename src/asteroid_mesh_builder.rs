@@ -1,44 +1,48 @@
 ﻿use bevy::asset::Assets;
-use bevy::color::Color;
 use bevy::input::mouse::MouseMotion;
 use bevy::math::{Quat, Vec3};
-use bevy::pbr::{MaterialMeshBundle, StandardMaterial};
+use bevy::pbr::MaterialMeshBundle;
 use bevy::prelude::*;
-use bevy::render::mesh::{Indices, PrimitiveTopology};
+use bevy::render::mesh::{Indices, MeshVertexAttribute, PrimitiveTopology, VertexAttributeValues};
+use bevy::render::primitives::Aabb;
 use bevy::render::render_asset::RenderAssetUsages;
+#[cfg(feature = "gui")]
 use bevy_egui::EguiContexts;
 
+use crate::asteroid_material::AsteroidMaterialHandle;
 use crate::compute_events::MeshDataAfterCompute;
-use crate::sphere_mesh::SphereMesh;
+use crate::settings::spin_settings::SpinSettings;
 
 pub struct AsteroidMeshBuilderPlugin;
 
 impl Plugin for AsteroidMeshBuilderPlugin {
     fn build(&self, app: &mut App) {
         app
-            .observe(generate_mesh_from_new_vertices)
-            .add_systems(Update, rotate_asteroid_mouse);
+            .insert_resource(SpinSettings::default())
+            .insert_resource(ViewRotation::default())
+            .observe(update_asteroid_mesh)
+            .add_systems(Update, (rotate_asteroid_mouse, spin_asteroid).chain());
     }
 }
 
 #[derive(Component)]
 pub struct Asteroid;
 
+/// Orientation set by dragging with the mouse, the spin is applied on top of it.
+#[derive(Resource, Default)]
+pub struct ViewRotation(pub Quat);
+
 pub fn render_generated_asteroid(
     mut commands: Commands,
     mesh: Mesh,
-    mut materials: ResMut<Assets<StandardMaterial>>,
+    material: &AsteroidMaterialHandle,
     mut meshes: ResMut<Assets<Mesh>>,
     rot: Quat,
 ) {
     commands.spawn((
         MaterialMeshBundle {
             mesh: meshes.add(mesh),
-            material: materials.add(StandardMaterial {
-                base_color: Color::srgb(0.4, 0.4, 0.4),
-                perceptual_roughness: 0.9,
-                ..default()
-            }),
+            material: material.0.clone(),
 
             transform: Transform {
                 translation: Vec3::ZERO,
@@ -50,35 +54,49 @@ pub fn render_generated_asteroid(
         Asteroid
     ));
 }
-fn generate_mesh_from_new_vertices(
+/// Writes each compute result into the displayed asteroid's mesh, the entity and its index buffer are kept.
+fn update_asteroid_mesh(
     trigger: Trigger<MeshDataAfterCompute>,
-    asteroid_query: Query<(Entity, &Transform), With<Asteroid>>,
+    asteroid_query: Query<(Entity, &Handle<Mesh>), With<Asteroid>>,
     mut commands: Commands,
-    meshes: ResMut<Assets<Mesh>>,
-    materials: ResMut<Assets<StandardMaterial>>,
-    sphere_mesh: ResMut<SphereMesh>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    material: Res<AsteroidMaterialHandle>,
 ) {
-    let ev = trigger.event();
-    let new_vertices = ev.0.clone();
-    let normals = ev.1.clone();
+    let asteroid_mesh = &trigger.event().0;
+    let new_vertices = &asteroid_mesh.positions;
+    let normals = &asteroid_mesh.normals;
 
     if new_vertices.is_empty()
     {
         return;
     }
 
-    let mut rot = Quat::default();
+    let Ok((asteroid_entity, mesh_handle)) = asteroid_query.get_single() else {
+        render_generated_asteroid(commands, asteroid_mesh.to_mesh(), &material, meshes, Quat::IDENTITY);
+        return;
+    };
 
-    if let Ok(asteroid_entity) = asteroid_query.get_single() {
-        rot = asteroid_entity.1.rotation;
-        commands.entity(asteroid_entity.0).despawn();
-    }
+    let Some(mesh) = meshes.get_mut(mesh_handle) else {
+        return;
+    };
+    overwrite_attribute(mesh, Mesh::ATTRIBUTE_POSITION, new_vertices);
+    overwrite_attribute(mesh, Mesh::ATTRIBUTE_NORMAL, normals);
+
+    // The bounds are only computed for entities without an Aabb, drop it so culling uses the new shape
+    commands.entity(asteroid_entity).remove::<Aabb>();
+}
 
-    let mesh = generate_mesh(new_vertices, sphere_mesh.indices.clone(), normals);
-    render_generated_asteroid(commands, mesh, materials, meshes, rot);
+/// Copies into the attribute's existing buffer instead of allocating a new one.
+fn overwrite_attribute(mesh: &mut Mesh, attribute: MeshVertexAttribute, values: &[Vec3]) {
+    if let Some(VertexAttributeValues::Float32x3(buffer)) = mesh.attribute_mut(attribute.id) {
+        buffer.clear();
+        buffer.extend(values.iter().map(|value| value.to_array()));
+        return;
+    }
+    mesh.insert_attribute(attribute, values.to_vec());
 }
 
-fn generate_mesh(vertices: Vec<Vec3>, indices: Vec<u32>, normals: Vec<Vec3>) -> Mesh {
+pub fn generate_mesh(vertices: Vec<Vec3>, indices: Vec<u32>, normals: Vec<Vec3>) -> Mesh {
     let mut mesh = Mesh::new(PrimitiveTopology::TriangleList, RenderAssetUsages::RENDER_WORLD | RenderAssetUsages::MAIN_WORLD);
     mesh.insert_attribute(Mesh::ATTRIBUTE_POSITION, vertices);
     mesh.insert_attribute(Mesh::ATTRIBUTE_NORMAL, normals);
@@ -87,12 +105,14 @@ fn generate_mesh(vertices: Vec<Vec3>, indices: Vec<u32>, normals: Vec<Vec3>) ->
 }
 
 fn rotate_asteroid_mouse(
-    mut query: Query<&mut Transform, With<Asteroid>>,
+    mut view_rotation: ResMut<ViewRotation>,
     mut mouse_motion_events: EventReader<MouseMotion>,
     mouse_button_input: Res<ButtonInput<MouseButton>>,
-    mut contexts: EguiContexts,
+    #[cfg(feature = "gui")] mut contexts: EguiContexts,
 )
 {
+    // Drags over the settings window don't rotate the asteroid
+    #[cfg(feature = "gui")]
     if let Some(ctx) = contexts.try_ctx_mut() {
         if ctx.is_pointer_over_area() {
             return;
@@ -108,9 +128,21 @@ fn rotate_asteroid_mouse(
             rotation_y += event.delta.x * 0.005;
         }
 
-        for mut transform in query.iter_mut() {
-            transform.rotation *= Quat::from_rotation_x(rotation_x);
-            transform.rotation *= Quat::from_rotation_y(rotation_y);
-        }
+        view_rotation.0 *= Quat::from_rotation_x(rotation_x);
+        view_rotation.0 *= Quat::from_rotation_y(rotation_y);
+    }
+}
+
+fn spin_asteroid(
+    mut query: Query<&mut Transform, With<Asteroid>>,
+    view_rotation: Res<ViewRotation>,
+    spin_settings: Res<SpinSettings>,
+    time: Res<Time>,
+) {
+    let time = (time.elapsed_seconds_f64() % spin_settings.get_loop_duration() as f64) as f32;
+    let spin = spin_settings.rotation_at(time);
+
+    for mut transform in query.iter_mut() {
+        transform.rotation = view_rotation.0 * spin;
     }
 }
@@ -0,0 +1,37 @@
+use serde::{Deserialize, Serialize};
+
+use crate::settings::asteroid_settings::AsteroidSettings;
+use crate::settings::spin_settings::SpinSettings;
+
+/// Everything needed to regenerate an exported asteroid, stored in the glTF asset `extras`.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct AsteroidMetadata {
+    pub generator: String,
+    pub version: String,
+    pub seed: u64,
+    pub resolution: u32,
+    pub settings: AsteroidSettings,
+    /// Spin of the viewer at export time, missing in files from before it was stored and in batch exports.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub spin: Option<SpinSettings>,
+}
+
+impl AsteroidMetadata {
+    pub const GENERATOR: &'static str = "AstroGen";
+
+    pub fn new(settings: &AsteroidSettings, seed: u64, resolution: u32) -> Self {
+        AsteroidMetadata {
+            generator: Self::GENERATOR.to_string(),
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            seed,
+            resolution,
+            settings: settings.clone(),
+            spin: None,
+        }
+    }
+
+    pub fn with_spin(mut self, spin: &SpinSettings) -> Self {
+        self.spin = Some(spin.clone());
+        self
+    }
+}
@@ -0,0 +1,387 @@
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use bevy::reflect::GetPath;
+use serde::Serialize;
+
+use crate::asteroid_metadata::AsteroidMetadata;
+use crate::collider::build_colliders;
+use crate::export_error::{io_error, ExportError};
+use crate::export_mesh::{ExportFormat, ExportMesh};
+use crate::gltf_exporter;
+use crate::generator::{generate_with_sphere, AsteroidMesh};
+use crate::mass_properties::MassProperties;
+use crate::mesh_quality::check_export_mesh;
+use crate::obj_exporter::write_obj;
+use crate::ply_exporter::{write_ply, PlyEncoding};
+use crate::settings::asteroid_settings::AsteroidSettings;
+use crate::settings::batch_settings::{BatchSettings, GridAxis, ManifestFormat};
+use crate::settings::export_settings::ExportSettings;
+use crate::settings::mass_settings::MassSettings;
+use crate::settings::mesh_quality_settings::{ExportQualityAction, MeshQualitySettings};
+use crate::settings::scale_settings::{LengthUnit, ScaleSettings};
+use crate::sphere_mesh::SphereMesh;
+use crate::usd_exporter::write_usda;
+
+/// One exported asteroid in the manifest.
+#[derive(Serialize)]
+pub struct ManifestEntry {
+    /// `None` when mesh quality blocked the export.
+    file: Option<String>,
+    seed: u64,
+    settings_hash: String,
+    /// Grid values applied on top of the base settings, by reflection path.
+    parameters: BTreeMap<String, f32>,
+    mass_properties: MassProperties,
+    /// Mesh quality notes of the export or the reason it was blocked, empty when the mesh is clean.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    mesh_quality: Vec<String>,
+}
+
+/// Everything a batch needs besides the batch settings, copied out of the app or parsed from the CLI.
+#[derive(Clone, Default)]
+pub struct BatchInputs {
+    pub settings: AsteroidSettings,
+    pub export_settings: ExportSettings,
+    pub scale_settings: ScaleSettings,
+    pub mass_settings: MassSettings,
+    pub quality_settings: MeshQualitySettings,
+}
+
+/// One seed and grid combination of a batch and the file it is written to.
+pub struct BatchItem {
+    pub seed: u64,
+    pub settings: AsteroidSettings,
+    parameters: BTreeMap<String, f32>,
+    path: PathBuf,
+}
+
+/// Generates and exports every seed and grid combination on the CPU, then writes the manifest.
+/// `progress` is called with the number of finished asteroids and the total. Returns the manifest path.
+pub fn run_batch(
+    batch_settings: &BatchSettings,
+    inputs: &BatchInputs,
+    progress: impl Fn(usize, usize),
+) -> Result<PathBuf, ExportError> {
+    let items = get_batch_items(batch_settings, inputs)?;
+    let sphere_mesh = SphereMesh::new(batch_settings.resolution as usize);
+    let mut entries = Vec::with_capacity(items.len());
+
+    for item in &items {
+        let asteroid_mesh = generate_with_sphere(&item.settings, item.seed, &sphere_mesh);
+        entries.push(export_batch_item(item, &asteroid_mesh, batch_settings, inputs)?);
+        progress(entries.len(), items.len());
+    }
+
+    write_manifest(batch_settings, &entries)
+}
+
+/// Creates the output directory and lists every seed and grid combination, seed by seed.
+pub fn get_batch_items(batch_settings: &BatchSettings, inputs: &BatchInputs) -> Result<Vec<BatchItem>, ExportError> {
+    let output_dir = Path::new(&batch_settings.output_dir);
+    fs::create_dir_all(output_dir).map_err(io_error(output_dir))?;
+
+    // Resolve every path up front so a typo fails before anything is written
+    let mut check = inputs.settings.clone();
+    for axis in &batch_settings.grid {
+        check
+            .path_mut::<f32>(axis.path.as_str())
+            .map_err(|_| ExportError::InvalidSetting(axis.path.clone()))?;
+    }
+
+    let combinations = batch_settings.get_grid_combinations();
+    let mut items = Vec::with_capacity(batch_settings.get_total());
+    for seed in (0..batch_settings.count as u64).map(|i| batch_settings.seed_start.wrapping_add(i)) {
+        for (combination_index, combination) in combinations.iter().enumerate() {
+            let mut settings = inputs.settings.clone();
+            let mut parameters = BTreeMap::new();
+            for (axis, &value) in batch_settings.grid.iter().zip(combination) {
+                if let Ok(field) = settings.path_mut::<f32>(axis.path.as_str()) {
+                    *field = value;
+                }
+                parameters.insert(axis.path.clone(), value);
+            }
+
+            let file_name = if batch_settings.grid.is_empty() {
+                format!("asteroid_{}", seed)
+            } else {
+                format!("asteroid_{}_{:03}", seed, combination_index)
+            };
+            let path = output_dir.join(file_name).with_extension(inputs.export_settings.get_extension(batch_settings.format));
+
+            items.push(BatchItem {
+                seed,
+                settings,
+                parameters,
+                path,
+            });
+        }
+    }
+    Ok(items)
+}
+
+/// Checks and writes the generated mesh of one item. A mesh blocked by `MeshQualitySettings::on_export` is
+/// not written but still gets an entry, so one bad seed doesn't abort the whole batch.
+pub fn export_batch_item(
+    item: &BatchItem,
+    asteroid_mesh: &AsteroidMesh,
+    batch_settings: &BatchSettings,
+    inputs: &BatchInputs,
+) -> Result<ManifestEntry, ExportError> {
+    let mut export_mesh = asteroid_mesh.to_export_mesh();
+    let quality = check_export_mesh(&mut export_mesh, &inputs.quality_settings);
+    let mass_properties = export_mesh
+        .get_mass_properties(inputs.mass_settings.density)
+        .scaled(inputs.scale_settings.get_scale_in_metres());
+
+    let (file, mesh_quality) = match quality {
+        Ok(notes) => {
+            let metadata = AsteroidMetadata::new(&item.settings, item.seed, batch_settings.resolution);
+            write_asteroid(batch_settings.format, export_mesh, &mass_properties, &metadata, inputs, &item.path)?;
+            let file = item.path.file_name().map(|name| name.to_string_lossy().into_owned()).unwrap_or_default();
+            (Some(file), notes)
+        }
+        Err(err @ ExportError::PoorMeshQuality(_)) => (None, vec![err.to_string()]),
+        Err(err) => return Err(err),
+    };
+
+    Ok(ManifestEntry {
+        file,
+        seed: item.seed,
+        settings_hash: get_settings_hash(&item.settings),
+        parameters: item.parameters.clone(),
+        mass_properties,
+        mesh_quality,
+    })
+}
+
+/// Writes the manifest next to the exported files and returns its path.
+pub fn write_manifest(batch_settings: &BatchSettings, entries: &[ManifestEntry]) -> Result<PathBuf, ExportError> {
+    let manifest_path = Path::new(&batch_settings.output_dir)
+        .join("manifest")
+        .with_extension(batch_settings.manifest.extension());
+    let manifest = match batch_settings.manifest {
+        ManifestFormat::Json => serde_json::to_string_pretty(entries).map_err(|err| ExportError::Encoding(err.to_string()))?,
+        ManifestFormat::Csv => to_csv(entries, &batch_settings.grid),
+    };
+    fs::write(&manifest_path, manifest).map_err(io_error(&manifest_path))?;
+    Ok(manifest_path)
+}
+
+/// Writes one asteroid with the same conventions as the interactive exporters. `export_mesh` has already been
+/// through `check_export_mesh`.
+fn write_asteroid(
+    format: ExportFormat,
+    mut export_mesh: ExportMesh,
+    mass_properties: &MassProperties,
+    metadata: &AsteroidMetadata,
+    inputs: &BatchInputs,
+    path: &Path,
+) -> Result<(), ExportError> {
+    let scale_settings = &inputs.scale_settings;
+    let settings = &metadata.settings;
+    let seed = metadata.seed;
+    match format {
+        ExportFormat::Glb => {
+            export_mesh.scale(scale_settings.get_scale_in_metres());
+            let collider_settings = &inputs.export_settings.collider_settings;
+            let colliders = build_colliders(&export_mesh.positions, &export_mesh.indices, collider_settings);
+            gltf_exporter::export(
+                Some(&export_mesh),
+                Some(mass_properties),
+                None,
+                &colliders,
+                metadata,
+                &inputs.export_settings,
+                path,
+            )?;
+        }
+        ExportFormat::Obj => {
+            export_mesh.scale(scale_settings.get_scale());
+            export_mesh.convert_axes(scale_settings);
+            write_obj(&export_mesh, path, &path.with_extension("mtl"), &settings.material_settings, scale_settings)
+                .map_err(io_error(path))?;
+        }
+        ExportFormat::PlyBinary | ExportFormat::PlyAscii => {
+            let encoding = match format {
+                ExportFormat::PlyAscii => PlyEncoding::Ascii,
+                _ => PlyEncoding::BinaryLittleEndian,
+            };
+            export_mesh.scale(scale_settings.get_scale());
+            export_mesh.convert_axes(scale_settings);
+            write_ply(&export_mesh, path, encoding, settings.material_settings.get_base_color(), scale_settings)
+                .map_err(io_error(path))?;
+        }
+        ExportFormat::Usda => {
+            export_mesh.scale(scale_settings.get_scale());
+            write_usda(&export_mesh, path, settings, scale_settings, seed).map_err(io_error(path))?;
+        }
+        _ => {
+            return Err(ExportError::Encoding(format!("{} is not supported in batch mode", format.label())));
+        }
+    }
+    Ok(())
+}
+
+/// FNV-1a over the serialized settings, stable across runs and platforms unlike `DefaultHasher`.
+pub fn get_settings_hash(settings: &AsteroidSettings) -> String {
+    let json = serde_json::to_string(settings).unwrap_or_default();
+    let hash = json.bytes().fold(0xcbf2_9ce4_8422_2325u64, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(0x0100_0000_01b3)
+    });
+    format!("{:016x}", hash)
+}
+
+fn to_csv(entries: &[ManifestEntry], grid: &[GridAxis]) -> String {
+    let mut csv = String::from("file,seed,settings_hash");
+    for axis in grid {
+        let _ = write!(csv, ",{}", axis.path);
+    }
+    csv.push_str(",volume,surface_area,mass,center_of_mass_x,center_of_mass_y,center_of_mass_z,inertia_xx,inertia_yy,inertia_zz,mesh_quality\n");
+
+    for entry in entries {
+        let _ = write!(csv, "{},{},{}", entry.file.as_deref().unwrap_or_default(), entry.seed, entry.settings_hash);
+        for axis in grid {
+            let _ = write!(csv, ",{}", entry.parameters.get(&axis.path).copied().unwrap_or_default());
+        }
+        let properties = &entry.mass_properties;
+        let [x, y, z] = properties.center_of_mass;
+        let inertia = properties.inertia_tensor;
+        let _ = writeln!(
+            csv,
+            ",{},{},{},{},{},{},{},{},{},\"{}\"",
+            properties.volume,
+            properties.surface_area,
+            properties.mass,
+            x,
+            y,
+            z,
+            inertia[0][0],
+            inertia[1][1],
+            inertia[2][2],
+            entry.mesh_quality.join("; ")
+        );
+    }
+    csv
+}
+
+const USAGE: &str = "Usage: astrogen batch [options]
+  --out DIR              output directory (default exports/batch)
+  --format FORMAT        glb, obj, ply or usda (default glb)
+  --manifest FORMAT      json or csv (default json)
+  --seed-start N         first seed (default 0)
+  --count N              number of seeds (default 10)
+  --grid PATH=MIN:MAX:STEPS
+                         sweep a setting, e.g. simple_noise_settings.scale=0.5:1.5:3, repeatable
+  --resolution N         sphere mesh subdivisions (default 100)
+  --from FILE            start from the settings stored in an exported glTF
+  --radius R             body radius (default 1)
+  --unit m|km            length unit of the radius (default m)
+  --density D            density in kg/m³ (default 2000)
+  --mesh-quality ACTION  warn, block or repair meshes with folded triangles (default warn),
+                         blocked asteroids stay in the manifest without a file";
+
+/// Runs a batch from the command line without opening a window. Returns the process exit code.
+pub fn run_cli(args: &[String]) -> i32 {
+    let (batch_settings, inputs) = match parse_args(args) {
+        Ok(parsed) => parsed,
+        Err(err) => {
+            eprintln!("{}\n\n{}", err, USAGE);
+            return 2;
+        }
+    };
+
+    let result = run_batch(&batch_settings, &inputs, |done, total| {
+        println!("[{}/{}]", done, total);
+    });
+    match result {
+        Ok(manifest_path) => {
+            println!("Manifest written to {}", manifest_path.display());
+            0
+        }
+        Err(err) => {
+            eprintln!("Batch failed: {}", err);
+            1
+        }
+    }
+}
+
+fn parse_args(args: &[String]) -> Result<(BatchSettings, BatchInputs), String> {
+    let mut batch_settings = BatchSettings::default();
+    let mut inputs = BatchInputs::default();
+    let mut args = args.iter();
+
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().cloned().ok_or_else(|| format!("Missing value for {}", arg));
+        match arg.as_str() {
+            "--out" => batch_settings.output_dir = value()?,
+            "--format" => {
+                batch_settings.format = match value()?.as_str() {
+                    "glb" => ExportFormat::Glb,
+                    "obj" => ExportFormat::Obj,
+                    "ply" => ExportFormat::PlyBinary,
+                    "usda" => ExportFormat::Usda,
+                    other => return Err(format!("Unknown format {}", other)),
+                }
+            }
+            "--manifest" => {
+                batch_settings.manifest = match value()?.as_str() {
+                    "json" => ManifestFormat::Json,
+                    "csv" => ManifestFormat::Csv,
+                    other => return Err(format!("Unknown manifest format {}", other)),
+                }
+            }
+            "--seed-start" => batch_settings.seed_start = parse_number(&value()?)?,
+            "--count" => batch_settings.count = parse_number(&value()?)?,
+            "--resolution" => batch_settings.resolution = parse_number(&value()?)?,
+            "--grid" => batch_settings.grid.push(parse_grid_axis(&value()?)?),
+            "--from" => {
+                let path = PathBuf::from(value()?);
+                inputs.settings = crate::gltf_importer::read_metadata(&path)
+                    .map_err(|err| format!("Could not read {}: {}", path.display(), err))?
+                    .settings;
+            }
+            "--radius" => inputs.scale_settings.body_radius = parse_number(&value()?)?,
+            "--unit" => {
+                inputs.scale_settings.unit = match value()?.as_str() {
+                    "m" => LengthUnit::Metres,
+                    "km" => LengthUnit::Kilometres,
+                    other => return Err(format!("Unknown unit {}", other)),
+                }
+            }
+            "--density" => inputs.mass_settings.density = parse_number(&value()?)?,
+            "--mesh-quality" => {
+                inputs.quality_settings.on_export = match value()?.as_str() {
+                    "warn" => ExportQualityAction::Warn,
+                    "block" => ExportQualityAction::Block,
+                    "repair" => ExportQualityAction::Repair,
+                    other => return Err(format!("Unknown mesh quality action {}", other)),
+                }
+            }
+            other => return Err(format!("Unknown option {}", other)),
+        }
+    }
+
+    Ok((batch_settings, inputs))
+}
+
+fn parse_number<T: std::str::FromStr>(value: &str) -> Result<T, String> {
+    value.parse().map_err(|_| format!("Invalid number {}", value))
+}
+
+/// `path=min:max:steps`
+fn parse_grid_axis(value: &str) -> Result<GridAxis, String> {
+    let (path, range) = value.split_once('=').ok_or_else(|| format!("Invalid grid {}, expected PATH=MIN:MAX:STEPS", value))?;
+    let parts: Vec<&str> = range.split(':').collect();
+    let [min, max, steps] = parts[..] else {
+        return Err(format!("Invalid grid {}, expected PATH=MIN:MAX:STEPS", value));
+    };
+    Ok(GridAxis {
+        path: path.to_string(),
+        min: parse_number(min)?,
+        max: parse_number(max)?,
+        steps: parse_number(steps)?,
+    })
+}
@@ -0,0 +1,247 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use bevy::prelude::*;
+use bevy::tasks::{block_on, futures_lite::future, AsyncComputeTaskPool, Task};
+use bevy_egui::{egui, EguiContexts};
+
+use crate::batch::{export_batch_item, get_batch_items, write_manifest, BatchInputs, BatchItem, ManifestEntry};
+use crate::export_error::ExportError;
+use crate::generation_jobs::{GenerationJobFinished, GenerationJobs, GenerationJobsPlugin, JobId};
+use crate::settings::asteroid_settings::AsteroidSettings;
+use crate::settings::batch_settings::{BatchSettings, GridAxis, ManifestFormat};
+use crate::settings::export_settings::ExportSettings;
+use crate::settings::mass_settings::MassSettings;
+use crate::settings::mesh_quality_settings::MeshQualitySettings;
+use crate::settings::scale_settings::ScaleSettings;
+
+/// Generates the batch items through `GenerationJobs`, which is added with its defaults unless the app added it
+/// first, and exports each one in the background as soon as its mesh arrives.
+pub struct BatchPlugin;
+
+impl Plugin for BatchPlugin {
+    fn build(&self, app: &mut App) {
+        if !app.is_plugin_added::<GenerationJobsPlugin>() {
+            app.add_plugins(GenerationJobsPlugin::default());
+        }
+
+        app.insert_resource(BatchSettings::default())
+            .insert_resource(BatchStatus::default())
+            .init_resource::<ExportSettings>()
+            .init_resource::<ScaleSettings>()
+            .init_resource::<MassSettings>()
+            .init_resource::<MeshQualitySettings>()
+            .observe(start_batch)
+            .observe(export_batch_asteroid)
+            .add_systems(Update, (poll_batch, show_batch_ui));
+    }
+}
+
+#[derive(Event)]
+pub struct RunBatchClicked;
+
+/// The running batch and the outcome of the last one.
+#[derive(Resource, Default)]
+pub struct BatchStatus {
+    run: Option<BatchRun>,
+    message: String,
+}
+
+struct BatchRun {
+    batch_settings: Arc<BatchSettings>,
+    inputs: Arc<BatchInputs>,
+    /// Items still being generated, with their index in `entries`.
+    jobs: HashMap<JobId, (usize, BatchItem)>,
+    exports: Vec<(usize, Task<Result<ManifestEntry, ExportError>>)>,
+    /// In item order, so the manifest matches the CLI's.
+    entries: Vec<Option<ManifestEntry>>,
+}
+
+fn start_batch(
+    _trigger: Trigger<RunBatchClicked>,
+    mut status: ResMut<BatchStatus>,
+    mut jobs: ResMut<GenerationJobs>,
+    batch_settings: Res<BatchSettings>,
+    settings: Res<AsteroidSettings>,
+    export_settings: Res<ExportSettings>,
+    scale_settings: Res<ScaleSettings>,
+    mass_settings: Res<MassSettings>,
+    quality_settings: Res<MeshQualitySettings>,
+) {
+    if status.run.is_some() {
+        return;
+    }
+
+    let inputs = BatchInputs {
+        settings: settings.clone(),
+        export_settings: export_settings.clone(),
+        scale_settings: scale_settings.clone(),
+        mass_settings: mass_settings.clone(),
+        quality_settings: quality_settings.clone(),
+    };
+    status.message.clear();
+    let items = match get_batch_items(&batch_settings, &inputs) {
+        Ok(items) => items,
+        Err(err) => {
+            status.message = format!("Batch failed: {}", err);
+            return;
+        }
+    };
+
+    let entries = items.iter().map(|_| None).collect();
+    let item_jobs = items
+        .into_iter()
+        .enumerate()
+        .map(|(index, item)| (jobs.submit(item.settings.clone(), item.seed, batch_settings.resolution), (index, item)))
+        .collect();
+    status.run = Some(BatchRun {
+        batch_settings: Arc::new(batch_settings.clone()),
+        inputs: Arc::new(inputs),
+        jobs: item_jobs,
+        exports: Vec::new(),
+        entries,
+    });
+}
+
+fn export_batch_asteroid(trigger: Trigger<GenerationJobFinished>, mut status: ResMut<BatchStatus>) {
+    let ev = trigger.event();
+    let Some(run) = status.run.as_mut() else {
+        return;
+    };
+    let Some((index, item)) = run.jobs.remove(&ev.id) else {
+        return;
+    };
+
+    let asteroid_mesh = ev.mesh.clone();
+    let batch_settings = run.batch_settings.clone();
+    let inputs = run.inputs.clone();
+    let task = AsyncComputeTaskPool::get()
+        .spawn(async move { export_batch_item(&item, &asteroid_mesh, &batch_settings, &inputs) });
+    run.exports.push((index, task));
+}
+
+fn poll_batch(mut status: ResMut<BatchStatus>, mut jobs: ResMut<GenerationJobs>) {
+    let Some(run) = status.run.as_mut() else {
+        return;
+    };
+
+    let mut failed = None;
+    run.exports.retain_mut(|(index, task)| match block_on(future::poll_once(task)) {
+        Some(Ok(entry)) => {
+            run.entries[*index] = Some(entry);
+            false
+        }
+        Some(Err(err)) => {
+            failed = Some(err);
+            false
+        }
+        None => true,
+    });
+
+    let message = if let Some(err) = failed {
+        for id in run.jobs.keys() {
+            jobs.cancel(*id);
+        }
+        format!("Batch failed: {}", err)
+    } else if run.jobs.is_empty() && run.exports.is_empty() {
+        let entries: Vec<ManifestEntry> = run.entries.drain(..).flatten().collect();
+        match write_manifest(&run.batch_settings, &entries) {
+            Ok(manifest_path) => format!("Batch done, manifest written to {}", manifest_path.display()),
+            Err(err) => format!("Batch failed: {}", err),
+        }
+    } else {
+        return;
+    };
+
+    // Dropping the run drops the export tasks still in flight
+    status.run = None;
+    status.message = message;
+    println!("{}", status.message);
+}
+
+fn show_batch_ui(
+    mut contexts: EguiContexts,
+    mut commands: Commands,
+    mut batch_settings: ResMut<BatchSettings>,
+    status: Res<BatchStatus>,
+    jobs: Res<GenerationJobs>,
+) {
+    let Some(ctx) = contexts.try_ctx_mut() else {
+        return;
+    };
+
+    egui::Window::new("Batch")
+        .default_open(false)
+        .default_width(420.)
+        .show(ctx, |ui| {
+            ui.horizontal(|ui| {
+                ui.label("Output directory:");
+                ui.text_edit_singleline(&mut batch_settings.output_dir);
+            });
+            ui.horizontal(|ui| {
+                ui.label("Format:");
+                egui::ComboBox::from_id_source("batch_format")
+                    .selected_text(batch_settings.format.label())
+                    .show_ui(ui, |ui| {
+                        for format in BatchSettings::FORMATS {
+                            ui.selectable_value(&mut batch_settings.format, format, format.label());
+                        }
+                    });
+                ui.label("Manifest:");
+                ui.radio_value(&mut batch_settings.manifest, ManifestFormat::Json, "JSON");
+                ui.radio_value(&mut batch_settings.manifest, ManifestFormat::Csv, "CSV");
+            });
+            ui.horizontal(|ui| {
+                ui.label("First seed:");
+                ui.add(egui::DragValue::new(&mut batch_settings.seed_start));
+                ui.label("Seeds:");
+                ui.add(egui::DragValue::new(&mut batch_settings.count).range(1..=100_000));
+                ui.label("Resolution:");
+                ui.add(egui::DragValue::new(&mut batch_settings.resolution).range(4..=400));
+            });
+
+            ui.label("Parameter grid:");
+            let mut removed = None;
+            for (index, axis) in batch_settings.grid.iter_mut().enumerate() {
+                ui.horizontal(|ui| {
+                    ui.add(egui::TextEdit::singleline(&mut axis.path).desired_width(180.));
+                    ui.add(egui::DragValue::new(&mut axis.min).speed(0.01));
+                    ui.label("to");
+                    ui.add(egui::DragValue::new(&mut axis.max).speed(0.01));
+                    ui.add(egui::DragValue::new(&mut axis.steps).range(1..=100).suffix(" steps"));
+                    if ui.button("✖").clicked() {
+                        removed = Some(index);
+                    }
+                });
+            }
+            if let Some(index) = removed {
+                batch_settings.grid.remove(index);
+            }
+            if ui.button("Add parameter").clicked() {
+                batch_settings.grid.push(GridAxis {
+                    path: "simple_noise_settings.scale".to_string(),
+                    min: 0.5,
+                    max: 1.0,
+                    steps: 3,
+                });
+            }
+
+            ui.separator();
+            let running = status.run.is_some();
+            ui.horizontal(|ui| {
+                if ui.add_enabled(!running, egui::Button::new("Run batch")).clicked() {
+                    commands.trigger(RunBatchClicked);
+                }
+                ui.label(format!("{} asteroids", batch_settings.get_total()));
+            });
+            if running {
+                let progress = jobs.get_progress();
+                ui.add(
+                    egui::ProgressBar::new(progress.get_fraction())
+                        .text(format!("{} / {} generated", progress.finished, progress.submitted)),
+                );
+            } else if !status.message.is_empty() {
+                ui.label(&status.message);
+            }
+        });
+}
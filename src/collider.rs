@@ -0,0 +1,54 @@
+use parry3d::na::Point3;
+use parry3d::transformation::convex_hull;
+use parry3d::transformation::vhacd::{VHACDParameters, VHACD};
+
+use crate::settings::collider_settings::ColliderSettings;
+
+/// Suffix that marks a node as collision geometry.
+pub const COLLIDER_SUFFIX: &str = "_collider";
+
+/// A single convex collision shape.
+pub struct ColliderMesh {
+    pub name: String,
+    pub positions: Vec<[f32; 3]>,
+    pub indices: Vec<u32>,
+}
+
+/// Builds the convex hull and the approximate convex decomposition requested in `settings`.
+pub fn build_colliders(positions: &[[f32; 3]], indices: &[u32], settings: &ColliderSettings) -> Vec<ColliderMesh> {
+    let points: Vec<Point3<f32>> = positions.iter().map(|&[x, y, z]| Point3::new(x, y, z)).collect();
+    let mut colliders = Vec::new();
+
+    if settings.convex_hull {
+        let (vertices, triangles) = convex_hull(&points);
+        colliders.push(to_collider_mesh(format!("asteroid_hull{}", COLLIDER_SUFFIX), &vertices, &triangles));
+    }
+
+    if settings.convex_decomposition {
+        let triangles: Vec<[u32; 3]> = indices.chunks_exact(3).map(|t| [t[0], t[1], t[2]]).collect();
+        let params = VHACDParameters {
+            max_convex_hulls: settings.max_convex_hulls.max(1),
+            resolution: settings.resolution.max(8),
+            ..Default::default()
+        };
+        let decomposition = VHACD::decompose(&params, &points, &triangles, false);
+
+        for (i, (vertices, triangles)) in decomposition
+            .compute_convex_hulls(params.convex_hull_downsampling)
+            .iter()
+            .enumerate()
+        {
+            colliders.push(to_collider_mesh(format!("asteroid_part_{:02}{}", i, COLLIDER_SUFFIX), vertices, triangles));
+        }
+    }
+
+    colliders
+}
+
+fn to_collider_mesh(name: String, vertices: &[Point3<f32>], triangles: &[[u32; 3]]) -> ColliderMesh {
+    ColliderMesh {
+        name,
+        positions: vertices.iter().map(|p| [p.x, p.y, p.z]).collect(),
+        indices: triangles.iter().flatten().copied().collect(),
+    }
+}
@@ -1,21 +1,33 @@
 use bevy::math::Vec3;
-use bevy::prelude::{App, Commands, Plugin, Res, ResMut, Resource, Trigger, Update, World};
+use bevy::prelude::{default, App, Commands, Plugin, PostUpdate, Res, ResMut, Resource, Trigger, World};
 use bevy::render::render_resource::ShaderType;
-use bevy_easy_compute::prelude::{
-    AppComputeWorker, AppComputeWorkerBuilder, AppComputeWorkerPlugin, ComputeWorker,
-};
+use bevy_easy_compute::prelude::{AppComputeWorker, AppComputeWorkerBuilder, ComputeWorker};
 use bytemuck::{Pod, Zeroable};
+use crate::crater_grid::{CraterGrid, CRATER_GRID_CAPACITY};
 use crate::compute_shaders::{AsteroidShapeComputeShader, NormalComputeShader, NormalizeNormalComputeShader};
-use crate::compute_events::{CraterSettingsChanged, MeshDataAfterCompute, PerturbStrengthChanged, RidgeNoiseSettingsChanged, SimpleNoiseSettingsChanged};
+use crate::compute_events::{CraterSettingsChanged, MeshDataAfterCompute, PerturbStrengthChanged, RidgeNoiseSettingsChanged, SimpleNoiseSettingsChanged, ValueChanged};
+use crate::generation_jobs::{GenerationJobFinished, GenerationJobs, GenerationJobsPlugin, JobId};
 use crate::RngSeed;
 use crate::settings::asteroid_settings::AsteroidSettings;
 use crate::settings::crater_settings::{Crater, MAX_CRATER};
 use crate::sphere_mesh::SphereMesh;
 use crate::utils::PRNG;
 
+/// Regenerates the displayed asteroid through `GenerationJobs` whenever a `*Changed` event arrives.
+///
+/// Adds `GenerationJobsPlugin` with `gpu_resolution` at `SPHERE_RESOLUTION` unless the app added it first,
+/// so add this after `AppComputePlugin`.
 pub struct ComputePlugin;
-#[derive(Resource)]
-pub struct AsteroidComputeWorker;
+
+pub const SPHERE_RESOLUTION: usize = 400;
+
+/// Height layers cached by the shape shader, a run only re-evaluates the layers set in `dirty_layers`.
+pub const LAYER_CRATERS: u32 = 1;
+pub const LAYER_SHAPE_NOISE: u32 = 2;
+pub const LAYER_RIDGE_NOISE: u32 = 4;
+pub const LAYER_RIDGE_NOISE2: u32 = 8;
+pub const LAYER_PERTURB: u32 = 16;
+pub const ALL_LAYERS: u32 = 31;
 
 #[repr(C)]
 #[derive(ShaderType, Clone, Default, Copy, Pod, Zeroable)]
@@ -27,176 +39,262 @@ pub struct NormalAccumulator {
 
 impl Plugin for ComputePlugin {
     fn build(&self, app: &mut App) {
-        app.add_plugins(AppComputeWorkerPlugin::<AsteroidComputeWorker>::default())
-            .insert_resource(AsteroidSettings::default())
+        if !app.is_plugin_added::<GenerationJobsPlugin>() {
+            app.add_plugins(GenerationJobsPlugin {
+                gpu_resolution: SPHERE_RESOLUTION as u32,
+                ..default()
+            });
+        }
+
+        app.insert_resource(AsteroidSettings::default())
             .add_event::<MeshDataAfterCompute>()
             .add_event::<PerturbStrengthChanged>()
             .add_event::<CraterSettingsChanged>()
             .add_event::<SimpleNoiseSettingsChanged>()
             .add_event::<RidgeNoiseSettingsChanged>()
-            .observe(send_perturb_strength_data)
-            .observe(send_crater_settings_data)
-            .observe(send_simple_noise_settings_data)
-            .observe(send_ridge_noise_settings_data)
-            .add_systems(Update, receive_data_after_compute);
+            .insert_resource(ViewerJob::default())
+            .insert_resource(ValueChanged::default())
+            .observe(collect_perturb_strength)
+            .observe(collect_crater_settings)
+            .observe(collect_simple_noise_settings)
+            .observe(collect_ridge_noise_settings)
+            .observe(receive_viewer_asteroid)
+            .add_systems(PostUpdate, submit_viewer_job);
     }
 }
 
-impl ComputeWorker for AsteroidComputeWorker {
-    fn build(world: &mut World) -> AppComputeWorker<Self> {
-        const SPHERE_RESOLUTION: usize = 400;
-        const WORKGROUP_SIZE: u32 = 64; // This should match @workgroup_size in the shader
-        const NUM_NOISE_PARAMS: usize = 3;
-
-        let sphere_mesh = SphereMesh::new(SPHERE_RESOLUTION);
-        let vertex_count = sphere_mesh.vertices.len();
-        let noise_params: Vec<[f32; 4]> = vec![[0.0; 4]; NUM_NOISE_PARAMS];
-        let indices_len = sphere_mesh.indices.len();
-        let num_triangles = indices_len / 3;
-
-        let num_workgroups = (vertex_count + WORKGROUP_SIZE as usize - 1) / WORKGROUP_SIZE as usize;
-        let num_workgroups_normal = (num_triangles + WORKGROUP_SIZE as usize - 1) / WORKGROUP_SIZE as usize;
-
-        let worker = AppComputeWorkerBuilder::new(world)
-            .add_storage("vertices", &sphere_mesh.vertices)
-            .add_staging("normals", &vec![Vec3::ZERO; vertex_count])
-            .add_storage("indices", &sphere_mesh.indices)
-            .add_uniform("num_vertices", &(vertex_count as u32))
-            .add_uniform("num_triangles", &(num_triangles as u32))
-            .add_staging("new_vertices", &vec![Vec3::ZERO; vertex_count])
-            .add_storage("noise_params_shape", &noise_params)
-            .add_storage("noise_params_ridge", &noise_params)
-            .add_storage("noise_params_ridge2", &noise_params)
-            .add_uniform("max_strength", &0.)
-            .add_uniform("num_craters", &0)
-            .add_uniform("rim_steepness", &0.0)
-            .add_uniform("rim_width", &0.0)
-            .add_storage("craters", &[Crater::default(); MAX_CRATER])
-            .add_staging(
+/// Builds the shape, normal and normalize passes over `sphere_mesh`. Any worker type can use it,
+/// each type gets its own buffers sized for its sphere.
+pub fn build_asteroid_worker<W: ComputeWorker>(world: &mut World, sphere_mesh: &SphereMesh) -> AppComputeWorker<W> {
+    const WORKGROUP_SIZE: u32 = 64; // This should match @workgroup_size in the shader
+    const NUM_NOISE_PARAMS: usize = 3;
+
+    let vertex_count = sphere_mesh.vertices.len();
+    let noise_params = [[0.0f32; 4]; NUM_NOISE_PARAMS];
+    let indices_len = sphere_mesh.indices.len();
+    let num_triangles = indices_len / 3;
+
+    let num_workgroups = (vertex_count + WORKGROUP_SIZE as usize - 1) / WORKGROUP_SIZE as usize;
+    let num_workgroups_normal = (num_triangles + WORKGROUP_SIZE as usize - 1) / WORKGROUP_SIZE as usize;
+
+    AppComputeWorkerBuilder::new(world)
+        .add_storage("vertices", &sphere_mesh.vertices)
+        .add_staging("normals", &vec![Vec3::ZERO; vertex_count])
+        .add_storage("indices", &sphere_mesh.indices)
+        .add_uniform("num_vertices", &(vertex_count as u32))
+        .add_uniform("num_triangles", &(num_triangles as u32))
+        .add_staging("new_vertices", &vec![Vec3::ZERO; vertex_count])
+        .add_uniform("noise_params_shape", &noise_params)
+        .add_uniform("noise_params_ridge", &noise_params)
+        .add_uniform("noise_params_ridge2", &noise_params)
+        .add_uniform("max_strength", &0.)
+        .add_uniform("num_craters", &0)
+        .add_uniform("rim_steepness", &0.0)
+        .add_uniform("rim_width", &0.0)
+        .add_storage("craters", &vec![Crater::default(); MAX_CRATER])
+        .add_storage("layer_cache", &vec![[0.0f32; 4]; 2 * vertex_count])
+        .add_uniform("dirty_layers", &ALL_LAYERS)
+        .add_storage("crater_grid", &vec![0u32; CRATER_GRID_CAPACITY])
+        .add_staging(
+            "normal_accumulators",
+            &vec![NormalAccumulator::default(); vertex_count],
+        )
+        .add_pass::<AsteroidShapeComputeShader>(
+            [num_workgroups as u32, 1, 1],
+            &[
+                "vertices",
+                "new_vertices",
+                "num_vertices",
+                "noise_params_shape",
+                "noise_params_ridge",
+                "noise_params_ridge2",
+                "normal_accumulators",
+                "max_strength",
+                "num_craters",
+                "rim_steepness",
+                "rim_width",
+                "craters",
+                "layer_cache",
+                "dirty_layers",
+                "crater_grid",
+            ],
+        )
+        .add_pass::<NormalComputeShader>(
+            [num_workgroups_normal as u32, 1, 1],
+            &[
+                "new_vertices",
+                "indices",
+                "normal_accumulators",
+                "num_triangles",
+            ],
+        )
+        .add_pass::<NormalizeNormalComputeShader>(
+            [num_workgroups as u32, 1, 1],
+            &[
                 "normal_accumulators",
-                &vec![NormalAccumulator::default(); vertex_count],
-            )
-            .add_pass::<AsteroidShapeComputeShader>(
-                [num_workgroups as u32, 1, 1],
-                &[
-                    "vertices",
-                    "new_vertices",
-                    "num_vertices",
-                    "noise_params_shape",
-                    "noise_params_ridge",
-                    "noise_params_ridge2",
-                    "normal_accumulators",
-                    "max_strength",
-                    "num_craters",
-                    "rim_steepness",
-                    "rim_width",
-                    "craters",
-                ],
-            )
-            .add_pass::<NormalComputeShader>(
-                [num_workgroups_normal as u32, 1, 1],
-                &[
-                    "new_vertices",
-                    "indices",
-                    "normal_accumulators",
-                    "num_triangles",
-                ],
-            )
-            .add_pass::<NormalizeNormalComputeShader>(
-                [num_workgroups as u32, 1, 1],
-                &[
-                    "normal_accumulators",
-                    "num_vertices",
-                    "normals"
-                ],
-            )
-            .one_shot()
-            .build();
-
-        world.insert_resource(sphere_mesh);
-        worker
+                "num_vertices",
+                "normals"
+            ],
+        )
+        .one_shot()
+        .build()
+}
+
+/// Writes the parameters of the layers in `dirty_layers`, the shader keeps the cached heights of the others.
+/// `settings` must be sanitized.
+pub fn write_asteroid_settings<W: ComputeWorker>(
+    compute_worker: &mut AppComputeWorker<W>,
+    settings: &AsteroidSettings,
+    seed: u64,
+    edge_length: f32,
+    dirty_layers: u32,
+) {
+    if dirty_layers & LAYER_PERTURB != 0 {
+        compute_worker.write_slice("max_strength", &[settings.peturb_strength * edge_length / 2.]);
+    }
+
+    if dirty_layers & LAYER_CRATERS != 0 {
+        let crater_settings = &settings.crater_settings;
+        let craters = crater_settings.get_craters(seed);
+        let crater_grid = CraterGrid::new(&craters, crater_settings.get_rim_width());
+        compute_worker.write_slice("num_craters", &[craters.len() as u32]);
+        compute_worker.write_slice("rim_steepness", &[crater_settings.get_rim_steepness()]);
+        compute_worker.write_slice("rim_width", &[crater_settings.get_rim_width()]);
+        compute_worker.write_slice("craters", &craters);
+        compute_worker.write_slice("crater_grid", crater_grid.get_buffer_data());
+    }
+
+    if dirty_layers & LAYER_SHAPE_NOISE != 0 {
+        compute_worker.write_slice("noise_params_shape", &settings.simple_noise_settings.get_noise_params(PRNG::new(seed)));
+    }
+    if dirty_layers & LAYER_RIDGE_NOISE != 0 {
+        compute_worker.write_slice("noise_params_ridge", &settings.ridge_noise_settings.get_noise_params(PRNG::new(seed)));
+    }
+    if dirty_layers & LAYER_RIDGE_NOISE2 != 0 {
+        compute_worker.write_slice("noise_params_ridge2", &settings.ridge_noise_settings2.get_noise_params(PRNG::new(seed)));
+    }
+
+    compute_worker.write_slice("dirty_layers", &[dirty_layers]);
+}
+
+/// Layers to re-evaluate when the worker caches the heights of `cached`, all of them for another seed.
+/// Both settings must be sanitized.
+pub fn get_dirty_layers(cached: Option<&(AsteroidSettings, u64)>, settings: &AsteroidSettings, seed: u64) -> u32 {
+    let Some((cached, _)) = cached.filter(|(_, cached_seed)| *cached_seed == seed) else {
+        return ALL_LAYERS;
+    };
+
+    let mut dirty_layers = 0;
+    if cached.peturb_strength != settings.peturb_strength {
+        dirty_layers |= LAYER_PERTURB;
+    }
+    if cached.crater_settings != settings.crater_settings {
+        dirty_layers |= LAYER_CRATERS;
+    }
+    if cached.simple_noise_settings != settings.simple_noise_settings {
+        dirty_layers |= LAYER_SHAPE_NOISE;
+    }
+    if cached.ridge_noise_settings != settings.ridge_noise_settings {
+        dirty_layers |= LAYER_RIDGE_NOISE;
+    }
+    if cached.ridge_noise_settings2 != settings.ridge_noise_settings2 {
+        dirty_layers |= LAYER_RIDGE_NOISE2;
+    }
+    dirty_layers
+}
+
+/// Displaced vertices and normals of the last run, call once `ready()` returns true.
+pub fn read_mesh_data<W: ComputeWorker>(compute_worker: &AppComputeWorker<W>) -> (Vec<Vec3>, Vec<Vec3>) {
+    let raw_vertices: Vec<[f32; 4]> = compute_worker.read_vec("new_vertices");
+    let raw_normals: Vec<[f32; 4]> = compute_worker.read_vec("normals");
+    (convert_array4_to_vec3(raw_vertices), convert_array4_to_vec3(raw_normals))
+}
+
+/// Generation job of the displayed asteroid. The `*Changed` observers copy their settings into `AsteroidSettings`
+/// and mark it dirty, `submit_viewer_job` then replaces the previous job once per frame.
+#[derive(Resource)]
+pub struct ViewerJob {
+    id: Option<JobId>,
+    dirty: bool,
+}
+
+impl Default for ViewerJob {
+    /// Dirty, so the asteroid is generated on the first frame even without the settings window.
+    fn default() -> Self {
+        ViewerJob { id: None, dirty: true }
     }
 }
 
-fn send_perturb_strength_data(
+fn collect_perturb_strength(
     trigger: Trigger<PerturbStrengthChanged>,
-    mut compute_worker: ResMut<AppComputeWorker<AsteroidComputeWorker>>,
-    sphere_mesh: Res<SphereMesh>
+    mut settings: ResMut<AsteroidSettings>,
+    mut viewer_job: ResMut<ViewerJob>,
 ) {
-    let ev = trigger.event();
-    let perturb_strength = ev.0;
-    
-    let edge_length = (sphere_mesh.vertices[sphere_mesh.indices[0] as usize] - sphere_mesh.vertices[sphere_mesh.indices[1] as usize]).length();
-    let max_perturb_strength = perturb_strength * edge_length / 2.;
-    
-    compute_worker.write_slice("max_strength", &[max_perturb_strength]);
-    compute_worker.execute();
+    settings.peturb_strength = trigger.event().0;
+    viewer_job.dirty = true;
 }
 
-fn send_crater_settings_data(
+fn collect_crater_settings(
     trigger: Trigger<CraterSettingsChanged>,
-    mut compute_worker: ResMut<AppComputeWorker<AsteroidComputeWorker>>,
-    seed: ResMut<RngSeed>,
+    mut settings: ResMut<AsteroidSettings>,
+    mut viewer_job: ResMut<ViewerJob>,
 ) {
-    let ev = trigger.event();
-    let crater_settings = &ev.0;
-    let craters = crater_settings.get_craters(seed.0);
-
-    compute_worker.write_slice("num_craters", &[craters.len() as u32]);
-    compute_worker.write_slice("rim_steepness", &[crater_settings.get_rim_steepness()]);
-    compute_worker.write_slice("rim_width", &[crater_settings.get_rim_width()]);
-    compute_worker.write_slice("craters", &craters);
-    compute_worker.execute();
+    settings.crater_settings = trigger.event().0.clone();
+    viewer_job.dirty = true;
 }
 
-fn send_simple_noise_settings_data(
+fn collect_simple_noise_settings(
     trigger: Trigger<SimpleNoiseSettingsChanged>,
-    mut compute_worker: ResMut<AppComputeWorker<AsteroidComputeWorker>>,
-    seed: ResMut<RngSeed>,
+    mut settings: ResMut<AsteroidSettings>,
+    mut viewer_job: ResMut<ViewerJob>,
 ) {
-    let ev = trigger.event();
-    let simple_noise_settings = &ev.0;
-    
-    let prng = PRNG::new(seed.0);
-    let noise_params = simple_noise_settings.get_noise_params(prng);
-
-    compute_worker.write_slice("noise_params_shape", &noise_params);
-    compute_worker.execute();
+    settings.simple_noise_settings = trigger.event().0.clone();
+    viewer_job.dirty = true;
 }
 
-fn send_ridge_noise_settings_data(
+fn collect_ridge_noise_settings(
     trigger: Trigger<RidgeNoiseSettingsChanged>,
-    mut compute_worker: ResMut<AppComputeWorker<AsteroidComputeWorker>>,
-    seed: ResMut<RngSeed>,
+    mut settings: ResMut<AsteroidSettings>,
+    mut viewer_job: ResMut<ViewerJob>,
 ) {
     let ev = trigger.event();
-    let ridge_noise_settings = &ev.0;
-    let suffix = &ev.1;
+    if ev.1.is_empty() {
+        settings.ridge_noise_settings = ev.0.clone();
+    } else {
+        settings.ridge_noise_settings2 = ev.0.clone();
+    }
+    viewer_job.dirty = true;
+}
 
-    let prng = PRNG::new(seed.0);
-    let noise_params = ridge_noise_settings.get_noise_params(prng);
+fn submit_viewer_job(
+    mut viewer_job: ResMut<ViewerJob>,
+    mut jobs: ResMut<GenerationJobs>,
+    settings: Res<AsteroidSettings>,
+    seed: Res<RngSeed>,
+) {
+    if !viewer_job.dirty {
+        return;
+    }
 
-    compute_worker.write_slice(&format!("noise_params_ridge{}",suffix), &noise_params);
-    compute_worker.execute();
+    // A run already on the GPU finishes first, its outdated mesh is discarded
+    if let Some(id) = viewer_job.id.take() {
+        jobs.cancel(id);
+    }
+    viewer_job.id = Some(jobs.submit(settings.clone(), seed.0, SPHERE_RESOLUTION as u32));
+    viewer_job.dirty = false;
 }
 
-
-pub fn receive_data_after_compute(
-    compute_worker: ResMut<AppComputeWorker<AsteroidComputeWorker>>,
+fn receive_viewer_asteroid(
+    trigger: Trigger<GenerationJobFinished>,
+    mut viewer_job: ResMut<ViewerJob>,
     mut commands: Commands,
 ) {
-    if compute_worker.ready() {
-        let raw_vertices: Vec<[f32; 4]> = compute_worker.read_vec("new_vertices");
-        let vertices: Vec<Vec3> = convert_array4_to_vec3(raw_vertices);
-
-        let raw_normals: Vec<[f32; 4]> = compute_worker.read_vec("normals");
-        let normals: Vec<Vec3> = convert_array4_to_vec3(raw_normals);
-
-        commands.trigger(MeshDataAfterCompute(
-            vertices,
-            normals,
-        ));
+    let ev = trigger.event();
+    if viewer_job.id != Some(ev.id) {
+        return;
     }
+    viewer_job.id = None;
+    commands.trigger(MeshDataAfterCompute(ev.mesh.clone()));
 }
 
 fn convert_array4_to_vec3(raw: Vec<[f32; 4]>) -> Vec<Vec3> {
@@ -1,11 +1,39 @@
-﻿use bevy::math::Vec3;
-use bevy::prelude::Event;
+﻿use std::sync::Arc;
+
+use bevy::prelude::{Event, Resource};
+use crate::generator::AsteroidMesh;
 use crate::settings::crater_settings::CraterSettings;
+use crate::settings::material_settings::MaterialSettings;
 use crate::settings::ridge_noise_settings::RidgeNoiseSettings;
 use crate::settings::simple_noise_settings::SimpleNoiseSettings;
 
+/// Settings categories the UI still has to send as `*Changed` events, all of them after an import.
+#[derive(Resource)]
+pub struct ValueChanged {
+    pub perturb_strength: bool,
+    pub crater_settings: bool,
+    pub simple_noise_settings: bool,
+    pub ridge_noise_settings: bool,
+    pub ridge_noise_settings2: bool,
+    pub material_settings: bool,
+}
+
+impl Default for ValueChanged {
+    fn default() -> Self {
+        ValueChanged {
+            perturb_strength: true,
+            crater_settings: true,
+            simple_noise_settings: true,
+            ridge_noise_settings: true,
+            ridge_noise_settings2: true,
+            material_settings: true,
+        }
+    }
+}
+
+/// The displayed asteroid was regenerated.
 #[derive(Event)]
-pub struct MeshDataAfterCompute(pub Vec<Vec3>, pub Vec<Vec3>);
+pub struct MeshDataAfterCompute(pub Arc<AsteroidMesh>);
 
 
 #[derive(Event)]
@@ -18,4 +46,7 @@ pub struct CraterSettingsChanged(pub CraterSettings);
 pub struct SimpleNoiseSettingsChanged(pub SimpleNoiseSettings);
 
 #[derive(Event)]
-pub struct RidgeNoiseSettingsChanged(pub RidgeNoiseSettings, pub String);
\ No newline at end of file
+pub struct RidgeNoiseSettingsChanged(pub RidgeNoiseSettings, pub String);
+
+#[derive(Event)]
+pub struct MaterialSettingsChanged(pub MaterialSettings);
@@ -0,0 +1,119 @@
+use bevy::math::{Vec2, Vec3};
+
+use crate::settings::crater_settings::{Crater, MAX_CRATER};
+
+/// Cells along each cube face edge.
+pub const CRATER_GRID_SIZE: usize = 8;
+pub const CRATER_GRID_CELLS: usize = 6 * CRATER_GRID_SIZE * CRATER_GRID_SIZE;
+/// Size of the `crater_grid` buffer: offsets plus 16 cells per crater on average. Craters up to about 0.15
+/// across cover 7 cells each, grids of larger craters don't fit and the shader loops over every crater instead.
+pub const CRATER_GRID_CAPACITY: usize = CRATER_GRID_CELLS + 1 + 16 * MAX_CRATER;
+
+/// Offsets with an empty last entry, which `crater.wgsl` reads as "no grid".
+static NO_GRID: [u32; CRATER_GRID_CELLS + 1] = [0; CRATER_GRID_CELLS + 1];
+
+/// Extra angle added to each cell so vertices on a cell border, which the GPU may bin into the
+/// neighbouring cell, still see every crater that reaches them.
+const CELL_MARGIN: f32 = 1e-3;
+
+/// Cube-map cell list of the craters that can reach each cell, in the layout `crater.wgsl` reads:
+/// `data[cell]..data[cell + 1]` is the range of `data` holding the crater indices for `cell`.
+/// Indices stay in ascending order so crater heights are summed in the same order as a full loop.
+pub struct CraterGrid {
+    pub data: Vec<u32>,
+}
+
+impl CraterGrid {
+    pub fn new(craters: &[Crater], rim_width: f32) -> Self {
+        let crater_angles: Vec<f32> = craters
+            .iter()
+            .map(|crater| chord_to_angle(crater.radius * influence_factor(crater, rim_width)))
+            .collect();
+
+        let mut data = vec![0; CRATER_GRID_CELLS + 1];
+        for cell in 0..CRATER_GRID_CELLS {
+            data[cell] = data.len() as u32;
+            let (cell_centre, cell_angle) = cell_bounds(cell);
+            for (index, crater) in craters.iter().enumerate() {
+                let angle = cell_centre.dot(crater.centre).clamp(-1.0, 1.0).acos();
+                if angle <= crater_angles[index] + cell_angle + CELL_MARGIN {
+                    data.push(index as u32);
+                }
+            }
+        }
+        data[CRATER_GRID_CELLS] = data.len() as u32;
+
+        CraterGrid { data }
+    }
+
+    /// Contents of the `crater_grid` buffer, or offsets telling the shader to skip the grid when it doesn't fit.
+    pub fn get_buffer_data(&self) -> &[u32] {
+        if self.data.len() <= CRATER_GRID_CAPACITY {
+            &self.data
+        } else {
+            &NO_GRID
+        }
+    }
+
+    /// Indices of the craters that can change the height at `vertex_pos`.
+    pub fn get_craters(&self, vertex_pos: Vec3) -> &[u32] {
+        let cell = cell_index(vertex_pos);
+        &self.data[self.data[cell] as usize..self.data[cell + 1] as usize]
+    }
+}
+
+/// Distance from the centre, in crater radii, beyond which `crater_shape` is exactly zero: the rim has ended
+/// and the cavity is past both the floor and the rim by the smoothing width.
+fn influence_factor(crater: &Crater, rim_width: f32) -> f32 {
+    let cavity_end = (1.0 + crater.smoothness.max(0.0) + crater.floor_height.max(0.0)).sqrt();
+    (1.0 + rim_width).max(cavity_end)
+}
+
+fn chord_to_angle(chord: f32) -> f32 {
+    2.0 * (chord / 2.0).min(1.0).asin()
+}
+
+/// Cube face and face coordinates in [-1, 1] of a direction, matching `craterCell` in `crater.wgsl`.
+fn face_coordinates(pos: Vec3) -> (usize, Vec2) {
+    let abs = pos.abs();
+    if abs.x >= abs.y && abs.x >= abs.z {
+        (if pos.x > 0.0 { 0 } else { 1 }, Vec2::new(pos.y, pos.z) / abs.x)
+    } else if abs.y >= abs.z {
+        (if pos.y > 0.0 { 2 } else { 3 }, Vec2::new(pos.x, pos.z) / abs.y)
+    } else {
+        (if pos.z > 0.0 { 4 } else { 5 }, Vec2::new(pos.x, pos.y) / abs.z)
+    }
+}
+
+fn cell_index(pos: Vec3) -> usize {
+    let (face, uv) = face_coordinates(pos);
+    let max_cell = (CRATER_GRID_SIZE - 1) as f32;
+    let cell = ((uv + 1.0) / 2.0 * CRATER_GRID_SIZE as f32).clamp(Vec2::ZERO, Vec2::splat(max_cell));
+    face * CRATER_GRID_SIZE * CRATER_GRID_SIZE + cell.y as usize * CRATER_GRID_SIZE + cell.x as usize
+}
+
+fn face_point(face: usize, uv: Vec2) -> Vec3 {
+    let sign = if face % 2 == 0 { 1.0 } else { -1.0 };
+    match face / 2 {
+        0 => Vec3::new(sign, uv.x, uv.y),
+        1 => Vec3::new(uv.x, sign, uv.y),
+        _ => Vec3::new(uv.x, uv.y, sign),
+    }
+    .normalize()
+}
+
+/// Centre direction of a cell and the angle to its farthest corner.
+fn cell_bounds(cell: usize) -> (Vec3, f32) {
+    let face = cell / (CRATER_GRID_SIZE * CRATER_GRID_SIZE);
+    let row = cell / CRATER_GRID_SIZE % CRATER_GRID_SIZE;
+    let column = cell % CRATER_GRID_SIZE;
+    let cell_size = 2.0 / CRATER_GRID_SIZE as f32;
+    let min = Vec2::new(column as f32, row as f32) * cell_size - 1.0;
+
+    let centre = face_point(face, min + cell_size / 2.0);
+    let angle = [Vec2::ZERO, Vec2::X, Vec2::Y, Vec2::ONE]
+        .map(|corner| centre.dot(face_point(face, min + corner * cell_size)).clamp(-1.0, 1.0).acos())
+        .into_iter()
+        .fold(0.0, f32::max);
+    (centre, angle)
+}
@@ -0,0 +1,46 @@
+use std::fmt;
+use std::path::PathBuf;
+
+use crate::stl_exporter::PrintReport;
+
+/// Why an export did not produce its files, shown in the settings window.
+#[derive(Debug)]
+pub enum ExportError {
+    /// No asteroid has been generated yet.
+    NoAsteroid,
+    /// The field export was requested before generating a field.
+    NoField,
+    /// The asteroid mesh is missing an attribute the exporters need.
+    InvalidMesh(&'static str),
+    Io(PathBuf, std::io::Error),
+    Encoding(String),
+    /// A batch grid path that doesn't name a numeric setting.
+    InvalidSetting(String),
+    /// The STL failed validation, the report lists the problems.
+    NotPrintable(PrintReport),
+    /// The mesh has folded or broken triangles and `MeshQualitySettings::on_export` blocks it.
+    PoorMeshQuality(Vec<String>),
+}
+
+impl fmt::Display for ExportError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ExportError::NoAsteroid => write!(f, "No asteroid to export yet"),
+            ExportError::NoField => write!(f, "Generate a field before exporting it"),
+            ExportError::InvalidMesh(reason) => write!(f, "Asteroid mesh is invalid: {}", reason),
+            ExportError::Io(path, err) => write!(f, "Could not write {}: {}", path.display(), err),
+            ExportError::Encoding(err) => write!(f, "Could not encode the file: {}", err),
+            ExportError::InvalidSetting(path) => write!(f, "{} is not a numeric asteroid setting", path),
+            ExportError::NotPrintable(_) => write!(f, "Mesh is not printable, STL was not written"),
+            ExportError::PoorMeshQuality(issues) => write!(f, "Export blocked by mesh quality: {}", issues.join(", ")),
+        }
+    }
+}
+
+impl std::error::Error for ExportError {}
+
+/// Attaches the path being written to an I/O error.
+pub fn io_error(path: impl Into<PathBuf>) -> impl FnOnce(std::io::Error) -> ExportError {
+    let path = path.into();
+    move |err| ExportError::Io(path, err)
+}
@@ -0,0 +1,185 @@
+use bevy::asset::{Assets, Handle};
+use bevy::math::Vec3;
+use bevy::prelude::{Mesh, Query, With};
+use bevy::reflect::Reflect;
+use bevy::render::mesh::{Indices, VertexAttributeValues};
+use serde::{Deserialize, Serialize};
+
+use crate::asteroid_mesh_builder::Asteroid;
+use crate::export_error::ExportError;
+use crate::mass_properties::{compute_mass_properties, MassProperties};
+use crate::settings::scale_settings::ScaleSettings;
+
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq, Reflect, Serialize, Deserialize)]
+pub enum ExportFormat {
+    #[default]
+    Glb,
+    Obj,
+    PlyAscii,
+    PlyBinary,
+    Stl,
+    Usda,
+    HeightmapPng,
+    HeightmapExr,
+    Field,
+}
+
+impl ExportFormat {
+    pub const ALL: [ExportFormat; 9] = [
+        ExportFormat::Glb,
+        ExportFormat::Obj,
+        ExportFormat::PlyAscii,
+        ExportFormat::PlyBinary,
+        ExportFormat::Stl,
+        ExportFormat::Usda,
+        ExportFormat::HeightmapPng,
+        ExportFormat::HeightmapExr,
+        ExportFormat::Field,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            ExportFormat::Glb => "glTF",
+            ExportFormat::Obj => "obj",
+            ExportFormat::PlyAscii => "ply (ascii)",
+            ExportFormat::PlyBinary => "ply (binary)",
+            ExportFormat::Stl => "stl (3D print)",
+            ExportFormat::Usda => "usda",
+            ExportFormat::HeightmapPng => "heightmap (16-bit png)",
+            ExportFormat::HeightmapExr => "heightmap (32-bit exr)",
+            ExportFormat::Field => "asteroid field (glTF)",
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ExportFormat::Glb | ExportFormat::Field => "glb",
+            ExportFormat::Obj => "obj",
+            ExportFormat::PlyAscii | ExportFormat::PlyBinary => "ply",
+            ExportFormat::Stl => "stl",
+            ExportFormat::Usda => "usda",
+            ExportFormat::HeightmapPng => "png",
+            ExportFormat::HeightmapExr => "exr",
+        }
+    }
+}
+
+/// Mesh data pulled out of the rendered asteroid, shared by all exporters.
+#[derive(Clone)]
+pub struct ExportMesh {
+    pub positions: Vec<[f32; 3]>,
+    pub normals: Vec<[f32; 3]>,
+    pub indices: Vec<u32>,
+    /// Per-vertex linear RGB colors, only present when the mesh carries them.
+    pub colors: Option<Vec<[f32; 3]>>,
+    /// Named per-vertex scalar attributes, written by formats that support custom properties.
+    pub scalars: Vec<(String, Vec<f32>)>,
+}
+
+impl ExportMesh {
+    /// Copies the mesh of the current asteroid.
+    pub fn from_asteroid(
+        asteroid_query: &Query<&Handle<Mesh>, With<Asteroid>>,
+        meshes: &Assets<Mesh>,
+    ) -> Result<Self, ExportError> {
+        let mesh_handle = asteroid_query.get_single().map_err(|_| ExportError::NoAsteroid)?;
+        let mesh = meshes.get(mesh_handle).ok_or(ExportError::NoAsteroid)?;
+        Self::from_mesh(mesh)
+    }
+
+    pub fn from_mesh(mesh: &Mesh) -> Result<Self, ExportError> {
+        let Some(VertexAttributeValues::Float32x3(positions)) = mesh.attribute(Mesh::ATTRIBUTE_POSITION) else {
+            return Err(ExportError::InvalidMesh("vertices not found or not in Float32x3 format"));
+        };
+
+        let Some(VertexAttributeValues::Float32x3(normals)) = mesh.attribute(Mesh::ATTRIBUTE_NORMAL) else {
+            return Err(ExportError::InvalidMesh("vertex normals not found or not in Float32x3 format"));
+        };
+
+        let indices = match mesh.indices() {
+            Some(Indices::U32(indices)) => indices.clone(),
+            Some(Indices::U16(indices)) => indices.iter().map(|&i| i as u32).collect(),
+            None => return Err(ExportError::InvalidMesh("mesh has no indices")),
+        };
+
+        let colors = match mesh.attribute(Mesh::ATTRIBUTE_COLOR) {
+            Some(VertexAttributeValues::Float32x4(colors)) => {
+                Some(colors.iter().map(|&[r, g, b, _]| [r, g, b]).collect())
+            }
+            Some(VertexAttributeValues::Float32x3(colors)) => Some(colors.clone()),
+            _ => None,
+        };
+
+        // Distance from the centre, i.e. the final radius after displacement
+        let heights = positions
+            .iter()
+            .map(|&[x, y, z]| (x * x + y * y + z * z).sqrt())
+            .collect();
+
+        Ok(ExportMesh {
+            positions: positions.clone(),
+            normals: normals.clone(),
+            indices,
+            colors,
+            scalars: vec![("height".to_string(), heights)],
+        })
+    }
+
+    /// Wraps vertices generated on the CPU, e.g. by `ShapeEvaluator::evaluate_mesh`.
+    pub fn from_vertices(vertices: &[Vec3], normals: &[Vec3], indices: Vec<u32>) -> Self {
+        ExportMesh {
+            positions: vertices.iter().map(|v| v.to_array()).collect(),
+            normals: normals.iter().map(|n| n.to_array()).collect(),
+            indices,
+            colors: None,
+            scalars: Vec::new(),
+        }
+    }
+
+    pub fn triangle_count(&self) -> usize {
+        self.indices.len() / 3
+    }
+
+    /// Mass properties in the mesh's current units and axes, see `MassProperties::scaled`.
+    pub fn get_mass_properties(&self, density: f32) -> MassProperties {
+        compute_mass_properties(&self.positions, &self.indices, density)
+    }
+
+    /// Scales positions and the height attribute, e.g. from generator units to the body radius.
+    pub fn scale(&mut self, scale: f32) {
+        for position in &mut self.positions {
+            *position = position.map(|value| value * scale);
+        }
+        for (name, values) in &mut self.scalars {
+            if name == "height" {
+                values.iter_mut().for_each(|value| *value *= scale);
+            }
+        }
+    }
+
+    /// Replaces positions and normals, e.g. after a repair, and updates the height attribute to match.
+    pub fn set_surface(&mut self, positions: &[Vec3], normals: &[Vec3]) {
+        self.positions = positions.iter().map(|p| p.to_array()).collect();
+        self.normals = normals.iter().map(|n| n.to_array()).collect();
+        for (name, values) in &mut self.scalars {
+            if name == "height" {
+                *values = positions.iter().map(|p| p.length()).collect();
+            }
+        }
+    }
+
+    /// Converts from Bevy's Y-up, right-handed axes to the convention in `scale_settings`.
+    pub fn convert_axes(&mut self, scale_settings: &ScaleSettings) {
+        for position in &mut self.positions {
+            *position = scale_settings.convert_axes(*position);
+        }
+        for normal in &mut self.normals {
+            *normal = scale_settings.convert_axes(*normal);
+        }
+        if scale_settings.flips_winding() {
+            for triangle in self.indices.chunks_exact_mut(3) {
+                triangle.swap(1, 2);
+            }
+        }
+    }
+}
@@ -0,0 +1,128 @@
+use std::fs;
+use std::path::PathBuf;
+
+use bevy::app::{App, Plugin, Update};
+use bevy::prelude::{Event, ResMut, Resource};
+use bevy::tasks::{block_on, futures_lite::future, AsyncComputeTaskPool, Task};
+
+use crate::export_error::ExportError;
+use crate::export_mesh::ExportFormat;
+use crate::stl_exporter::PrintReport;
+
+/// Polls the tasks of the exporters, which add it themselves unless the app added it first.
+pub struct ExportTaskPlugin;
+
+impl Plugin for ExportTaskPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(ExportStatus::default())
+            .add_event::<ExportButtonClicked>()
+            .add_systems(Update, poll_export_tasks);
+    }
+}
+
+/// Asks the exporter of `format` to write the current asteroid to `path`.
+#[derive(Event)]
+pub struct ExportButtonClicked {
+    pub format: ExportFormat,
+    pub path: PathBuf,
+}
+
+/// Files written by one exporter.
+#[derive(Default)]
+pub struct ExportOutput {
+    pub paths: Vec<PathBuf>,
+    pub print_report: Option<PrintReport>,
+    /// Shown below the saved files, e.g. mesh quality issues.
+    pub notes: Vec<String>,
+}
+
+impl ExportOutput {
+    pub fn new(paths: Vec<PathBuf>) -> Self {
+        ExportOutput {
+            paths,
+            print_report: None,
+            notes: Vec::new(),
+        }
+    }
+
+    pub fn with_notes(mut self, notes: Vec<String>) -> Self {
+        self.notes = notes;
+        self
+    }
+}
+
+pub enum ExportMessage {
+    Saved { path: PathBuf, bytes: u64 },
+    Failed(String),
+    Info(String),
+}
+
+/// Running export tasks and the outcome of the last export.
+#[derive(Resource, Default)]
+pub struct ExportStatus {
+    /// Each task is tagged with the generation it was spawned in.
+    tasks: Vec<(u64, Task<Result<ExportOutput, ExportError>>)>,
+    /// Bumped by `clear` so results of earlier exports don't land in the new messages.
+    generation: u64,
+    pub messages: Vec<ExportMessage>,
+}
+
+impl ExportStatus {
+    /// Runs `export` on the async compute pool so writing large files doesn't stall the viewer.
+    pub fn spawn(&mut self, export: impl FnOnce() -> Result<ExportOutput, ExportError> + Send + 'static) {
+        let task = AsyncComputeTaskPool::get().spawn(async move { export() });
+        self.tasks.push((self.generation, task));
+    }
+
+    pub fn fail(&mut self, err: ExportError) {
+        self.messages.push(ExportMessage::Failed(err.to_string()));
+    }
+
+    pub fn is_running(&self) -> bool {
+        !self.tasks.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.generation += 1;
+        self.messages.clear();
+    }
+}
+
+/// The print report is only kept when `StlExporter` added it.
+fn poll_export_tasks(mut status: ResMut<ExportStatus>, mut print_report: Option<ResMut<PrintReport>>) {
+    let generation = status.generation;
+    let mut finished = Vec::new();
+    status.tasks.retain_mut(|(task_generation, task)| match block_on(future::poll_once(task)) {
+        Some(result) => {
+            if *task_generation == generation {
+                finished.push(result);
+            }
+            false
+        }
+        None => true,
+    });
+
+    for result in finished {
+        match result {
+            Ok(output) => {
+                for path in output.paths {
+                    let bytes = fs::metadata(&path).map(|metadata| metadata.len()).unwrap_or(0);
+                    println!("Asteroid data written {}", path.display());
+                    status.messages.push(ExportMessage::Saved { path, bytes });
+                }
+                status.messages.extend(output.notes.into_iter().map(ExportMessage::Info));
+                if let Some((report, print_report)) = output.print_report.zip(print_report.as_mut()) {
+                    **print_report = report;
+                }
+            }
+            Err(err) => {
+                println!("{}", err);
+                status.messages.push(ExportMessage::Failed(err.to_string()));
+                // Keep the report so the UI can show why the mesh isn't printable.
+                if let (ExportError::NotPrintable(report), Some(print_report)) = (err, print_report.as_mut()) {
+                    **print_report = report;
+                }
+            }
+        }
+    }
+}
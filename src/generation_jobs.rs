@@ -0,0 +1,289 @@
+use std::collections::VecDeque;
+use std::sync::{Arc, OnceLock};
+
+use bevy::prelude::*;
+use bevy::render::RenderApp;
+use bevy::tasks::{block_on, futures_lite::future, AsyncComputeTaskPool, Task};
+use bevy_easy_compute::prelude::{AppComputePlugin, AppComputeWorker, AppComputeWorkerPlugin, ComputeWorker};
+
+use crate::compute::{build_asteroid_worker, get_dirty_layers, read_mesh_data, write_asteroid_settings};
+use crate::generator::{generate_with_sphere, AsteroidMesh};
+use crate::settings::asteroid_settings::AsteroidSettings;
+use crate::sphere_mesh::SphereMesh;
+
+/// Runs submitted `GenerationJob`s and triggers `GenerationJobFinished` with each result.
+///
+/// Jobs at `gpu_resolution` run one after another on the compute shaders when a GPU is available, i.e.
+/// `AppComputePlugin` and a `RenderPlugin` that creates a renderer were added before this plugin.
+/// All other jobs, and every job in headless apps, run on the CPU, up to `max_parallel` at once.
+pub struct GenerationJobsPlugin {
+    pub gpu_resolution: u32,
+    pub max_parallel: usize,
+}
+
+impl Default for GenerationJobsPlugin {
+    fn default() -> Self {
+        GenerationJobsPlugin {
+            gpu_resolution: 100,
+            max_parallel: 4,
+        }
+    }
+}
+
+impl Plugin for GenerationJobsPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(GenerationJobs::new(self.gpu_resolution, self.max_parallel))
+            .add_systems(Update, run_generation_jobs);
+
+        // The render sub-app only exists when the renderer gets a device, `WgpuSettings { backends: None, .. }`
+        // or a missing `RenderPlugin` leave the compute worker without one.
+        let gpu_available = app.get_sub_app(RenderApp).is_some();
+        if gpu_available && app.is_plugin_added::<AppComputePlugin>() {
+            app.add_plugins(AppComputeWorkerPlugin::<GenerationWorker>::default());
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct JobId(u64);
+
+#[derive(Clone)]
+pub struct GenerationJob {
+    pub id: JobId,
+    pub settings: AsteroidSettings,
+    pub seed: u64,
+    pub resolution: u32,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum JobState {
+    Queued,
+    Running,
+}
+
+/// Counts since the queue was last idle, `finished / submitted` is the progress of the current burst.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct JobProgress {
+    pub submitted: usize,
+    pub finished: usize,
+    pub running: usize,
+}
+
+impl JobProgress {
+    pub fn get_fraction(&self) -> f32 {
+        if self.submitted == 0 {
+            1.0
+        } else {
+            self.finished as f32 / self.submitted as f32
+        }
+    }
+}
+
+/// Shared so every observer can keep the mesh without copying it.
+#[derive(Event)]
+pub struct GenerationJobFinished {
+    pub id: JobId,
+    pub mesh: Arc<AsteroidMesh>,
+}
+
+struct GpuRun {
+    id: JobId,
+    cancelled: bool,
+}
+
+#[derive(Resource)]
+pub struct GenerationJobs {
+    next_id: u64,
+    gpu_resolution: u32,
+    max_parallel: usize,
+    queued: VecDeque<GenerationJob>,
+    cpu_tasks: Vec<(JobId, Task<AsteroidMesh>)>,
+    /// Sphere of the last CPU resolution, built once by the first task that needs it.
+    cpu_sphere: Option<(u32, Arc<OnceLock<SphereMesh>>)>,
+    gpu_run: Option<GpuRun>,
+    /// Sanitized settings and seed of the last GPU run, whose layers the worker still caches.
+    gpu_cache: Option<(AsteroidSettings, u64)>,
+    progress: JobProgress,
+}
+
+impl GenerationJobs {
+    fn new(gpu_resolution: u32, max_parallel: usize) -> Self {
+        GenerationJobs {
+            next_id: 0,
+            gpu_resolution,
+            max_parallel: max_parallel.max(1),
+            queued: VecDeque::new(),
+            cpu_tasks: Vec::new(),
+            cpu_sphere: None,
+            gpu_run: None,
+            gpu_cache: None,
+            progress: JobProgress::default(),
+        }
+    }
+
+    pub fn submit(&mut self, settings: AsteroidSettings, seed: u64, resolution: u32) -> JobId {
+        if self.is_idle() {
+            self.progress = JobProgress::default();
+        }
+
+        let id = JobId(self.next_id);
+        self.next_id += 1;
+        self.queued.push_back(GenerationJob {
+            id,
+            settings,
+            seed,
+            resolution,
+        });
+        self.progress.submitted += 1;
+        id
+    }
+
+    /// Drops a stale job. A queued or CPU job stops right away, a GPU run finishes but its result is discarded.
+    pub fn cancel(&mut self, id: JobId) -> bool {
+        let cancelled = if let Some(index) = self.queued.iter().position(|job| job.id == id) {
+            self.queued.remove(index);
+            true
+        } else if let Some(index) = self.cpu_tasks.iter().position(|(task_id, _)| *task_id == id) {
+            // Dropping the task cancels it.
+            self.cpu_tasks.swap_remove(index);
+            true
+        } else if let Some(gpu_run) = self.gpu_run.as_mut().filter(|run| run.id == id && !run.cancelled) {
+            gpu_run.cancelled = true;
+            true
+        } else {
+            false
+        };
+
+        if cancelled {
+            self.progress.submitted -= 1;
+        }
+        cancelled
+    }
+
+    pub fn get_state(&self, id: JobId) -> Option<JobState> {
+        if self.queued.iter().any(|job| job.id == id) {
+            Some(JobState::Queued)
+        } else if self.cpu_tasks.iter().any(|(task_id, _)| *task_id == id)
+            || self.gpu_run.as_ref().is_some_and(|run| run.id == id && !run.cancelled)
+        {
+            Some(JobState::Running)
+        } else {
+            None
+        }
+    }
+
+    pub fn get_progress(&self) -> JobProgress {
+        JobProgress {
+            running: self.cpu_tasks.len() + self.gpu_run.iter().filter(|run| !run.cancelled).count(),
+            ..self.progress
+        }
+    }
+
+    pub fn is_idle(&self) -> bool {
+        self.queued.is_empty() && self.cpu_tasks.is_empty() && self.gpu_run.is_none()
+    }
+
+    fn get_cpu_sphere(&mut self, resolution: u32) -> Arc<OnceLock<SphereMesh>> {
+        match &self.cpu_sphere {
+            Some((cached, sphere)) if *cached == resolution => sphere.clone(),
+            _ => {
+                let sphere = Arc::new(OnceLock::new());
+                self.cpu_sphere = Some((resolution, sphere.clone()));
+                sphere
+            }
+        }
+    }
+}
+
+/// Compute worker shared by all generation jobs, runs one job at a time.
+#[derive(Resource)]
+pub struct GenerationWorker;
+
+/// Sphere the generation worker displaces, its indices are reused for every GPU result.
+#[derive(Resource)]
+struct GpuSphereMesh(SphereMesh);
+
+impl ComputeWorker for GenerationWorker {
+    fn build(world: &mut World) -> AppComputeWorker<Self> {
+        let resolution = world.resource::<GenerationJobs>().gpu_resolution;
+        let sphere_mesh = SphereMesh::new(resolution as usize);
+        let worker = build_asteroid_worker(world, &sphere_mesh);
+        world.insert_resource(GpuSphereMesh(sphere_mesh));
+        worker
+    }
+}
+
+fn run_generation_jobs(
+    mut commands: Commands,
+    mut jobs: ResMut<GenerationJobs>,
+    gpu_worker: Option<ResMut<AppComputeWorker<GenerationWorker>>>,
+    sphere_mesh: Option<Res<GpuSphereMesh>>,
+) {
+    let jobs = &mut *jobs;
+
+    let mut finished = Vec::new();
+    jobs.cpu_tasks.retain_mut(|(id, task)| match block_on(future::poll_once(task)) {
+        Some(mesh) => {
+            finished.push(GenerationJobFinished { id: *id, mesh: Arc::new(mesh) });
+            false
+        }
+        None => true,
+    });
+
+    let mut gpu = gpu_worker.zip(sphere_mesh);
+    if let Some((gpu_worker, sphere_mesh)) = gpu.as_mut() {
+        if gpu_worker.ready() {
+            if let Some(gpu_run) = jobs.gpu_run.take().filter(|run| !run.cancelled) {
+                let (positions, normals) = read_mesh_data(gpu_worker);
+                let mesh = AsteroidMesh {
+                    positions,
+                    normals,
+                    indices: sphere_mesh.0.indices.clone(),
+                };
+                finished.push(GenerationJobFinished { id: gpu_run.id, mesh: Arc::new(mesh) });
+            }
+        }
+    }
+
+    let mut waiting = VecDeque::new();
+    while let Some(job) = jobs.queued.pop_front() {
+        match gpu.as_mut() {
+            Some((gpu_worker, sphere_mesh)) if job.resolution == jobs.gpu_resolution => {
+                if jobs.gpu_run.is_some() {
+                    waiting.push_back(job);
+                    continue;
+                }
+                let mut settings = job.settings;
+                settings.sanitize();
+                // Only the layers that differ from the previous run, whichever job it belonged to
+                let dirty_layers = get_dirty_layers(jobs.gpu_cache.as_ref(), &settings, job.seed);
+                write_asteroid_settings(gpu_worker, &settings, job.seed, sphere_mesh.0.edge_length(), dirty_layers);
+                gpu_worker.execute();
+                jobs.gpu_cache = Some((settings, job.seed));
+                jobs.gpu_run = Some(GpuRun {
+                    id: job.id,
+                    cancelled: false,
+                });
+            }
+            _ => {
+                if jobs.cpu_tasks.len() >= jobs.max_parallel {
+                    waiting.push_back(job);
+                    continue;
+                }
+                let id = job.id;
+                let sphere = jobs.get_cpu_sphere(job.resolution);
+                let task = AsyncComputeTaskPool::get().spawn(async move {
+                    let sphere_mesh = sphere.get_or_init(|| SphereMesh::new(job.resolution as usize));
+                    generate_with_sphere(&job.settings, job.seed, sphere_mesh)
+                });
+                jobs.cpu_tasks.push((id, task));
+            }
+        }
+    }
+    jobs.queued = waiting;
+
+    jobs.progress.finished += finished.len();
+    for event in finished {
+        commands.trigger(event);
+    }
+}
@@ -0,0 +1,52 @@
+use bevy::math::Vec3;
+use bevy::prelude::Mesh;
+
+use crate::asteroid_mesh_builder::generate_mesh;
+use crate::export_mesh::ExportMesh;
+use crate::mass_properties::{compute_mass_properties, MassProperties};
+use crate::settings::asteroid_settings::AsteroidSettings;
+use crate::shape_evaluator::ShapeEvaluator;
+use crate::sphere_mesh::SphereMesh;
+
+/// A generated asteroid in generator units, the undisplaced sphere has radius 1.
+#[derive(Clone)]
+pub struct AsteroidMesh {
+    pub positions: Vec<Vec3>,
+    pub normals: Vec<Vec3>,
+    pub indices: Vec<u32>,
+}
+
+impl AsteroidMesh {
+    /// Render mesh with positions, normals and indices, kept in both worlds like the viewer mesh.
+    pub fn to_mesh(&self) -> Mesh {
+        generate_mesh(self.positions.clone(), self.indices.clone(), self.normals.clone())
+    }
+
+    /// Input for the exporters, e.g. `gltf_exporter::export` or `obj_exporter::write_obj`.
+    pub fn to_export_mesh(&self) -> ExportMesh {
+        ExportMesh::from_vertices(&self.positions, &self.normals, self.indices.clone())
+    }
+
+    /// Mass properties in generator units, see `MassProperties::scaled` to convert to metres.
+    pub fn get_mass_properties(&self, density: f32) -> MassProperties {
+        let positions: Vec<[f32; 3]> = self.positions.iter().map(|p| p.to_array()).collect();
+        compute_mass_properties(&positions, &self.indices, density)
+    }
+}
+
+/// Generates an asteroid on the CPU. Matches the viewer mesh for the same settings, seed and resolution
+/// (the viewer uses `compute::SPHERE_RESOLUTION`).
+pub fn generate(settings: &AsteroidSettings, seed: u64, resolution: u32) -> AsteroidMesh {
+    generate_with_sphere(settings, seed, &SphereMesh::new(resolution as usize))
+}
+
+/// Same as `generate`, reusing a sphere mesh when generating many asteroids at one resolution.
+pub fn generate_with_sphere(settings: &AsteroidSettings, seed: u64, sphere_mesh: &SphereMesh) -> AsteroidMesh {
+    let evaluator = ShapeEvaluator::new(settings, seed, sphere_mesh.edge_length());
+    let (positions, normals) = evaluator.evaluate_mesh(sphere_mesh);
+    AsteroidMesh {
+        positions,
+        normals,
+        indices: sphere_mesh.indices.clone(),
+    }
+}
@@ -1,306 +1,816 @@
 use std::{fs, mem};
 use std::borrow::Cow;
+use std::f32::consts::TAU;
 use std::io::Write;
+use std::path::{Path, PathBuf};
 
 use bevy::app::{App, Plugin};
 use bevy::asset::{Assets, Handle};
-use bevy::prelude::{Mesh, Query, Res, Trigger, With};
-use bevy::render::mesh::{Indices, VertexAttributeValues};
+use bevy::math::Vec3;
+use bevy::prelude::{Mesh, Query, Res, ResMut, Trigger, With};
 use gltf_json as json;
 use json::validation::Checked::Valid;
 use json::validation::USize64;
 
+use crate::asteroid_field::AsteroidField;
+use crate::asteroid_metadata::AsteroidMetadata;
 use crate::asteroid_mesh_builder::Asteroid;
-use crate::ui_asteroid_settings::ExportButtonClicked;
+use crate::collider::{build_colliders, ColliderMesh};
+use crate::compute::SPHERE_RESOLUTION;
+use crate::export_error::{io_error, ExportError};
+use crate::export_mesh::{ExportFormat, ExportMesh};
+use crate::export_task::{ExportButtonClicked, ExportOutput, ExportStatus, ExportTaskPlugin};
+use crate::mass_properties::{write_mass_properties, MassProperties};
+use crate::mesh_quality::check_export_mesh;
+use crate::settings::asteroid_settings::AsteroidSettings;
+use crate::settings::collider_settings::ColliderOutput;
+use crate::settings::export_settings::{get_collider_path, ExportSettings, GltfOutput};
+use crate::settings::material_settings::MaterialSettings;
+use crate::settings::mass_settings::MassSettings;
+use crate::settings::mesh_quality_settings::MeshQualitySettings;
+use crate::settings::scale_settings::ScaleSettings;
+use crate::settings::spin_settings::SpinSettings;
+use crate::RngSeed;
 
 pub struct GlTFExporter;
 
 impl Plugin for GlTFExporter {
     fn build(&self, app: &mut App) {
-        app.observe(export_gltf);
+        if !app.is_plugin_added::<ExportTaskPlugin>() {
+            app.add_plugins(ExportTaskPlugin);
+        }
+
+        app.init_resource::<ExportSettings>()
+            .init_resource::<ScaleSettings>()
+            .init_resource::<MassSettings>()
+            .init_resource::<MeshQualitySettings>()
+            .init_resource::<SpinSettings>()
+            .init_resource::<AsteroidField>()
+            .observe(export_gltf)
+            .observe(export_field_gltf);
     }
 }
 
-#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
-enum Output {
-    /// Output standard glTF.
-    Standard,
-    /// Output binary glTF.
-    Binary,
-}
+const MESH_QUANTIZATION: &str = "KHR_mesh_quantization";
+const MESHOPT_COMPRESSION: &str = "EXT_meshopt_compression";
 
 fn export_gltf(
-    _: Trigger<ExportButtonClicked>,
-    mut asteroid_query: Query<&Handle<Mesh>, With<Asteroid>>,
+    trigger: Trigger<ExportButtonClicked>,
+    asteroid_query: Query<&Handle<Mesh>, With<Asteroid>>,
     meshes: Res<Assets<Mesh>>,
+    settings: Res<AsteroidSettings>,
+    export_settings: Res<ExportSettings>,
+    mass_settings: Res<MassSettings>,
+    scale_settings: Res<ScaleSettings>,
+    quality_settings: Res<MeshQualitySettings>,
+    spin_settings: Res<SpinSettings>,
+    seed: Res<RngSeed>,
+    mut export_status: ResMut<ExportStatus>,
 ) {
-    let mesh_handle = asteroid_query.get_single_mut().unwrap();
-
-    if let Some(mesh) = meshes.get(mesh_handle) {
-        let mut mesh_vertices: &Vec<[f32; 3]> = &vec![];
-        let mut mesh_normals: &Vec<[f32; 3]> = &vec![];
-        let mut mesh_indices: &Vec<u32> = &vec![];
+    let ev = trigger.event();
+    if ev.format != ExportFormat::Glb {
+        return;
+    }
 
-        if let Some(VertexAttributeValues::Float32x3(vertices)) =
-            mesh.attribute(Mesh::ATTRIBUTE_POSITION)
-        {
-            mesh_vertices = vertices;
-        } else {
-            println!("Vertices not found or not in Float32x3 format.");
+    let mut export_mesh = match ExportMesh::from_asteroid(&asteroid_query, &meshes) {
+        Ok(export_mesh) => export_mesh,
+        Err(err) => {
+            export_status.fail(err);
+            return;
         }
+    };
 
-        if let Some(indices) = mesh.indices() {
-            match indices {
-                Indices::U16(_) => {
-                    println!("WARNING !! Indices are in u16");
-                }
-                Indices::U32(indices) => {
-                    mesh_indices = indices;
+    // glTF is always in metres
+    let scale = scale_settings.get_scale_in_metres();
+    let metadata = AsteroidMetadata::new(&settings, seed.0, SPHERE_RESOLUTION as u32).with_spin(&spin_settings);
+    let density = mass_settings.density;
+    let export_settings = export_settings.clone();
+    let spin_settings = spin_settings.clone();
+    let path = ev.path.clone();
+    let quality_settings = quality_settings.clone();
+
+    export_status.spawn(move || {
+        let notes = check_export_mesh(&mut export_mesh, &quality_settings)?;
+        let mass_properties = export_mesh.get_mass_properties(density).scaled(scale);
+        export_mesh.scale(scale);
+        let collider_settings = &export_settings.collider_settings;
+        let colliders = build_colliders(&export_mesh.positions, &export_mesh.indices, collider_settings);
+
+        let mut paths = Vec::new();
+        match collider_settings.output {
+            ColliderOutput::GltfNodes => {
+                paths.extend(export(Some(&export_mesh), Some(&mass_properties), Some(&spin_settings), &colliders, &metadata, &export_settings, &path)?);
+            }
+            ColliderOutput::SeparateFile => {
+                paths.extend(export(Some(&export_mesh), Some(&mass_properties), Some(&spin_settings), &[], &metadata, &export_settings, &path)?);
+                if !colliders.is_empty() {
+                    let collider_path = get_collider_path(&path);
+                    paths.extend(export(None, None, None, &colliders, &metadata, &export_settings, &collider_path)?);
                 }
             }
-        } else {
-            println!("Mesh has no indices.");
         }
+        paths.push(write_mass_properties(&mass_properties, &path)?);
+        Ok(ExportOutput::new(paths).with_notes(notes))
+    });
+}
+
+fn export_field_gltf(
+    trigger: Trigger<ExportButtonClicked>,
+    field: Res<AsteroidField>,
+    export_settings: Res<ExportSettings>,
+    scale_settings: Res<ScaleSettings>,
+    mut export_status: ResMut<ExportStatus>,
+) {
+    let ev = trigger.event();
+    if ev.format != ExportFormat::Field {
+        return;
+    }
+    if field.is_empty() {
+        export_status.fail(ExportError::NoField);
+        return;
+    }
+
+    // Field distances and sizes are absolute, only the unit is converted
+    let scale = scale_settings.unit.get_metres();
+    let field = field.clone();
+    let export_settings = export_settings.clone();
+    let path = ev.path.clone();
+
+    export_status.spawn(move || Ok(ExportOutput::new(export_field(&field, scale, &export_settings, &path)?)));
+}
+
+/// One buffer view worth of data and the accessor reading it, plus its meshopt encoded form
+/// when compression is enabled.
+struct Stream {
+    data: Vec<u8>,
+    compressed: Option<Vec<u8>>,
+    stride: usize,
+    count: usize,
+    target: Option<json::buffer::Target>,
+    component_type: json::accessor::ComponentType,
+    type_: json::accessor::Type,
+    normalized: bool,
+    bounds: Option<(Vec<f32>, Vec<f32>)>,
+}
 
-        if let Some(VertexAttributeValues::Float32x3(normals)) =
-            mesh.attribute(Mesh::ATTRIBUTE_NORMAL)
-        {
-            mesh_normals = normals;
+impl Stream {
+    /// A `Vec3` attribute, `T` may carry padding so every element stays 4-byte aligned.
+    fn vertices<T>(
+        values: &[T],
+        component_type: json::accessor::ComponentType,
+        normalized: bool,
+        compress: bool,
+    ) -> Result<Self, ExportError> {
+        let compressed = if compress {
+            Some(meshopt::encode_vertex_buffer(values).map_err(encoding_error)?)
         } else {
-            println!("Vertex normals not found or not in Float32x3 format.");
+            None
+        };
+
+        Ok(Stream {
+            data: to_padded_byte_vector(values),
+            compressed,
+            stride: mem::size_of::<T>(),
+            count: values.len(),
+            target: Some(json::buffer::Target::ArrayBuffer),
+            component_type,
+            type_: json::accessor::Type::Vec3,
+            normalized,
+            bounds: None,
+        })
+    }
+
+    /// Stored as `u16` whenever every index fits.
+    fn indices(indices: &[u32], vertex_count: usize, compress: bool) -> Result<Self, ExportError> {
+        let (data, stride, component_type) = if vertex_count <= u16::MAX as usize {
+            let indices: Vec<u16> = indices.iter().map(|&index| index as u16).collect();
+            (to_padded_byte_vector(&indices), mem::size_of::<u16>(), json::accessor::ComponentType::U16)
+        } else {
+            (to_padded_byte_vector(indices), mem::size_of::<u32>(), json::accessor::ComponentType::U32)
+        };
+
+        let compressed = if compress {
+            Some(meshopt::encode_index_buffer(indices, vertex_count).map_err(encoding_error)?)
+        } else {
+            None
+        };
+
+        Ok(Stream {
+            data,
+            compressed,
+            stride,
+            count: indices.len(),
+            target: Some(json::buffer::Target::ElementArrayBuffer),
+            component_type,
+            type_: json::accessor::Type::Scalar,
+            normalized: false,
+            bounds: None,
+        })
+    }
+
+    /// Keyframe times or values, never compressed since they are tiny.
+    fn animation<T>(values: &[T], type_: json::accessor::Type) -> Self {
+        Stream {
+            data: to_padded_byte_vector(values),
+            compressed: None,
+            stride: mem::size_of::<T>(),
+            count: values.len(),
+            target: None,
+            component_type: json::accessor::ComponentType::F32,
+            type_,
+            normalized: false,
+            bounds: None,
         }
+    }
 
-        export(mesh_vertices, mesh_indices, mesh_normals);
-    } else {
-        println!("Mesh not found.");
+    fn with_bounds<const N: usize>(mut self, (min, max): ([f32; N], [f32; N])) -> Self {
+        self.bounds = Some((Vec::from(min), Vec::from(max)));
+        self
+    }
+
+    fn byte_length(&self) -> usize {
+        self.count * self.stride
     }
 }
 
-fn export(vertices: &Vec<[f32; 3]>, indices: &Vec<u32>, normals: &Vec<[f32; 3]>) {
-    let output: Output = Output::Binary;
-    let colors: Vec<[f32; 3]> = vec![[1., 1., 1.]; vertices.len()];
+/// Positions of the streams making up one primitive.
+struct PrimitiveStreams {
+    positions: usize,
+    normals: Option<usize>,
+    colors: Option<usize>,
+    indices: usize,
+}
 
-    let (min, max) = bounding_coords(&vertices);
+/// Writes the asteroid and/or its colliders. Colliders become extra nodes named with the `_collider` suffix.
+/// Mass properties and the spin are stored in the asteroid node `extras`, the spin is also written as a
+/// looping rotation animation on that node. Returns every file written.
+pub fn export(
+    export_mesh: Option<&ExportMesh>,
+    mass_properties: Option<&MassProperties>,
+    spin: Option<&SpinSettings>,
+    colliders: &[ColliderMesh],
+    metadata: &AsteroidMetadata,
+    export_settings: &ExportSettings,
+    path: &Path,
+) -> Result<Vec<PathBuf>, ExportError> {
+    let quantize = export_settings.gltf_quantize;
+    let compress = export_settings.gltf_meshopt_compression;
+
+    let mut root = new_root(metadata)?;
+
+    let mut streams = Vec::new();
+    let asteroid = export_mesh
+        .map(|export_mesh| push_asteroid_streams(&mut streams, &optimize_mesh(export_mesh), quantize, compress))
+        .transpose()?;
+    let spin = spin.filter(|spin| spin.enabled && asteroid.is_some());
+    let spin_streams = spin.map(|spin| push_spin_streams(&mut streams, spin));
+
+    // Colliders keep full precision, they are small and physics engines read them as f32
+    let mut collider_streams = Vec::new();
+    for collider in colliders {
+        streams.push(
+            Stream::vertices(&collider.positions, json::accessor::ComponentType::F32, false, compress)?
+                .with_bounds(bounding_coords(&collider.positions)),
+        );
+        streams.push(Stream::indices(&collider.indices, collider.positions.len(), compress)?);
+        collider_streams.push(PrimitiveStreams {
+            positions: streams.len() - 2,
+            normals: None,
+            colors: None,
+            indices: streams.len() - 1,
+        });
+    }
 
-    let mut root = gltf_json::Root::default();
+    let (accessors, bin) = push_streams(&mut root, &streams, export_settings, path);
+    if quantize && asteroid.is_some() {
+        root.extensions_used.push(MESH_QUANTIZATION.to_string());
+        root.extensions_required.push(MESH_QUANTIZATION.to_string());
+    }
 
-    let positions_length = vertices.len() * mem::size_of::<[f32; 3]>();
-    let colors_length = colors.len() * mem::size_of::<[f32; 3]>();
-    let normals_length = normals.len() * mem::size_of::<[f32; 3]>();
-    let indices_length = indices.len() * mem::size_of::<u32>();
-    let buffer_length = positions_length + colors_length + normals_length + indices_length;
+    let mut nodes = Vec::new();
 
-    let buffer = root.push(json::Buffer {
-        byte_length: USize64::from(buffer_length),
-        extensions: Default::default(),
-        extras: Default::default(),
-        name: None,
-        uri: if output == Output::Standard {
-            Some("buffer0.bin".into())
-        } else {
+    if let Some((primitive_streams, transform)) = asteroid {
+        let material = push_material(&mut root, &metadata.settings.material_settings);
+
+        let mut extras = serde_json::Map::new();
+        if let Some(properties) = mass_properties {
+            extras.insert("mass_properties".to_string(), serde_json::to_value(properties).map_err(encoding_error)?);
+        }
+        if let Some(spin) = spin {
+            extras.insert(
+                "spin".to_string(),
+                serde_json::json!({
+                    "settings": spin,
+                    "axis": spin.get_axis().to_array(),
+                    "angular_velocity": TAU / spin.get_spin_period(),
+                    "loop_duration": spin.get_loop_duration(),
+                }),
+            );
+        }
+        let extras = if extras.is_empty() {
             None
-        },
-    });
+        } else {
+            Some(json::extras::RawValue::from_string(json::Value::Object(extras).to_string()).map_err(encoding_error)?)
+        };
+
+        let mesh = push_mesh(&mut root, &accessors, &primitive_streams, Some(material), "asteroid");
+        let asteroid_node = match (spin, transform) {
+            // The animation has to pivot on the body origin, so the quantization transform moves to a child
+            (Some(_), Some((translation, scale))) => {
+                let mesh_node = root.push(json::Node {
+                    mesh: Some(mesh),
+                    name: Some("asteroid_mesh".to_string()),
+                    translation: Some(translation),
+                    scale: Some([scale; 3]),
+                    ..Default::default()
+                });
+                root.push(json::Node {
+                    children: Some(vec![mesh_node]),
+                    name: Some("asteroid".to_string()),
+                    extras,
+                    ..Default::default()
+                })
+            }
+            _ => root.push(json::Node {
+                mesh: Some(mesh),
+                name: Some("asteroid".to_string()),
+                translation: transform.map(|(translation, _)| translation),
+                scale: transform.map(|(_, scale)| [scale; 3]),
+                extras,
+                ..Default::default()
+            }),
+        };
+        nodes.push(asteroid_node);
+
+        if let Some((input, output)) = spin_streams {
+            root.push(json::Animation {
+                channels: vec![json::animation::Channel {
+                    sampler: json::Index::new(0),
+                    target: json::animation::Target {
+                        node: asteroid_node,
+                        path: Valid(json::animation::Property::Rotation),
+                        extensions: Default::default(),
+                        extras: Default::default(),
+                    },
+                    extensions: Default::default(),
+                    extras: Default::default(),
+                }],
+                samplers: vec![json::animation::Sampler {
+                    input: accessors[input],
+                    interpolation: Valid(json::animation::Interpolation::Linear),
+                    output: accessors[output],
+                    extensions: Default::default(),
+                    extras: Default::default(),
+                }],
+                name: Some("asteroid_spin".to_string()),
+                extensions: Default::default(),
+                extras: Default::default(),
+            });
+        }
+    }
 
-    // Buffer view for positions
-    let position_buffer_view = root.push(json::buffer::View {
-        buffer,
-        byte_length: USize64::from(positions_length),
-        byte_offset: None,
-        byte_stride: None,
-        extensions: Default::default(),
-        extras: Default::default(),
-        name: None,
-        target: Some(Valid(json::buffer::Target::ArrayBuffer)),
-    });
+    let mut collider_nodes = Vec::new();
+    for (collider, primitive_streams) in colliders.iter().zip(&collider_streams) {
+        let mesh = push_mesh(&mut root, &accessors, primitive_streams, None, &collider.name);
+        collider_nodes.push(root.push(json::Node {
+            mesh: Some(mesh),
+            name: Some(collider.name.clone()),
+            ..Default::default()
+        }));
+    }
 
-    // Buffer view for colors
-    let color_buffer_view = root.push(json::buffer::View {
-        buffer,
-        byte_length: USize64::from(colors_length),
-        byte_offset: Some(USize64::from(positions_length)),
-        byte_stride: None,
-        extensions: Default::default(),
-        extras: Default::default(),
-        name: None,
-        target: Some(Valid(json::buffer::Target::ArrayBuffer)),
-    });
+    // Colliders follow the spinning asteroid, otherwise they stay at the root like before
+    match (spin, nodes.first()) {
+        (Some(_), Some(&asteroid_node)) if !collider_nodes.is_empty() => {
+            root.nodes[asteroid_node.value()].children.get_or_insert_with(Vec::new).extend(collider_nodes);
+        }
+        _ => nodes.extend(collider_nodes),
+    }
 
-    let normal_buffer_view = root.push(json::buffer::View {
-        buffer,
-        byte_length: USize64::from(normals_length),
-        byte_offset: Some(USize64::from(positions_length + colors_length)),
-        byte_stride: None,
+    root.push(json::Scene {
         extensions: Default::default(),
         extras: Default::default(),
         name: None,
-        target: Some(Valid(json::buffer::Target::ArrayBuffer)),
+        nodes,
     });
 
-    // Buffer view for indices
-    let index_buffer_view = root.push(json::buffer::View {
-        buffer,
-        byte_length: USize64::from(indices_length),
-        byte_offset: Some(USize64::from(
-            positions_length + colors_length + normals_length,
-        )),
-        byte_stride: None,
-        extensions: Default::default(),
-        extras: Default::default(),
-        name: None,
-        target: Some(Valid(json::buffer::Target::ElementArrayBuffer)),
-    });
+    write_root(&root, bin, export_settings.gltf_output, path)
+}
 
-    let positions_accessor = root.push(json::Accessor {
-        buffer_view: Some(position_buffer_view),
-        byte_offset: None,
-        count: USize64::from(vertices.len()),
-        component_type: Valid(json::accessor::GenericComponentType(
-            json::accessor::ComponentType::F32,
-        )),
-        extensions: Default::default(),
-        extras: Default::default(),
-        type_: Valid(json::accessor::Type::Vec3),
-        min: Some(json::Value::from(Vec::from(min))),
-        max: Some(json::Value::from(Vec::from(max))),
-        name: None,
-        normalized: false,
-        sparse: None,
-    });
+/// Writes the field as one scene. Each shape is stored once, with its regeneration metadata in the
+/// mesh `extras`, and referenced by every node placed with it. Returns every file written.
+fn export_field(
+    field: &AsteroidField,
+    scale: f32,
+    export_settings: &ExportSettings,
+    path: &Path,
+) -> Result<Vec<PathBuf>, ExportError> {
+    let quantize = export_settings.gltf_quantize;
+    let compress = export_settings.gltf_meshopt_compression;
+    let resolution = field.settings.resolution;
+
+    let mut root = new_root(&AsteroidMetadata::new(&field.asteroid_settings, field.base_seed, resolution))?;
+
+    let mut streams = Vec::new();
+    let mut shape_streams = Vec::new();
+    for shape in &field.shapes {
+        shape_streams.push(push_asteroid_streams(&mut streams, &optimize_mesh(&shape.mesh), quantize, compress)?);
+    }
 
-    let colors_accessor = root.push(json::Accessor {
-        buffer_view: Some(color_buffer_view),
-        byte_offset: None,
-        count: USize64::from(colors.len()),
-        component_type: Valid(json::accessor::GenericComponentType(
-            json::accessor::ComponentType::F32,
-        )),
-        extensions: Default::default(),
-        extras: Default::default(),
-        type_: Valid(json::accessor::Type::Vec3),
-        min: None,
-        max: None,
-        name: None,
-        normalized: false,
-        sparse: None,
-    });
+    let (accessors, bin) = push_streams(&mut root, &streams, export_settings, path);
+    if quantize && !field.shapes.is_empty() {
+        root.extensions_used.push(MESH_QUANTIZATION.to_string());
+        root.extensions_required.push(MESH_QUANTIZATION.to_string());
+    }
 
-    let normals_accessor = root.push(json::Accessor {
-        buffer_view: Some(normal_buffer_view),
-        byte_offset: None,
-        count: USize64::from(normals.len()),
-        component_type: Valid(json::accessor::GenericComponentType(
-            json::accessor::ComponentType::F32,
-        )),
-        extensions: Default::default(),
-        extras: Default::default(),
-        type_: Valid(json::accessor::Type::Vec3),
-        min: None,
-        max: None,
-        name: None,
-        normalized: false,
-        sparse: None,
-    });
+    let material = push_material(&mut root, &field.asteroid_settings.material_settings);
+    let mut meshes = Vec::new();
+    for (index, (shape, (primitive_streams, _))) in field.shapes.iter().zip(&shape_streams).enumerate() {
+        let mesh = push_mesh(&mut root, &accessors, primitive_streams, Some(material), &format!("asteroid_shape_{:02}", index));
+        let metadata = serde_json::to_string(&AsteroidMetadata::new(&shape.settings, shape.seed, resolution)).map_err(encoding_error)?;
+        root.meshes[mesh.value()].extras = Some(json::extras::RawValue::from_string(metadata).map_err(encoding_error)?);
+        meshes.push(mesh);
+    }
+
+    let mut nodes = Vec::new();
+    for (index, instance) in field.instances.iter().enumerate() {
+        // Fold the dequantization transform into the instance, exact because both scales are uniform
+        let (offset, mesh_scale) = shape_streams[instance.shape].1.unwrap_or(([0.0; 3], 1.0));
+        let size = instance.size * scale;
+        let translation = instance.translation * scale + instance.rotation * (Vec3::from(offset) * size);
+
+        nodes.push(root.push(json::Node {
+            mesh: Some(meshes[instance.shape]),
+            name: Some(format!("asteroid_{:04}", index)),
+            translation: Some(translation.to_array()),
+            rotation: Some(json::scene::UnitQuaternion(instance.rotation.to_array())),
+            scale: Some([size * mesh_scale; 3]),
+            ..Default::default()
+        }));
+    }
 
-    let indices_accessor = root.push(json::Accessor {
-        buffer_view: Some(index_buffer_view),
-        byte_offset: None,
-        count: USize64::from(indices.len()),
-        component_type: Valid(json::accessor::GenericComponentType(
-            json::accessor::ComponentType::U32,
-        )),
+    let extras = serde_json::json!({ "field": field.settings }).to_string();
+    root.push(json::Scene {
         extensions: Default::default(),
-        extras: Default::default(),
-        type_: Valid(json::accessor::Type::Scalar),
-        min: None,
-        max: None,
-        name: None,
-        normalized: false,
-        sparse: None,
+        extras: Some(json::extras::RawValue::from_string(extras).map_err(encoding_error)?),
+        name: Some("asteroid_field".to_string()),
+        nodes,
     });
 
-    let primitive = json::mesh::Primitive {
-        attributes: {
-            let mut map = std::collections::BTreeMap::new();
-            map.insert(Valid(json::mesh::Semantic::Positions), positions_accessor);
-            map.insert(Valid(json::mesh::Semantic::Colors(0)), colors_accessor);
-            map.insert(Valid(json::mesh::Semantic::Normals), normals_accessor);
-            map
-        },
-        extensions: Default::default(),
-        extras: Default::default(),
-        indices: Some(indices_accessor),
-        material: None,
-        mode: Valid(json::mesh::Mode::Triangles),
-        targets: None,
-    };
+    write_root(&root, bin, export_settings.gltf_output, path)
+}
 
-    let mesh = root.push(json::Mesh {
-        extensions: Default::default(),
-        extras: Default::default(),
-        name: None,
-        primitives: vec![primitive],
-        weights: None,
-    });
+/// Root with the generator and the regeneration metadata in the asset `extras`.
+fn new_root(metadata: &AsteroidMetadata) -> Result<json::Root, ExportError> {
+    let mut root = json::Root::default();
+    root.asset.generator = Some(format!("{} {}", metadata.generator, metadata.version));
+    let extras = serde_json::to_string(metadata).map_err(encoding_error)?;
+    root.asset.extras = Some(json::extras::RawValue::from_string(extras).map_err(encoding_error)?);
+    Ok(root)
+}
 
-    let node = root.push(json::Node {
-        mesh: Some(mesh),
+fn push_material(root: &mut json::Root, material_settings: &MaterialSettings) -> json::Index<json::Material> {
+    let base_color = material_settings.base_color;
+    let material = root.push(json::Material {
+        pbr_metallic_roughness: json::material::PbrMetallicRoughness {
+            base_color_factor: json::material::PbrBaseColorFactor([base_color[0], base_color[1], base_color[2], 1.0]),
+            metallic_factor: json::material::StrengthFactor(material_settings.metallic),
+            roughness_factor: json::material::StrengthFactor(material_settings.roughness),
+            ..Default::default()
+        },
+        extensions: Some(json::extensions::material::Material {
+            ior: Some(json::extensions::material::Ior {
+                ior: json::extensions::material::IndexOfRefraction(material_settings.get_ior()),
+                ..Default::default()
+            }),
+            ..Default::default()
+        }),
         ..Default::default()
     });
+    root.extensions_used.push("KHR_materials_ior".to_string());
+    material
+}
 
-    root.push(json::Scene {
+/// Lays the streams out in the binary buffer and creates their views and accessors. Returns the
+/// accessors in stream order and the binary chunk.
+fn push_streams(
+    root: &mut json::Root,
+    streams: &[Stream],
+    export_settings: &ExportSettings,
+    path: &Path,
+) -> (Vec<json::Index<json::Accessor>>, Vec<u8>) {
+    let output = export_settings.gltf_output;
+    let compress = export_settings.gltf_meshopt_compression;
+    let bin_path = path.with_extension("bin");
+
+    // With meshopt compression the binary chunk holds the encoded streams and the views point into
+    // a data-less fallback buffer of the decoded size
+    let data_buffer = json::Index::<json::Buffer>::new(0);
+    let fallback_buffer = json::Index::<json::Buffer>::new(1);
+    let mut bin = Vec::new();
+    let mut fallback_length = 0;
+    let mut views = Vec::new();
+
+    for stream in streams {
+        let byte_stride = match stream.target {
+            Some(json::buffer::Target::ArrayBuffer) => Some(json::buffer::Stride(stream.stride)),
+            _ => None,
+        };
+
+        let view = match &stream.compressed {
+            Some(compressed) => {
+                let mode = match stream.target {
+                    Some(json::buffer::Target::ElementArrayBuffer) => "TRIANGLES",
+                    _ => "ATTRIBUTES",
+                };
+                let mut extensions = json::extensions::buffer::View::default();
+                extensions.others.insert(
+                    MESHOPT_COMPRESSION.to_string(),
+                    serde_json::json!({
+                        "buffer": 0,
+                        "byteOffset": bin.len(),
+                        "byteLength": compressed.len(),
+                        "byteStride": stream.stride,
+                        "mode": mode,
+                        "count": stream.count,
+                    }),
+                );
+                let view = json::buffer::View {
+                    buffer: fallback_buffer,
+                    byte_length: USize64::from(stream.byte_length()),
+                    byte_offset: Some(USize64::from(fallback_length)),
+                    byte_stride,
+                    extensions: Some(extensions),
+                    extras: Default::default(),
+                    name: None,
+                    target: stream.target.map(Valid),
+                };
+                bin.extend_from_slice(&to_padded_byte_vector(compressed));
+                fallback_length += stream.data.len();
+                view
+            }
+            None => {
+                let view = json::buffer::View {
+                    buffer: data_buffer,
+                    byte_length: USize64::from(stream.byte_length()),
+                    byte_offset: Some(USize64::from(bin.len())),
+                    byte_stride,
+                    extensions: Default::default(),
+                    extras: Default::default(),
+                    name: None,
+                    target: stream.target.map(Valid),
+                };
+                bin.extend_from_slice(&stream.data);
+                view
+            }
+        };
+        views.push(view);
+    }
+
+    root.push(json::Buffer {
+        byte_length: USize64::from(bin.len()),
         extensions: Default::default(),
         extras: Default::default(),
         name: None,
-        nodes: vec![node],
+        uri: if output == GltfOutput::Standard {
+            bin_path.file_name().map(|name| name.to_string_lossy().into_owned())
+        } else {
+            None
+        },
     });
 
+    if compress {
+        let mut extensions = json::extensions::buffer::Buffer::default();
+        extensions.others.insert(MESHOPT_COMPRESSION.to_string(), serde_json::json!({ "fallback": true }));
+        root.push(json::Buffer {
+            byte_length: USize64::from(fallback_length),
+            extensions: Some(extensions),
+            extras: Default::default(),
+            name: None,
+            uri: None,
+        });
+        root.extensions_used.push(MESHOPT_COMPRESSION.to_string());
+        root.extensions_required.push(MESHOPT_COMPRESSION.to_string());
+    }
+
+    let accessors: Vec<json::Index<json::Accessor>> = streams
+        .iter()
+        .zip(views)
+        .map(|(stream, view)| {
+            let buffer_view = root.push(view);
+            root.push(json::Accessor {
+                buffer_view: Some(buffer_view),
+                byte_offset: None,
+                count: USize64::from(stream.count),
+                component_type: Valid(json::accessor::GenericComponentType(stream.component_type)),
+                extensions: Default::default(),
+                extras: Default::default(),
+                type_: Valid(stream.type_),
+                min: stream.bounds.as_ref().map(|(min, _)| json::Value::from(min.clone())),
+                max: stream.bounds.as_ref().map(|(_, max)| json::Value::from(max.clone())),
+                name: None,
+                normalized: stream.normalized,
+                sparse: None,
+            })
+        })
+        .collect();
+
+    (accessors, bin)
+}
+
+/// Writes `.glb`, or `.gltf` with the binary chunk in a `.bin` next to it.
+fn write_root(root: &json::Root, bin: Vec<u8>, output: GltfOutput, path: &Path) -> Result<Vec<PathBuf>, ExportError> {
+    let bin_path = path.with_extension("bin");
     match output {
-        Output::Standard => {
-            let _ = fs::create_dir("asteroid");
-
-            let writer = fs::File::create("asteroid/triangle.gltf").expect("I/O error");
-            json::serialize::to_writer_pretty(writer, &root).expect("Serialization error");
-
-            let bin = {
-                let mut data = to_padded_byte_vector(&vertices);
-                data.extend_from_slice(&to_padded_byte_vector(&colors));
-                data.extend_from_slice(&to_padded_byte_vector(&normals));
-                data.extend_from_slice(&to_padded_byte_vector(&indices));
-                data
-            };
-            let mut writer = fs::File::create("asteroid/buffer0.bin").expect("I/O error");
-            writer.write_all(&bin).expect("I/O error");
-            println!("Asteroid data written asteroid.glb");
+        GltfOutput::Standard => {
+            let writer = fs::File::create(path).map_err(io_error(path))?;
+            json::serialize::to_writer_pretty(writer, root).map_err(encoding_error)?;
+
+            let mut writer = fs::File::create(&bin_path).map_err(io_error(&bin_path))?;
+            writer.write_all(&bin).map_err(io_error(&bin_path))?;
+            Ok(vec![path.to_path_buf(), bin_path])
         }
-        Output::Binary => {
-            let json_string = json::serialize::to_string(&root).expect("Serialization error");
+        GltfOutput::Binary => {
+            let json_string = json::serialize::to_string(root).map_err(encoding_error)?;
             let mut json_offset = json_string.len();
             align_to_multiple_of_four(&mut json_offset);
             let glb = gltf::binary::Glb {
                 header: gltf::binary::Header {
                     magic: *b"glTF",
                     version: 2,
-                    length: (json_offset + buffer_length)
+                    length: (json_offset + bin.len())
                         .try_into()
-                        .expect("file size exceeds binary glTF limit"),
+                        .map_err(|_| ExportError::Encoding("file size exceeds binary glTF limit".to_string()))?,
                 },
-                bin: Some(Cow::Owned({
-                    let mut data = to_padded_byte_vector(&vertices);
-                    data.extend_from_slice(&to_padded_byte_vector(&colors));
-                    data.extend_from_slice(&to_padded_byte_vector(&normals));
-                    data.extend_from_slice(&to_padded_byte_vector(&indices));
-                    data
-                })),
+                bin: Some(Cow::Owned(bin)),
                 json: Cow::Owned(json_string.into_bytes()),
             };
-            let writer = std::fs::File::create("asteroid.glb").expect("I/O error");
-            glb.to_writer(writer).expect("glTF binary output error");
-            println!("Asteroid data written asteroid.glb");
+            let writer = std::fs::File::create(path).map_err(io_error(path))?;
+            glb.to_writer(writer).map_err(encoding_error)?;
+            Ok(vec![path.to_path_buf()])
         }
     }
 }
 
+/// Samples one loop of the spin. Returns the stream positions of the keyframe times and rotations.
+fn push_spin_streams(streams: &mut Vec<Stream>, spin: &SpinSettings) -> (usize, usize) {
+    let (times, rotations) = spin.get_keyframes();
+    let rotations: Vec<[f32; 4]> = rotations.iter().map(|rotation| rotation.to_array()).collect();
+    let duration = spin.get_loop_duration();
+
+    streams.push(Stream::animation(&times, json::accessor::Type::Scalar).with_bounds(([0.0], [duration])));
+    streams.push(Stream::animation(&rotations, json::accessor::Type::Vec4));
+    (streams.len() - 2, streams.len() - 1)
+}
+
+/// Adds the render mesh streams. When quantizing, returns the node translation and uniform scale
+/// that map the stored [-1, 1] positions back to the original size.
+fn push_asteroid_streams(
+    streams: &mut Vec<Stream>,
+    export_mesh: &ExportMesh,
+    quantize: bool,
+    compress: bool,
+) -> Result<(PrimitiveStreams, Option<([f32; 3], f32)>), ExportError> {
+    let (min, max) = bounding_coords(&export_mesh.positions);
+    let mut transform = None;
+
+    if quantize {
+        let center = [(min[0] + max[0]) / 2., (min[1] + max[1]) / 2., (min[2] + max[2]) / 2.];
+        let half_extent = (0..3).map(|i| (max[i] - min[i]) / 2.).fold(f32::EPSILON, f32::max);
+        transform = Some((center, half_extent));
+
+        let positions: Vec<[i16; 4]> = export_mesh
+            .positions
+            .iter()
+            .map(|&p| {
+                let [x, y, z] = [0, 1, 2].map(|i| quantize_snorm16((p[i] - center[i]) / half_extent));
+                [x, y, z, 0]
+            })
+            .collect();
+        // Accessor bounds are the stored component values, whether or not the accessor is normalized
+        let bounds = bounding_coords(
+            &positions
+                .iter()
+                .map(|q| [0, 1, 2].map(|i| q[i] as f32))
+                .collect::<Vec<_>>(),
+        );
+        streams.push(Stream::vertices(&positions, json::accessor::ComponentType::I16, true, compress)?.with_bounds(bounds));
+
+        let normals: Vec<[i8; 4]> = export_mesh
+            .normals
+            .iter()
+            .map(|&n| {
+                let [x, y, z] = n.map(quantize_snorm8);
+                [x, y, z, 0]
+            })
+            .collect();
+        streams.push(Stream::vertices(&normals, json::accessor::ComponentType::I8, true, compress)?);
+    } else {
+        streams.push(Stream::vertices(&export_mesh.positions, json::accessor::ComponentType::F32, false, compress)?.with_bounds((min, max)));
+        streams.push(Stream::vertices(&export_mesh.normals, json::accessor::ComponentType::F32, false, compress)?);
+    }
+    let positions = streams.len() - 2;
+    let normals = streams.len() - 1;
+
+    // Colors are only written when the mesh actually carries them
+    let colors = export_mesh.colors.as_ref().map(|colors| {
+        if quantize {
+            let colors: Vec<[u8; 4]> = colors
+                .iter()
+                .map(|&c| {
+                    let [r, g, b] = c.map(|value| (value.clamp(0., 1.) * u8::MAX as f32).round() as u8);
+                    [r, g, b, 0]
+                })
+                .collect();
+            streams.push(Stream::vertices(&colors, json::accessor::ComponentType::U8, true, compress)?);
+        } else {
+            streams.push(Stream::vertices(colors, json::accessor::ComponentType::F32, false, compress)?);
+        }
+        streams.len() - 1
+    });
+
+    streams.push(Stream::indices(&export_mesh.indices, export_mesh.positions.len(), compress)?);
+
+    let primitive_streams = PrimitiveStreams {
+        positions,
+        normals: Some(normals),
+        colors,
+        indices: streams.len() - 1,
+    };
+    Ok((primitive_streams, transform))
+}
+
+fn push_mesh(
+    root: &mut json::Root,
+    accessors: &[json::Index<json::Accessor>],
+    streams: &PrimitiveStreams,
+    material: Option<json::Index<json::Material>>,
+    name: &str,
+) -> json::Index<json::Mesh> {
+    let primitive = json::mesh::Primitive {
+        attributes: {
+            let mut map = std::collections::BTreeMap::new();
+            map.insert(Valid(json::mesh::Semantic::Positions), accessors[streams.positions]);
+            if let Some(normals) = streams.normals {
+                map.insert(Valid(json::mesh::Semantic::Normals), accessors[normals]);
+            }
+            if let Some(colors) = streams.colors {
+                map.insert(Valid(json::mesh::Semantic::Colors(0)), accessors[colors]);
+            }
+            map
+        },
+        extensions: Default::default(),
+        extras: Default::default(),
+        indices: Some(accessors[streams.indices]),
+        material,
+        mode: Valid(json::mesh::Mode::Triangles),
+        targets: None,
+    };
+
+    root.push(json::Mesh {
+        extensions: Default::default(),
+        extras: Default::default(),
+        name: Some(name.to_string()),
+        primitives: vec![primitive],
+        weights: None,
+    })
+}
+
+/// Reorders triangles for the post-transform vertex cache, then vertices in first-use order.
+fn optimize_mesh(export_mesh: &ExportMesh) -> ExportMesh {
+    let vertex_count = export_mesh.positions.len();
+    let mut indices = meshopt::optimize_vertex_cache(&export_mesh.indices, vertex_count);
+    // Optimizing a buffer of vertex ids gives the new vertex order, which is then applied to every attribute
+    let vertex_ids: Vec<u32> = (0..vertex_count as u32).collect();
+    let order = meshopt::optimize_vertex_fetch(&mut indices, &vertex_ids);
+
+    let reorder = |values: &[[f32; 3]]| order.iter().map(|&i| values[i as usize]).collect::<Vec<_>>();
+
+    ExportMesh {
+        positions: reorder(&export_mesh.positions),
+        normals: reorder(&export_mesh.normals),
+        indices,
+        colors: export_mesh.colors.as_deref().map(reorder),
+        scalars: export_mesh
+            .scalars
+            .iter()
+            .map(|(name, values)| (name.clone(), order.iter().map(|&i| values[i as usize]).collect()))
+            .collect(),
+    }
+}
+
+fn encoding_error(err: impl std::fmt::Display) -> ExportError {
+    ExportError::Encoding(err.to_string())
+}
+
+fn quantize_snorm16(value: f32) -> i16 {
+    (value.clamp(-1., 1.) * i16::MAX as f32).round() as i16
+}
+
+fn quantize_snorm8(value: f32) -> i8 {
+    (value.clamp(-1., 1.) * i8::MAX as f32).round() as i8
+}
+
 /// Calculate bounding coordinates of a list of vertices, used for the clipping distance of the model
 fn bounding_coords(points: &[[f32; 3]]) -> ([f32; 3], [f32; 3]) {
     let mut min = [f32::MAX, f32::MAX, f32::MAX];
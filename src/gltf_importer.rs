@@ -0,0 +1,86 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use bevy::app::{App, Plugin};
+use bevy::prelude::{Event, ResMut, Resource, Trigger};
+
+use crate::asteroid_metadata::AsteroidMetadata;
+use crate::compute::SPHERE_RESOLUTION;
+use crate::compute_events::ValueChanged;
+use crate::settings::asteroid_settings::AsteroidSettings;
+use crate::settings::spin_settings::SpinSettings;
+use crate::RngSeed;
+
+pub struct GlTFImporter;
+
+#[derive(Event)]
+pub struct OpenButtonClicked(pub PathBuf);
+
+/// Result of the last "Open glb…" action, shown in the settings window.
+#[derive(Resource, Default)]
+pub struct ImportStatus(pub String);
+
+impl Plugin for GlTFImporter {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(ImportStatus::default())
+            .add_event::<OpenButtonClicked>()
+            .observe(import_gltf);
+    }
+}
+
+fn import_gltf(
+    trigger: Trigger<OpenButtonClicked>,
+    mut settings: ResMut<AsteroidSettings>,
+    mut seed: ResMut<RngSeed>,
+    mut spin_settings: ResMut<SpinSettings>,
+    mut value_changed: ResMut<ValueChanged>,
+    mut import_status: ResMut<ImportStatus>,
+) {
+    let path = &trigger.event().0;
+
+    let metadata = match read_metadata(path) {
+        Ok(metadata) => metadata,
+        Err(err) => {
+            import_status.0 = format!("Could not open {}: {}", path.display(), err);
+            return;
+        }
+    };
+
+    *settings = metadata.settings;
+    seed.0 = metadata.seed;
+    *spin_settings = metadata.spin.unwrap_or_default();
+    // Re-upload every settings category so the asteroid is regenerated
+    *value_changed = ValueChanged::default();
+
+    import_status.0 = if metadata.resolution as usize != SPHERE_RESOLUTION {
+        format!(
+            "Loaded {} (made with {} {}), exported at resolution {} but regenerated at {}",
+            path.display(),
+            metadata.generator,
+            metadata.version,
+            metadata.resolution,
+            SPHERE_RESOLUTION
+        )
+    } else {
+        format!("Loaded {} (made with {} {})", path.display(), metadata.generator, metadata.version)
+    };
+}
+
+pub fn read_metadata(path: &Path) -> Result<AsteroidMetadata, String> {
+    // Only the asset extras are read, so required extensions such as meshopt compression don't matter
+    let data = fs::read(path).map_err(|err| err.to_string())?;
+    let gltf = gltf::Gltf::from_slice_without_validation(&data).map_err(|err| err.to_string())?;
+    let extras = gltf
+        .document
+        .as_json()
+        .asset
+        .extras
+        .as_ref()
+        .ok_or_else(|| "file has no AstroGen settings".to_string())?;
+
+    let metadata: AsteroidMetadata = serde_json::from_str(extras.get()).map_err(|err| err.to_string())?;
+    if metadata.generator != AsteroidMetadata::GENERATOR {
+        return Err(format!("file was made by {}", metadata.generator));
+    }
+    Ok(metadata)
+}
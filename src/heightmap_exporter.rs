@@ -0,0 +1,272 @@
+use std::f32::consts::PI;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::thread;
+
+use bevy::app::{App, Plugin};
+use bevy::math::Vec3;
+use bevy::prelude::{Res, ResMut, Trigger};
+use serde::Serialize;
+
+use crate::asteroid_metadata::AsteroidMetadata;
+use crate::export_error::io_error;
+use crate::export_mesh::ExportFormat;
+use crate::export_task::{ExportButtonClicked, ExportOutput, ExportStatus, ExportTaskPlugin};
+use crate::settings::asteroid_settings::AsteroidSettings;
+use crate::settings::export_settings::{ExportSettings, HeightmapLayout};
+use crate::settings::scale_settings::ScaleSettings;
+use crate::shape_evaluator::ShapeEvaluator;
+use crate::RngSeed;
+
+pub struct HeightmapExporter;
+
+impl Plugin for HeightmapExporter {
+    fn build(&self, app: &mut App) {
+        if !app.is_plugin_added::<ExportTaskPlugin>() {
+            app.add_plugins(ExportTaskPlugin);
+        }
+
+        app.init_resource::<ExportSettings>()
+            .init_resource::<ScaleSettings>()
+            .observe(export_heightmap);
+    }
+}
+
+/// Cube faces in OpenGL order, with the suffix appended to the file stem.
+const CUBE_FACES: [(&str, CubeFace); 6] = [
+    ("px", CubeFace::PositiveX),
+    ("nx", CubeFace::NegativeX),
+    ("py", CubeFace::PositiveY),
+    ("ny", CubeFace::NegativeY),
+    ("pz", CubeFace::PositiveZ),
+    ("nz", CubeFace::NegativeZ),
+];
+
+#[derive(Clone, Copy)]
+enum CubeFace {
+    PositiveX,
+    NegativeX,
+    PositiveY,
+    NegativeY,
+    PositiveZ,
+    NegativeZ,
+}
+
+/// Radial displacement sampled on a regular grid, row-major with the first row at the top.
+pub struct Heightmap {
+    pub width: u32,
+    pub height: u32,
+    pub values: Vec<f32>,
+}
+
+/// Written next to the images so the normalised PNG values can be mapped back to radius.
+#[derive(Serialize)]
+struct HeightmapInfo {
+    generator: &'static str,
+    layout: &'static str,
+    width: u32,
+    height: u32,
+    body_radius: f32,
+    unit: &'static str,
+    /// Displacement from the undisplaced sphere, `radius = body_radius + displacement`.
+    min_displacement: f32,
+    max_displacement: f32,
+    /// How pixel values map to displacement.
+    encoding: &'static str,
+    faces: Vec<String>,
+}
+
+fn export_heightmap(
+    trigger: Trigger<ExportButtonClicked>,
+    settings: Res<AsteroidSettings>,
+    export_settings: Res<ExportSettings>,
+    scale_settings: Res<ScaleSettings>,
+    seed: Res<RngSeed>,
+    mut export_status: ResMut<ExportStatus>,
+) {
+    let ev = trigger.event();
+    if !matches!(ev.format, ExportFormat::HeightmapPng | ExportFormat::HeightmapExr) {
+        return;
+    }
+
+    // The perturbation only moves vertices along the surface, so it has no effect on the radial field
+    let evaluator = ShapeEvaluator::new(&settings, seed.0, 0.0);
+    let resolution = export_settings.heightmap_resolution.max(1);
+    let layout = export_settings.heightmap_layout;
+    let scale_settings = scale_settings.clone();
+    let format = ev.format;
+    let path = ev.path.clone();
+
+    export_status.spawn(move || {
+        write_heightmaps(&evaluator, layout, resolution, &scale_settings, format, &path).map_err(io_error(&path))?;
+        Ok(ExportOutput::new(get_output_paths(layout, &path)))
+    });
+}
+
+/// Every file written for a heightmap export to `path`, including the JSON sidecar.
+pub fn get_output_paths(layout: HeightmapLayout, path: &Path) -> Vec<PathBuf> {
+    let mut paths = match layout {
+        HeightmapLayout::Equirectangular => vec![path.to_path_buf()],
+        HeightmapLayout::CubeMap => CUBE_FACES
+            .iter()
+            .map(|(suffix, _)| get_face_path(path, suffix))
+            .collect(),
+    };
+    paths.push(path.with_extension("json"));
+    paths
+}
+
+fn get_face_path(path: &Path, suffix: &str) -> PathBuf {
+    let stem = path.file_stem().map(|s| s.to_string_lossy()).unwrap_or_default();
+    let extension = path.extension().map(|s| s.to_string_lossy()).unwrap_or_default();
+    path.with_file_name(format!("{}_{}.{}", stem, suffix, extension))
+}
+
+pub fn write_heightmaps(
+    evaluator: &ShapeEvaluator,
+    layout: HeightmapLayout,
+    resolution: u32,
+    scale_settings: &ScaleSettings,
+    format: ExportFormat,
+    path: &Path,
+) -> std::io::Result<()> {
+    let mut maps: Vec<(PathBuf, Heightmap)> = match layout {
+        HeightmapLayout::Equirectangular => {
+            vec![(path.to_path_buf(), sample_equirectangular(evaluator, resolution))]
+        }
+        HeightmapLayout::CubeMap => CUBE_FACES
+            .iter()
+            .map(|&(suffix, face)| (get_face_path(path, suffix), sample_cube_face(evaluator, face, resolution)))
+            .collect(),
+    };
+
+    let scale = scale_settings.get_scale();
+    for (_, map) in &mut maps {
+        map.values.iter_mut().for_each(|value| *value *= scale);
+    }
+
+    // Shared range so cube faces stay continuous across seams
+    let (min, max) = maps
+        .iter()
+        .flat_map(|(_, map)| map.values.iter())
+        .fold((f32::MAX, f32::MIN), |(min, max), &value| (min.min(value), max.max(value)));
+
+    for (map_path, map) in &maps {
+        match format {
+            ExportFormat::HeightmapExr => write_exr(map, map_path)?,
+            _ => write_png16(map, map_path, min, max)?,
+        }
+    }
+
+    let info = HeightmapInfo {
+        generator: AsteroidMetadata::GENERATOR,
+        layout: match layout {
+            HeightmapLayout::Equirectangular => "equirectangular",
+            HeightmapLayout::CubeMap => "cube_map",
+        },
+        width: maps[0].1.width,
+        height: maps[0].1.height,
+        body_radius: scale_settings.body_radius,
+        unit: scale_settings.unit.label(),
+        min_displacement: min,
+        max_displacement: max,
+        encoding: match format {
+            ExportFormat::HeightmapExr => "displacement",
+            _ => "min_displacement + value / 65535 * (max_displacement - min_displacement)",
+        },
+        faces: maps
+            .iter()
+            .filter_map(|(map_path, _)| map_path.file_name())
+            .map(|name| name.to_string_lossy().into_owned())
+            .collect(),
+    };
+    let json = serde_json::to_string_pretty(&info).map_err(std::io::Error::other)?;
+    fs::write(path.with_extension("json"), json)
+}
+
+/// Longitude runs along x from -180° to 180° starting at -X, latitude along y from +Y to -Y.
+pub fn sample_equirectangular(evaluator: &ShapeEvaluator, resolution: u32) -> Heightmap {
+    let width = resolution * 2;
+    let height = resolution;
+
+    let values = sample_grid(evaluator, width, height, |x, y| {
+        let longitude = (x as f32 + 0.5) / width as f32 * 2.0 * PI - PI;
+        let latitude = PI / 2.0 - (y as f32 + 0.5) / height as f32 * PI;
+        Vec3::new(
+            latitude.cos() * longitude.cos(),
+            latitude.sin(),
+            latitude.cos() * longitude.sin(),
+        )
+    });
+
+    Heightmap { width, height, values }
+}
+
+/// Faces follow the OpenGL cube map convention, so they load directly as a cube texture.
+fn sample_cube_face(evaluator: &ShapeEvaluator, face: CubeFace, resolution: u32) -> Heightmap {
+    let values = sample_grid(evaluator, resolution, resolution, |x, y| {
+        let u = (x as f32 + 0.5) / resolution as f32 * 2.0 - 1.0;
+        let v = (y as f32 + 0.5) / resolution as f32 * 2.0 - 1.0;
+        let direction = match face {
+            CubeFace::PositiveX => Vec3::new(1.0, -v, -u),
+            CubeFace::NegativeX => Vec3::new(-1.0, -v, u),
+            CubeFace::PositiveY => Vec3::new(u, 1.0, v),
+            CubeFace::NegativeY => Vec3::new(u, -1.0, -v),
+            CubeFace::PositiveZ => Vec3::new(u, -v, 1.0),
+            CubeFace::NegativeZ => Vec3::new(-u, -v, -1.0),
+        };
+        direction.normalize()
+    });
+
+    Heightmap { width: resolution, height: resolution, values }
+}
+
+/// Evaluates the displacement for every pixel, splitting rows across all available cores.
+fn sample_grid(
+    evaluator: &ShapeEvaluator,
+    width: u32,
+    height: u32,
+    direction: impl Fn(u32, u32) -> Vec3 + Sync,
+) -> Vec<f32> {
+    let mut values = vec![0.0; (width * height) as usize];
+    let threads = thread::available_parallelism().map(|n| n.get()).unwrap_or(1);
+    let rows_per_chunk = (height as usize).div_ceil(threads).max(1);
+
+    thread::scope(|scope| {
+        for (chunk_index, chunk) in values.chunks_mut(rows_per_chunk * width as usize).enumerate() {
+            let direction = &direction;
+            scope.spawn(move || {
+                let first_row = chunk_index * rows_per_chunk;
+                for (i, value) in chunk.iter_mut().enumerate() {
+                    let x = (i % width as usize) as u32;
+                    let y = (first_row + i / width as usize) as u32;
+                    *value = evaluator.height(direction(x, y)) - 1.0;
+                }
+            });
+        }
+    });
+
+    values
+}
+
+fn write_png16(map: &Heightmap, path: &Path, min: f32, max: f32) -> std::io::Result<()> {
+    let range = if max > min { max - min } else { 1.0 };
+    let pixels: Vec<u16> = map
+        .values
+        .iter()
+        .map(|&value| (((value - min) / range).clamp(0.0, 1.0) * u16::MAX as f32).round() as u16)
+        .collect();
+
+    let image = image::ImageBuffer::<image::Luma<u16>, Vec<u16>>::from_raw(map.width, map.height, pixels)
+        .expect("pixel count matches the image size");
+    image.save(path).map_err(std::io::Error::other)
+}
+
+/// EXR has no single channel float variant in `image`, so the displacement is copied to RGB.
+fn write_exr(map: &Heightmap, path: &Path) -> std::io::Result<()> {
+    let pixels: Vec<f32> = map.values.iter().flat_map(|&value| [value; 3]).collect();
+
+    let image = image::Rgb32FImage::from_raw(map.width, map.height, pixels)
+        .expect("pixel count matches the image size");
+    image.save(path).map_err(std::io::Error::other)
+}
@@ -0,0 +1,72 @@
+//! Procedural asteroid generator.
+//!
+//! `generate` builds an asteroid mesh on the CPU from `AsteroidSettings`, a seed and a sphere resolution. The
+//! exporter modules write it to glTF, OBJ, PLY, STL or USD. `ProceduralAsteroidPlugin` generates asteroids inside
+//! a Bevy game, and the `viewer` module holds the plugins of the AstroGen app. The egui settings window and the
+//! file dialogs are behind the default `gui` feature.
+
+use bevy::prelude::Resource;
+
+mod asteroid_field;
+mod asteroid_material;
+mod asteroid_metadata;
+mod asteroid_mesh_builder;
+mod batch;
+#[cfg(feature = "gui")]
+mod batch_ui;
+mod collider;
+mod export_error;
+mod export_mesh;
+mod export_task;
+mod generation_jobs;
+mod generator;
+pub mod gltf_exporter;
+mod gltf_importer;
+pub mod heightmap_exporter;
+mod light;
+mod main_camera;
+mod mass_properties;
+mod mesh_quality;
+mod mesh_validation;
+pub mod obj_exporter;
+pub mod ply_exporter;
+mod procedural_asteroid;
+pub mod settings;
+mod shape_evaluator;
+mod sphere_mesh;
+pub mod stl_exporter;
+mod utils;
+#[cfg(feature = "gui")]
+mod ui_asteroid_settings;
+pub mod usd_exporter;
+pub mod viewer;
+mod compute;
+mod compute_shaders;
+mod compute_events;
+mod crater_grid;
+
+pub use asteroid_field::{build_field, AsteroidField, FieldInstance, FieldShape};
+pub use asteroid_metadata::AsteroidMetadata;
+pub use batch::{run_batch, BatchInputs};
+pub use collider::{build_colliders, ColliderMesh};
+pub use export_error::ExportError;
+pub use export_mesh::{ExportFormat, ExportMesh};
+pub use generation_jobs::{
+    GenerationJob, GenerationJobFinished, GenerationJobs, GenerationJobsPlugin, JobId, JobProgress, JobState,
+};
+pub use generator::{generate, generate_with_sphere, AsteroidMesh};
+pub use gltf_importer::read_metadata;
+pub use mass_properties::{compute_mass_properties, MassProperties};
+pub use mesh_validation::{
+    analyze_mesh_quality, repair_mesh, validate_mesh, MeshQualityReport, MeshValidationReport, TRIANGLE_DEGENERATE,
+    TRIANGLE_FLIPPED, TRIANGLE_NON_MANIFOLD, TRIANGLE_SELF_INTERSECTING,
+};
+pub use procedural_asteroid::{ProceduralAsteroid, ProceduralAsteroidPlugin};
+pub use settings::asteroid_settings::AsteroidSettings;
+pub use shape_evaluator::ShapeEvaluator;
+pub use sphere_mesh::SphereMesh;
+pub use utils::{derive_seed, PRNG};
+
+/// Seed of the asteroid shown in the viewer.
+#[derive(Resource)]
+pub struct RngSeed(pub u64);
@@ -4,29 +4,19 @@ use bevy_easy_compute::prelude::AppComputePlugin;
 use bevy_egui::EguiPlugin;
 use bevy_embedded_assets::{EmbeddedAssetPlugin, PluginMode};
 
-use crate::asteroid_mesh_builder::AsteroidMeshBuilderPlugin;
-use crate::compute::ComputePlugin;
-use crate::gltf_exporter::GlTFExporter;
-use crate::light::LightPlugin;
-use crate::main_camera::MainCameraPlugin;
-use crate::ui_asteroid_settings::UIAsteroidSettings;
-
-mod asteroid_mesh_builder;
-mod gltf_exporter;
-mod light;
-mod main_camera;
-mod settings;
-mod sphere_mesh;
-mod utils;
-mod ui_asteroid_settings;
-pub mod compute;
-pub mod compute_shaders;
-mod compute_events;
-
-#[derive(Resource)]
-struct RngSeed(u64);
+use astrogen::viewer::{
+    self, AsteroidFieldPlugin, AsteroidMaterialPlugin, AsteroidMeshBuilderPlugin, BatchPlugin, ComputePlugin,
+    ExportTaskPlugin, GlTFExporter, GlTFImporter, HeightmapExporter, LightPlugin, MainCameraPlugin,
+    MassPropertiesPlugin, MeshQualityPlugin, ObjExporter, PlyExporter, StlExporter, UIAsteroidSettings, UsdExporter,
+};
+use astrogen::RngSeed;
 
 fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.first().map(String::as_str) == Some("batch") {
+        std::process::exit(viewer::run_cli(&args[1..]));
+    }
+
     App::new()
         .add_plugins(EmbeddedAssetPlugin {
             mode: PluginMode::ReplaceDefault,
@@ -43,12 +33,26 @@ fn main() {
                       FrameTimeDiagnosticsPlugin,
                       AppComputePlugin,
                       ComputePlugin,
+                      AsteroidMaterialPlugin,
                       AsteroidMeshBuilderPlugin,
+                      ExportTaskPlugin,
                       GlTFExporter,
                       MainCameraPlugin,
                       LightPlugin,
                       UIAsteroidSettings,
         ))
+        .add_plugins((
+            ObjExporter,
+            PlyExporter,
+            StlExporter,
+            UsdExporter,
+            GlTFImporter,
+            HeightmapExporter,
+            MassPropertiesPlugin,
+            AsteroidFieldPlugin,
+            BatchPlugin,
+            MeshQualityPlugin,
+        ))
         .insert_resource(RngSeed(2))
         .run();
 }
@@ -0,0 +1,233 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use bevy::app::{App, Plugin, Update};
+use bevy::math::{DMat3, DVec3};
+use bevy::prelude::{ResMut, Resource, Trigger};
+use bevy::tasks::{block_on, futures_lite::future, AsyncComputeTaskPool, Task};
+use serde::Serialize;
+
+use crate::compute_events::MeshDataAfterCompute;
+use crate::export_error::{io_error, ExportError};
+use crate::generator::AsteroidMesh;
+use crate::settings::mass_settings::MassSettings;
+
+pub struct MassPropertiesPlugin;
+
+impl Plugin for MassPropertiesPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<MassSettings>()
+            .insert_resource(AsteroidMassProperties::default())
+            .insert_resource(MassPropertiesJob::default())
+            .observe(queue_mass_properties)
+            .add_systems(Update, update_mass_properties);
+    }
+}
+
+/// Mass properties of the current asteroid in generator units at unit density.
+#[derive(Resource, Default)]
+pub struct AsteroidMassProperties(pub Option<MassProperties>);
+
+/// Physical properties of a closed triangle mesh. Exported values are in SI units: kg, m² and m³.
+#[derive(Clone, Debug, Serialize)]
+pub struct MassProperties {
+    pub density: f32,
+    pub volume: f32,
+    pub surface_area: f32,
+    pub mass: f32,
+    pub center_of_mass: [f32; 3],
+    /// Inertia tensor about the centre of mass, row-major.
+    pub inertia_tensor: [[f32; 3]; 3],
+}
+
+impl MassProperties {
+    /// Converts from generator units, e.g. to metres with `ScaleSettings::get_scale_in_metres`.
+    pub fn scaled(&self, scale: f32) -> MassProperties {
+        let scale_3 = scale.powi(3);
+        MassProperties {
+            density: self.density,
+            volume: self.volume * scale_3,
+            surface_area: self.surface_area * scale * scale,
+            mass: self.mass * scale_3,
+            center_of_mass: self.center_of_mass.map(|value| value * scale),
+            inertia_tensor: self.inertia_tensor.map(|row| row.map(|value| value * scale_3 * scale * scale)),
+        }
+    }
+
+    /// Mass and inertia scale linearly with a uniform density.
+    pub fn with_density(&self, density: f32) -> MassProperties {
+        let scale = density / self.density;
+        MassProperties {
+            density,
+            mass: self.mass * scale,
+            inertia_tensor: self.inertia_tensor.map(|row| row.map(|value| value * scale)),
+            ..self.clone()
+        }
+    }
+}
+
+/// Integration of the latest compute result in the background. Results that arrive while one runs
+/// replace each other and the latest is integrated next.
+#[derive(Resource, Default)]
+struct MassPropertiesJob {
+    pending: Option<Arc<AsteroidMesh>>,
+    task: Option<Task<MassProperties>>,
+}
+
+fn queue_mass_properties(trigger: Trigger<MeshDataAfterCompute>, mut job: ResMut<MassPropertiesJob>) {
+    let asteroid_mesh = &trigger.event().0;
+    if !asteroid_mesh.positions.is_empty() {
+        job.pending = Some(asteroid_mesh.clone());
+    }
+}
+
+fn update_mass_properties(mut job: ResMut<MassPropertiesJob>, mut mass_properties: ResMut<AsteroidMassProperties>) {
+    let job = &mut *job;
+
+    if let Some(task) = job.task.as_mut() {
+        let Some(properties) = block_on(future::poll_once(task)) else {
+            return;
+        };
+        job.task = None;
+        mass_properties.0 = Some(properties);
+    }
+
+    if let Some(asteroid_mesh) = job.pending.take() {
+        let task = AsyncComputeTaskPool::get().spawn(async move { asteroid_mesh.get_mass_properties(1.0) });
+        job.task = Some(task);
+    }
+}
+
+/// Writes the `.mass.json` sidecar of the file at `path` and returns its path. Exporters call it once their
+/// own file is written, so a failed export leaves no sidecar behind.
+pub fn write_mass_properties(properties: &MassProperties, path: &Path) -> Result<PathBuf, ExportError> {
+    let mass_path = get_mass_properties_path(path);
+    let json = serde_json::to_string_pretty(properties).map_err(|err| ExportError::Encoding(err.to_string()))?;
+    fs::write(&mass_path, json).map_err(io_error(&mass_path))?;
+    Ok(mass_path)
+}
+
+/// `asteroid.glb` becomes `asteroid.mass.json`.
+pub fn get_mass_properties_path(path: &Path) -> PathBuf {
+    path.with_extension("mass.json")
+}
+
+/// Integrates over the signed tetrahedra formed by each triangle and the origin (divergence theorem).
+/// The mesh must be closed with outward facing, counter-clockwise triangles.
+pub fn compute_mass_properties(positions: &[[f32; 3]], indices: &[u32], density: f32) -> MassProperties {
+    // Second moment of the canonical tetrahedron (0, e1, e2, e3)
+    let canonical = DMat3::from_cols_array(&[2., 1., 1., 1., 2., 1., 1., 1., 2.]) * (1. / 120.);
+
+    let mut volume = 0.0;
+    let mut surface_area = 0.0;
+    let mut first_moment = DVec3::ZERO;
+    let mut covariance = DMat3::ZERO;
+
+    for triangle in indices.chunks_exact(3) {
+        let [a, b, c] = [0, 1, 2].map(|i| DVec3::from(positions[triangle[i] as usize].map(f64::from)));
+
+        surface_area += (b - a).cross(c - a).length() * 0.5;
+
+        let determinant = a.dot(b.cross(c));
+        volume += determinant / 6.;
+        first_moment += determinant / 24. * (a + b + c);
+
+        let basis = DMat3::from_cols(a, b, c);
+        covariance += determinant * basis * canonical * basis.transpose();
+    }
+
+    let density = density as f64;
+    let center_of_mass = if volume.abs() > f64::EPSILON { first_moment / volume } else { DVec3::ZERO };
+
+    // Parallel axis theorem, moving the second moment from the origin to the centre of mass
+    let covariance = covariance - volume * outer_product(center_of_mass, center_of_mass);
+    let inertia = (DMat3::from_diagonal(DVec3::splat(trace(covariance))) - covariance) * density;
+
+    MassProperties {
+        density: density as f32,
+        volume: volume as f32,
+        surface_area: surface_area as f32,
+        mass: (volume * density) as f32,
+        center_of_mass: center_of_mass.as_vec3().to_array(),
+        inertia_tensor: inertia.transpose().to_cols_array_2d().map(|row| row.map(|value| value as f32)),
+    }
+}
+
+fn outer_product(a: DVec3, b: DVec3) -> DMat3 {
+    DMat3::from_cols(a * b.x, a * b.y, a * b.z)
+}
+
+fn trace(matrix: DMat3) -> f64 {
+    matrix.x_axis.x + matrix.y_axis.y + matrix.z_axis.z
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f32::consts::PI;
+
+    use super::*;
+    use crate::sphere_mesh::SphereMesh;
+
+    /// Unit cube from `offset` to `offset + 1`, outward facing and counter-clockwise.
+    fn cube(offset: [f32; 3]) -> (Vec<[f32; 3]>, Vec<u32>) {
+        let positions = (0..8)
+            .map(|i| [i & 1, (i >> 1) & 1, (i >> 2) & 1].map(|bit| bit as f32))
+            .map(|corner| [0, 1, 2].map(|axis| corner[axis] + offset[axis]))
+            .collect();
+        let indices = vec![
+            0, 2, 1, 1, 2, 3, // -z
+            4, 5, 6, 5, 7, 6, // +z
+            0, 1, 4, 1, 5, 4, // -y
+            2, 6, 3, 3, 6, 7, // +y
+            0, 4, 2, 2, 4, 6, // -x
+            1, 3, 5, 3, 7, 5, // +x
+        ];
+        (positions, indices)
+    }
+
+    fn assert_close(actual: f32, expected: f32, tolerance: f32) {
+        assert!((actual - expected).abs() <= tolerance, "{} is not within {} of {}", actual, tolerance, expected);
+    }
+
+    #[test]
+    fn unit_cube() {
+        let (positions, indices) = cube([2.0, 3.0, 4.0]);
+        let properties = compute_mass_properties(&positions, &indices, 2.0);
+
+        assert_close(properties.volume, 1.0, 1e-5);
+        assert_close(properties.surface_area, 6.0, 1e-5);
+        assert_close(properties.mass, 2.0, 1e-5);
+        for (value, expected) in properties.center_of_mass.into_iter().zip([2.5, 3.5, 4.5]) {
+            assert_close(value, expected, 1e-5);
+        }
+        // m (a² + b²) / 12 about the centre of mass, no products of inertia
+        for (row, values) in properties.inertia_tensor.iter().enumerate() {
+            for (column, &value) in values.iter().enumerate() {
+                assert_close(value, if row == column { 2.0 / 6.0 } else { 0.0 }, 1e-4);
+            }
+        }
+    }
+
+    #[test]
+    fn sphere_mesh() {
+        let sphere = SphereMesh::new(50);
+        let positions: Vec<[f32; 3]> = sphere.vertices.iter().map(|v| v.to_array()).collect();
+        let properties = compute_mass_properties(&positions, &sphere.indices, 1.0);
+
+        // The inscribed polyhedron is slightly smaller than the unit sphere
+        let volume = 4.0 / 3.0 * PI;
+        assert_close(properties.volume, volume, volume * 1e-3);
+        assert_close(properties.surface_area, 4.0 * PI, 4.0 * PI * 1e-3);
+        for value in properties.center_of_mass {
+            assert_close(value, 0.0, 1e-5);
+        }
+        // 2/5 m r² on the diagonal
+        for (row, values) in properties.inertia_tensor.iter().enumerate() {
+            for (column, &value) in values.iter().enumerate() {
+                let expected = if row == column { 0.4 * volume } else { 0.0 };
+                assert_close(value, expected, 0.4 * volume * 1e-3);
+            }
+        }
+    }
+}
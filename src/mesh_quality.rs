@@ -0,0 +1,174 @@
+use std::sync::Arc;
+
+use bevy::prelude::*;
+use bevy::render::mesh::PrimitiveTopology;
+use bevy::render::primitives::Aabb;
+use bevy::render::render_asset::RenderAssetUsages;
+use bevy::tasks::{block_on, futures_lite::future, AsyncComputeTaskPool, Task};
+
+use crate::asteroid_mesh_builder::Asteroid;
+use crate::compute_events::MeshDataAfterCompute;
+use crate::export_error::ExportError;
+use crate::export_mesh::ExportMesh;
+use crate::generator::AsteroidMesh;
+use crate::mesh_validation::{analyze_mesh_quality, repair_mesh, MeshQualityReport};
+use crate::settings::mesh_quality_settings::{ExportQualityAction, MeshQualitySettings};
+use crate::shape_evaluator::compute_normals;
+
+/// Analyses every compute result in the background and highlights flipped, self-intersecting, degenerate and
+/// non-manifold triangles over the asteroid.
+pub struct MeshQualityPlugin;
+
+impl Plugin for MeshQualityPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<MeshQualitySettings>()
+            .insert_resource(MeshQuality::default())
+            .observe(queue_mesh_analysis)
+            .add_systems(Update, (analyze_asteroid_mesh, show_quality_highlight).chain());
+    }
+}
+
+/// Report of the displayed asteroid. Only one analysis runs at a time, results that arrive meanwhile
+/// replace each other and the latest is analysed next.
+#[derive(Resource, Default)]
+pub struct MeshQuality {
+    pub report: Option<MeshQualityReport>,
+    pending: Option<Arc<AsteroidMesh>>,
+    task: Option<Task<(Arc<AsteroidMesh>, MeshQualityReport)>>,
+}
+
+impl MeshQuality {
+    pub fn is_running(&self) -> bool {
+        self.task.is_some() || self.pending.is_some()
+    }
+}
+
+/// Child of the asteroid holding only the triangles with issues.
+#[derive(Component)]
+struct QualityHighlight;
+
+/// Pushed outwards so the highlight draws over the surface it copies.
+const HIGHLIGHT_OFFSET: f32 = 1.002;
+
+fn queue_mesh_analysis(trigger: Trigger<MeshDataAfterCompute>, mut mesh_quality: ResMut<MeshQuality>) {
+    let asteroid_mesh = &trigger.event().0;
+    if !asteroid_mesh.positions.is_empty() {
+        mesh_quality.pending = Some(asteroid_mesh.clone());
+    }
+}
+
+fn analyze_asteroid_mesh(
+    mut commands: Commands,
+    mut mesh_quality: ResMut<MeshQuality>,
+    asteroid_query: Query<Entity, With<Asteroid>>,
+    highlight_query: Query<(Entity, &Handle<Mesh>), With<QualityHighlight>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    let mesh_quality = &mut *mesh_quality;
+
+    if let Some(task) = mesh_quality.task.as_mut() {
+        let Some((asteroid_mesh, report)) = block_on(future::poll_once(task)) else {
+            return;
+        };
+        mesh_quality.task = None;
+
+        let highlight_mesh = build_highlight_mesh(&asteroid_mesh.positions, &asteroid_mesh.indices, &report);
+        if let Ok((entity, mesh_handle)) = highlight_query.get_single() {
+            meshes.insert(mesh_handle, highlight_mesh);
+            commands.entity(entity).remove::<Aabb>();
+        } else if let Ok(asteroid) = asteroid_query.get_single() {
+            commands
+                .spawn((
+                    PbrBundle {
+                        mesh: meshes.add(highlight_mesh),
+                        material: materials.add(highlight_material()),
+                        visibility: Visibility::Hidden,
+                        ..default()
+                    },
+                    QualityHighlight,
+                ))
+                .set_parent(asteroid);
+        }
+        mesh_quality.report = Some(report);
+    }
+
+    if let Some(asteroid_mesh) = mesh_quality.pending.take() {
+        let task = AsyncComputeTaskPool::get().spawn(async move {
+            let report = analyze_mesh_quality(&asteroid_mesh.positions, &asteroid_mesh.indices);
+            (asteroid_mesh, report)
+        });
+        mesh_quality.task = Some(task);
+    }
+}
+
+fn show_quality_highlight(
+    mut highlight_query: Query<&mut Visibility, With<QualityHighlight>>,
+    settings: Res<MeshQualitySettings>,
+    mesh_quality: Res<MeshQuality>,
+) {
+    let has_issues = mesh_quality.report.as_ref().is_some_and(|report| !report.is_clean());
+    for mut visibility in highlight_query.iter_mut() {
+        let target = if settings.highlight && has_issues {
+            Visibility::Inherited
+        } else {
+            Visibility::Hidden
+        };
+        if *visibility != target {
+            *visibility = target;
+        }
+    }
+}
+
+/// Unindexed copy of the triangles with any issue flag.
+fn build_highlight_mesh(positions: &[Vec3], indices: &[u32], report: &MeshQualityReport) -> Mesh {
+    let highlighted: Vec<Vec3> = indices
+        .chunks_exact(3)
+        .zip(&report.triangle_flags)
+        .filter(|(_, &flags)| flags != 0)
+        .flat_map(|(triangle, _)| triangle.iter().map(|&i| positions[i as usize] * HIGHLIGHT_OFFSET))
+        .collect();
+
+    Mesh::new(PrimitiveTopology::TriangleList, RenderAssetUsages::RENDER_WORLD | RenderAssetUsages::MAIN_WORLD)
+        .with_inserted_attribute(Mesh::ATTRIBUTE_POSITION, highlighted)
+}
+
+fn highlight_material() -> StandardMaterial {
+    StandardMaterial {
+        base_color: Color::srgb(1.0, 0.1, 0.6),
+        unlit: true,
+        // Flipped triangles face inwards, draw both sides
+        cull_mode: None,
+        double_sided: true,
+        depth_bias: 100.0,
+        ..default()
+    }
+}
+
+/// Applies `settings.on_export` to a mesh about to be written, call it before scaling or converting axes.
+/// Returns the notes to show with the export messages.
+pub fn check_export_mesh(export_mesh: &mut ExportMesh, settings: &MeshQualitySettings) -> Result<Vec<String>, ExportError> {
+    let mut positions: Vec<Vec3> = export_mesh.positions.iter().map(|&p| Vec3::from(p)).collect();
+    let report = analyze_mesh_quality(&positions, &export_mesh.indices);
+    if report.is_clean() {
+        return Ok(Vec::new());
+    }
+
+    match settings.on_export {
+        ExportQualityAction::Warn => Ok(report
+            .issues()
+            .into_iter()
+            .map(|issue| format!("Mesh quality: {}", issue))
+            .collect()),
+        ExportQualityAction::Block => Err(ExportError::PoorMeshQuality(report.issues())),
+        ExportQualityAction::Repair => {
+            let report = repair_mesh(&mut positions, &mut export_mesh.indices, settings.max_repair_iterations);
+            let normals = compute_normals(&positions, &export_mesh.indices);
+            export_mesh.set_surface(&positions, &normals);
+
+            let mut notes = vec!["Mesh quality: repaired folded triangles".to_string()];
+            notes.extend(report.issues().into_iter().map(|issue| format!("Mesh quality after repair: {}", issue)));
+            Ok(notes)
+        }
+    }
+}
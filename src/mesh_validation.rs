@@ -0,0 +1,505 @@
+use std::collections::HashMap;
+
+use bevy::math::{UVec3, Vec3};
+
+/// Result of checking a triangle mesh for watertightness and manifoldness.
+#[derive(Clone, Debug, Default)]
+pub struct MeshValidationReport {
+    /// Edges used by only one triangle, i.e. holes in the surface.
+    pub boundary_edges: usize,
+    /// Edges shared by more than two triangles.
+    pub non_manifold_edges: usize,
+    /// Edges whose two triangles traverse them in the same direction, i.e. flipped winding.
+    pub inconsistent_edges: usize,
+    /// Triangles with repeated indices or (near) zero area.
+    pub degenerate_triangles: usize,
+}
+
+impl MeshValidationReport {
+    pub fn is_watertight(&self) -> bool {
+        self.boundary_edges == 0
+    }
+
+    pub fn is_manifold(&self) -> bool {
+        self.non_manifold_edges == 0 && self.inconsistent_edges == 0
+    }
+
+    pub fn is_valid(&self) -> bool {
+        self.is_watertight() && self.is_manifold() && self.degenerate_triangles == 0
+    }
+
+    pub fn issues(&self) -> Vec<String> {
+        let mut issues = Vec::new();
+        if self.boundary_edges > 0 {
+            issues.push(format!("Not watertight: {} open edges", self.boundary_edges));
+        }
+        if self.non_manifold_edges > 0 {
+            issues.push(format!("{} edges are shared by more than two triangles", self.non_manifold_edges));
+        }
+        if self.inconsistent_edges > 0 {
+            issues.push(format!("{} edges have inconsistent triangle winding", self.inconsistent_edges));
+        }
+        if self.degenerate_triangles > 0 {
+            issues.push(format!("{} degenerate triangles", self.degenerate_triangles));
+        }
+        issues
+    }
+}
+
+/// Counts how each undirected edge is used. A closed, consistently oriented manifold
+/// uses every edge exactly twice, once in each direction.
+pub fn validate_mesh(positions: &[Vec3], indices: &[u32]) -> MeshValidationReport {
+    let mut report = MeshValidationReport {
+        degenerate_triangles: indices
+            .chunks_exact(3)
+            .filter(|triangle| is_degenerate(positions, triangle))
+            .count(),
+        ..Default::default()
+    };
+
+    for &(forward, backward) in count_edge_uses(indices).values() {
+        match forward + backward {
+            1 => report.boundary_edges += 1,
+            2 if forward != 1 => report.inconsistent_edges += 1,
+            2 => {}
+            _ => report.non_manifold_edges += 1,
+        }
+    }
+
+    report
+}
+
+/// Flags in `MeshQualityReport::triangle_flags`.
+pub const TRIANGLE_FLIPPED: u8 = 1;
+pub const TRIANGLE_SELF_INTERSECTING: u8 = 2;
+pub const TRIANGLE_DEGENERATE: u8 = 4;
+pub const TRIANGLE_NON_MANIFOLD: u8 = 8;
+
+/// Result of checking a displaced sphere for folds, see `analyze_mesh_quality`.
+#[derive(Clone, Debug, Default)]
+pub struct MeshQualityReport {
+    /// Triangles facing the centre instead of away from it.
+    pub flipped_triangles: usize,
+    /// Triangles crossing another triangle they share no vertex with.
+    pub self_intersecting_triangles: usize,
+    /// Triangles with repeated indices or (near) zero area.
+    pub degenerate_triangles: usize,
+    /// Edges not used exactly twice in opposite directions, open and inconsistent edges included.
+    pub non_manifold_edges: usize,
+    /// `TRIANGLE_*` flags of each analysed triangle.
+    pub triangle_flags: Vec<u8>,
+}
+
+impl MeshQualityReport {
+    pub fn is_clean(&self) -> bool {
+        self.flipped_triangles == 0
+            && self.self_intersecting_triangles == 0
+            && self.degenerate_triangles == 0
+            && self.non_manifold_edges == 0
+    }
+
+    pub fn issues(&self) -> Vec<String> {
+        let mut issues = Vec::new();
+        if self.flipped_triangles > 0 {
+            issues.push(format!("{} triangles are flipped towards the centre", self.flipped_triangles));
+        }
+        if self.self_intersecting_triangles > 0 {
+            issues.push(format!("{} triangles intersect the surface", self.self_intersecting_triangles));
+        }
+        if self.degenerate_triangles > 0 {
+            issues.push(format!("{} degenerate triangles", self.degenerate_triangles));
+        }
+        if self.non_manifold_edges > 0 {
+            issues.push(format!("{} non-manifold edges", self.non_manifold_edges));
+        }
+        issues
+    }
+}
+
+/// Checks a mesh that is star-shaped around the origin, like every generated asteroid, for folded and broken
+/// triangles. A triangle is flipped when its winding normal points towards the origin.
+pub fn analyze_mesh_quality(positions: &[Vec3], indices: &[u32]) -> MeshQualityReport {
+    let edge_uses = count_edge_uses(indices);
+    let is_manifold_edge = |from: u32, to: u32| edge_uses.get(&(from.min(to), from.max(to))) == Some(&(1, 1));
+
+    let mut triangle_flags = vec![0; indices.len() / 3];
+    for (flags, triangle) in triangle_flags.iter_mut().zip(indices.chunks_exact(3)) {
+        if is_degenerate(positions, triangle) {
+            *flags |= TRIANGLE_DEGENERATE;
+            continue;
+        }
+
+        let [a, b, c] = triangle_positions(positions, triangle);
+        if (b - a).cross(c - a).dot(a + b + c) < 0.0 {
+            *flags |= TRIANGLE_FLIPPED;
+        }
+        if triangle_edges(triangle).iter().any(|&(from, to)| !is_manifold_edge(from, to)) {
+            *flags |= TRIANGLE_NON_MANIFOLD;
+        }
+    }
+
+    for (flags, intersecting) in triangle_flags.iter_mut().zip(find_self_intersections(positions, indices)) {
+        if intersecting {
+            *flags |= TRIANGLE_SELF_INTERSECTING;
+        }
+    }
+
+    let count = |flag: u8| triangle_flags.iter().filter(|&&flags| flags & flag != 0).count();
+    MeshQualityReport {
+        flipped_triangles: count(TRIANGLE_FLIPPED),
+        self_intersecting_triangles: count(TRIANGLE_SELF_INTERSECTING),
+        degenerate_triangles: count(TRIANGLE_DEGENERATE),
+        non_manifold_edges: edge_uses.values().filter(|&&uses| uses != (1, 1)).count(),
+        triangle_flags,
+    }
+}
+
+/// Unfolds a displaced sphere: the vertices of flipped, self-intersecting and collapsed triangles are moved to
+/// the direction of their neighbours' centroid, at the neighbours' mean distance from the origin, until none are
+/// left or `max_iterations` rounds have run. Triangles with repeated indices are dropped, non-manifold edges are left
+/// as they are. Returns the report of the repaired mesh.
+pub fn repair_mesh(positions: &mut [Vec3], indices: &mut Vec<u32>, max_iterations: u32) -> MeshQualityReport {
+    const REPAIRED: u8 = TRIANGLE_FLIPPED | TRIANGLE_SELF_INTERSECTING | TRIANGLE_DEGENERATE;
+
+    *indices = indices
+        .chunks_exact(3)
+        .filter(|triangle| !has_repeated_indices(triangle))
+        .flatten()
+        .copied()
+        .collect();
+
+    let mut neighbours = vec![Vec::new(); positions.len()];
+    for triangle in indices.chunks_exact(3) {
+        for (from, to) in triangle_edges(triangle) {
+            neighbours[from as usize].push(to);
+            neighbours[to as usize].push(from);
+        }
+    }
+
+    let mut report = analyze_mesh_quality(positions, indices);
+    for _ in 0..max_iterations {
+        let mut vertices: Vec<u32> = indices
+            .chunks_exact(3)
+            .zip(&report.triangle_flags)
+            .filter(|(_, &flags)| flags & REPAIRED != 0)
+            .flat_map(|(triangle, _)| triangle.iter().copied())
+            .collect();
+        if vertices.is_empty() {
+            break;
+        }
+        vertices.sort_unstable();
+        vertices.dedup();
+
+        // All targets are taken before moving any vertex, so the result doesn't depend on the vertex order
+        let targets: Vec<Vec3> = vertices
+            .iter()
+            .map(|&vertex| {
+                let vertex_neighbours = &neighbours[vertex as usize];
+                if vertex_neighbours.is_empty() {
+                    return positions[vertex as usize];
+                }
+                let count = vertex_neighbours.len() as f32;
+                let centroid = vertex_neighbours.iter().map(|&n| positions[n as usize]).sum::<Vec3>() / count;
+                let radius = vertex_neighbours.iter().map(|&n| positions[n as usize].length()).sum::<f32>() / count;
+                centroid.normalize_or_zero() * radius
+            })
+            .collect();
+        for (&vertex, target) in vertices.iter().zip(targets) {
+            positions[vertex as usize] = target;
+        }
+
+        report = analyze_mesh_quality(positions, indices);
+    }
+
+    report
+}
+
+const MIN_AREA: f32 = 1e-12;
+
+/// (min, max) vertex pair -> (uses as min -> max, uses as max -> min).
+/// Triangles with repeated indices have no proper edges and are skipped.
+fn count_edge_uses(indices: &[u32]) -> HashMap<(u32, u32), (u32, u32)> {
+    let mut edges: HashMap<(u32, u32), (u32, u32)> = HashMap::with_capacity(indices.len());
+    for triangle in indices.chunks_exact(3) {
+        if has_repeated_indices(triangle) {
+            continue;
+        }
+        for (from, to) in triangle_edges(triangle) {
+            let entry = edges.entry((from.min(to), from.max(to))).or_default();
+            if from < to {
+                entry.0 += 1;
+            } else {
+                entry.1 += 1;
+            }
+        }
+    }
+    edges
+}
+
+fn triangle_edges(triangle: &[u32]) -> [(u32, u32); 3] {
+    [(triangle[0], triangle[1]), (triangle[1], triangle[2]), (triangle[2], triangle[0])]
+}
+
+fn triangle_positions(positions: &[Vec3], triangle: &[u32]) -> [Vec3; 3] {
+    [
+        positions[triangle[0] as usize],
+        positions[triangle[1] as usize],
+        positions[triangle[2] as usize],
+    ]
+}
+
+fn has_repeated_indices(triangle: &[u32]) -> bool {
+    triangle[0] == triangle[1] || triangle[1] == triangle[2] || triangle[2] == triangle[0]
+}
+
+fn is_degenerate(positions: &[Vec3], triangle: &[u32]) -> bool {
+    if has_repeated_indices(triangle) {
+        return true;
+    }
+    let [a, b, c] = triangle_positions(positions, triangle);
+    (b - a).cross(c - a).length() * 0.5 <= MIN_AREA
+}
+
+/// Marks the triangles that cross another triangle. Triangles are binned into a grid of cells about one
+/// triangle across, and each pair sharing a cell but not an edge is tested once, in the first cell both cover.
+fn find_self_intersections(positions: &[Vec3], indices: &[u32]) -> Vec<bool> {
+    const MAX_CELLS_PER_AXIS: u32 = 1 << 20;
+
+    let triangle_count = indices.len() / 3;
+    let mut intersecting = vec![false; triangle_count];
+    let triangles: Vec<usize> = (0..triangle_count)
+        .filter(|&i| !is_degenerate(positions, &indices[i * 3..i * 3 + 3]))
+        .collect();
+    if triangles.is_empty() {
+        return intersecting;
+    }
+
+    let bounds: Vec<(Vec3, Vec3)> = indices
+        .chunks_exact(3)
+        .map(|triangle| {
+            let [a, b, c] = triangle_positions(positions, triangle);
+            (a.min(b).min(c), a.max(b).max(c))
+        })
+        .collect();
+    let (mesh_min, mesh_max) = triangles.iter().fold(
+        (Vec3::splat(f32::MAX), Vec3::splat(f32::MIN)),
+        |(min, max), &i| (min.min(bounds[i].0), max.max(bounds[i].1)),
+    );
+    let mean_size = triangles.iter().map(|&i| (bounds[i].1 - bounds[i].0).max_element()).sum::<f32>()
+        / triangles.len() as f32;
+    let cell_size = mean_size
+        .max((mesh_max - mesh_min).max_element() / MAX_CELLS_PER_AXIS as f32)
+        .max(f32::MIN_POSITIVE);
+
+    let cell_of = |p: Vec3| ((p - mesh_min) / cell_size).as_uvec3().min(UVec3::splat(MAX_CELLS_PER_AXIS - 1));
+    let cell_key = |cell: UVec3| ((cell.x as u64) << 42) | ((cell.y as u64) << 21) | cell.z as u64;
+
+    let mut entries: Vec<(u64, u32)> = Vec::with_capacity(triangles.len() * 2);
+    for &i in &triangles {
+        let (min, max) = (cell_of(bounds[i].0), cell_of(bounds[i].1));
+        for x in min.x..=max.x {
+            for y in min.y..=max.y {
+                for z in min.z..=max.z {
+                    entries.push((cell_key(UVec3::new(x, y, z)), i as u32));
+                }
+            }
+        }
+    }
+    entries.sort_unstable();
+
+    let mut cell_start = 0;
+    while cell_start < entries.len() {
+        let key = entries[cell_start].0;
+        let cell_end = cell_start + entries[cell_start..].iter().take_while(|entry| entry.0 == key).count();
+        let cell = &entries[cell_start..cell_end];
+        cell_start = cell_end;
+
+        for (n, &(_, i)) in cell.iter().enumerate() {
+            let i = i as usize;
+            for &(_, j) in &cell[n + 1..] {
+                let j = j as usize;
+                let (min, max) = (bounds[i].0.max(bounds[j].0), bounds[i].1.min(bounds[j].1));
+                if min.cmpgt(max).any() || cell_key(cell_of(min)) != key {
+                    continue;
+                }
+
+                let (triangle_i, triangle_j) = (&indices[i * 3..i * 3 + 3], &indices[j * 3..j * 3 + 3]);
+                let (positions_i, positions_j) =
+                    (triangle_positions(positions, triangle_i), triangle_positions(positions, triangle_j));
+                let shared_count = triangle_i.iter().filter(|vertex| triangle_j.contains(vertex)).count();
+                let is_intersecting = match shared_count {
+                    0 => triangles_intersect(positions_i, positions_j),
+                    1 => {
+                        let shared_i = triangle_i.iter().position(|vertex| triangle_j.contains(vertex)).unwrap();
+                        let shared_j = triangle_j.iter().position(|&vertex| vertex == triangle_i[shared_i]).unwrap();
+                        corner_triangles_intersect(positions_i, shared_i, positions_j, shared_j)
+                    }
+                    // Neighbours across an edge meet along it by construction
+                    _ => false,
+                };
+                if is_intersecting {
+                    intersecting[i] = true;
+                    intersecting[j] = true;
+                }
+            }
+        }
+    }
+
+    intersecting
+}
+
+/// Whether an edge of either triangle crosses the other. Overlaps of two triangles in the same plane are not
+/// detected.
+fn triangles_intersect(a: [Vec3; 3], b: [Vec3; 3]) -> bool {
+    (0..3).any(|k| segment_hits_triangle(a[k], a[(k + 1) % 3], b) || segment_hits_triangle(b[k], b[(k + 1) % 3], a))
+}
+
+/// Whether two triangles that share only their corners `shared_a` and `shared_b` cross. Their intersection runs
+/// from the shared vertex to the edge opposite it in one of the triangles, so only those two edges are tested.
+fn corner_triangles_intersect(a: [Vec3; 3], shared_a: usize, b: [Vec3; 3], shared_b: usize) -> bool {
+    segment_hits_triangle(a[(shared_a + 1) % 3], a[(shared_a + 2) % 3], b)
+        || segment_hits_triangle(b[(shared_b + 1) % 3], b[(shared_b + 2) % 3], a)
+}
+
+/// Möller–Trumbore intersection of the segment `start..end` with a triangle.
+fn segment_hits_triangle(start: Vec3, end: Vec3, [a, b, c]: [Vec3; 3]) -> bool {
+    let direction = end - start;
+    let (edge_1, edge_2) = (b - a, c - a);
+    let h = direction.cross(edge_2);
+    let det = edge_1.dot(h);
+    // Parallel to the triangle's plane, relative to the segment and triangle size
+    if det.abs() <= f32::EPSILON * direction.length() * edge_1.cross(edge_2).length() {
+        return false;
+    }
+
+    let inv_det = 1.0 / det;
+    let s = start - a;
+    let u = s.dot(h) * inv_det;
+    if !(0.0..=1.0).contains(&u) {
+        return false;
+    }
+    let q = s.cross(edge_1);
+    let v = direction.dot(q) * inv_det;
+    if v < 0.0 || u + v > 1.0 {
+        return false;
+    }
+    (0.0..=1.0).contains(&(edge_2.dot(q) * inv_det))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sphere_mesh::SphereMesh;
+
+    /// Faces 0 to 3 meet at the top vertex 0, faces 4 to 7 at the bottom vertex 5. Vertex 3 is `+X`.
+    fn octahedron() -> (Vec<Vec3>, Vec<u32>) {
+        let sphere = SphereMesh::new(0);
+        (sphere.vertices, sphere.indices)
+    }
+
+    fn flagged(report: &MeshQualityReport, flag: u8) -> Vec<usize> {
+        (0..report.triangle_flags.len()).filter(|&i| report.triangle_flags[i] & flag != 0).collect()
+    }
+
+    #[test]
+    fn clean_octahedron() {
+        let (positions, indices) = octahedron();
+        assert!(analyze_mesh_quality(&positions, &indices).is_clean());
+    }
+
+    #[test]
+    fn top_pulled_below_the_equator_flips_its_faces() {
+        let (mut positions, indices) = octahedron();
+        positions[0] = Vec3::new(0.0, -0.5, 0.0);
+
+        let report = analyze_mesh_quality(&positions, &indices);
+        assert_eq!(report.triangle_flags, [TRIANGLE_FLIPPED, TRIANGLE_FLIPPED, TRIANGLE_FLIPPED, TRIANGLE_FLIPPED, 0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn top_pulled_through_the_side_intersects() {
+        let (mut positions, indices) = octahedron();
+        positions[0] = Vec3::new(-2.0, 0.0, -2.0);
+
+        let report = analyze_mesh_quality(&positions, &indices);
+        assert_eq!(report.flipped_triangles, 0);
+        assert_eq!(flagged(&report, TRIANGLE_SELF_INTERSECTING), [1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn triangles_sharing_a_vertex_can_intersect() {
+        let fan = |offset: f32| {
+            vec![
+                Vec3::ZERO,
+                Vec3::new(2.0, 0.0, 0.0),
+                Vec3::new(0.0, 2.0, 0.0),
+                Vec3::new(offset, offset, -1.0),
+                Vec3::new(offset, offset, 1.0),
+            ]
+        };
+        let indices = [0, 1, 2, 0, 3, 4];
+
+        // The far edge of the second triangle pierces the first one
+        let report = analyze_mesh_quality(&fan(0.5), &indices);
+        assert_eq!(flagged(&report, TRIANGLE_SELF_INTERSECTING), [0, 1]);
+
+        let report = analyze_mesh_quality(&fan(-0.5), &indices);
+        assert_eq!(report.self_intersecting_triangles, 0);
+    }
+
+    #[test]
+    fn top_collapsed_onto_a_side_vertex_is_degenerate() {
+        let (mut positions, indices) = octahedron();
+        positions[0] = positions[3];
+
+        let report = analyze_mesh_quality(&positions, &indices);
+        assert_eq!(flagged(&report, TRIANGLE_DEGENERATE), [1, 2]);
+    }
+
+    #[test]
+    fn missing_face_is_non_manifold() {
+        let (positions, mut indices) = octahedron();
+        indices.truncate(21);
+
+        let report = analyze_mesh_quality(&positions, &indices);
+        assert_eq!(report.non_manifold_edges, 3);
+        assert_eq!(flagged(&report, TRIANGLE_NON_MANIFOLD), [3, 4, 6]);
+    }
+
+    #[test]
+    fn repair_unfolds_displaced_vertices() {
+        let sphere = SphereMesh::new(2);
+        let vertex = 6;
+        let triangle = sphere.indices.chunks_exact(3).find(|triangle| triangle.contains(&vertex)).unwrap();
+        let neighbour = triangle.iter().copied().find(|&index| index != vertex).unwrap();
+        let original = sphere.vertices[vertex as usize];
+        let displacements = [
+            ("pushed through the centre", original * -0.5),
+            ("collapsed onto a neighbour", sphere.vertices[neighbour as usize]),
+            ("dragged sideways", (original + original.cross(Vec3::new(0.3, 0.7, 0.2)).normalize() * 0.9).normalize()),
+        ];
+
+        for (name, displaced) in displacements {
+            let (mut positions, mut indices) = (sphere.vertices.clone(), sphere.indices.clone());
+            positions[vertex as usize] = displaced;
+            assert!(!analyze_mesh_quality(&positions, &indices).is_clean(), "{} is already clean", name);
+
+            let report = repair_mesh(&mut positions, &mut indices, 10);
+            assert!(report.is_clean(), "{} is not repaired: {:?}", name, report.issues());
+            assert_eq!(indices, sphere.indices);
+            assert!(positions[vertex as usize].distance(original) < 0.1, "{} moved to {}", name, positions[vertex as usize]);
+        }
+    }
+
+    #[test]
+    fn repair_drops_collapsed_triangles_and_keeps_holes() {
+        let (mut positions, mut indices) = octahedron();
+        indices[1] = indices[0];
+
+        let report = repair_mesh(&mut positions, &mut indices, 10);
+        assert_eq!(indices.len(), 21);
+        assert_eq!(report.degenerate_triangles, 0);
+        assert_eq!(report.non_manifold_edges, 3);
+        assert_eq!(positions, octahedron().0);
+    }
+}
@@ -0,0 +1,147 @@
+use std::fs;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+use bevy::app::{App, Plugin};
+use bevy::asset::{Assets, Handle};
+use bevy::color::{ColorToComponents, Srgba};
+use bevy::prelude::{Mesh, Query, Res, ResMut, Trigger, With};
+
+use crate::asteroid_mesh_builder::Asteroid;
+use crate::export_error::io_error;
+use crate::export_mesh::{ExportFormat, ExportMesh};
+use crate::export_task::{ExportButtonClicked, ExportOutput, ExportStatus, ExportTaskPlugin};
+use crate::mass_properties::write_mass_properties;
+use crate::mesh_quality::check_export_mesh;
+use crate::settings::asteroid_settings::AsteroidSettings;
+use crate::settings::material_settings::MaterialSettings;
+use crate::settings::mass_settings::MassSettings;
+use crate::settings::mesh_quality_settings::MeshQualitySettings;
+use crate::settings::scale_settings::ScaleSettings;
+
+pub struct ObjExporter;
+
+impl Plugin for ObjExporter {
+    fn build(&self, app: &mut App) {
+        if !app.is_plugin_added::<ExportTaskPlugin>() {
+            app.add_plugins(ExportTaskPlugin);
+        }
+
+        app.init_resource::<ScaleSettings>()
+            .init_resource::<MassSettings>()
+            .init_resource::<MeshQualitySettings>()
+            .observe(export_obj);
+    }
+}
+
+const MATERIAL_NAME: &str = "asteroid";
+
+fn export_obj(
+    trigger: Trigger<ExportButtonClicked>,
+    asteroid_query: Query<&Handle<Mesh>, With<Asteroid>>,
+    meshes: Res<Assets<Mesh>>,
+    settings: Res<AsteroidSettings>,
+    scale_settings: Res<ScaleSettings>,
+    quality_settings: Res<MeshQualitySettings>,
+    mass_settings: Res<MassSettings>,
+    mut export_status: ResMut<ExportStatus>,
+) {
+    let ev = trigger.event();
+    if ev.format != ExportFormat::Obj {
+        return;
+    }
+
+    let mut export_mesh = match ExportMesh::from_asteroid(&asteroid_query, &meshes) {
+        Ok(export_mesh) => export_mesh,
+        Err(err) => {
+            export_status.fail(err);
+            return;
+        }
+    };
+
+    let obj_path = ev.path.clone();
+    let mtl_path = obj_path.with_extension("mtl");
+    let material_settings = settings.material_settings.clone();
+    let scale_settings = scale_settings.clone();
+    let quality_settings = quality_settings.clone();
+    let density = mass_settings.density;
+
+    export_status.spawn(move || {
+        let notes = check_export_mesh(&mut export_mesh, &quality_settings)?;
+        let mass_properties = export_mesh.get_mass_properties(density).scaled(scale_settings.get_scale_in_metres());
+        export_mesh.scale(scale_settings.get_scale());
+        export_mesh.convert_axes(&scale_settings);
+        write_obj(&export_mesh, &obj_path, &mtl_path, &material_settings, &scale_settings).map_err(io_error(&obj_path))?;
+        let mass_path = write_mass_properties(&mass_properties, &obj_path)?;
+        Ok(ExportOutput::new(vec![obj_path, mtl_path, mass_path]).with_notes(notes))
+    });
+}
+
+pub fn write_obj(
+    export_mesh: &ExportMesh,
+    obj_path: &Path,
+    mtl_path: &Path,
+    material_settings: &MaterialSettings,
+    scale_settings: &ScaleSettings,
+) -> std::io::Result<()> {
+    write_mtl(mtl_path, material_settings)?;
+
+    let mut writer = BufWriter::new(fs::File::create(obj_path)?);
+    writeln!(writer, "# AstroGen asteroid")?;
+    writeln!(
+        writer,
+        "# units: {}, up axis: {:?}, {:?}-handed",
+        scale_settings.unit.label(),
+        scale_settings.up_axis,
+        scale_settings.handedness
+    )?;
+    if let Some(mtl_name) = mtl_path.file_name() {
+        writeln!(writer, "mtllib {}", mtl_name.to_string_lossy())?;
+    }
+    writeln!(writer, "o asteroid")?;
+
+    match &export_mesh.colors {
+        // Vertex colors follow the position as the widely supported `v x y z r g b` extension
+        Some(colors) => {
+            for (&[x, y, z], &[r, g, b]) in export_mesh.positions.iter().zip(colors) {
+                writeln!(writer, "v {} {} {} {} {} {}", x, y, z, r, g, b)?;
+            }
+        }
+        None => {
+            for &[x, y, z] in &export_mesh.positions {
+                writeln!(writer, "v {} {} {}", x, y, z)?;
+            }
+        }
+    }
+
+    for &[x, y, z] in &export_mesh.normals {
+        writeln!(writer, "vn {} {} {}", x, y, z)?;
+    }
+
+    writeln!(writer, "usemtl {}", MATERIAL_NAME)?;
+    for triangle in export_mesh.indices.chunks_exact(3) {
+        // OBJ indices are 1-based
+        let (a, b, c) = (triangle[0] + 1, triangle[1] + 1, triangle[2] + 1);
+        writeln!(writer, "f {a}//{a} {b}//{b} {c}//{c}")?;
+    }
+
+    writer.flush()
+}
+
+fn write_mtl(path: &Path, material_settings: &MaterialSettings) -> std::io::Result<()> {
+    let [r, g, b] = Srgba::from(material_settings.get_base_color()).to_f32_array_no_alpha();
+    // Blinn-Phong exponent approximation for viewers that ignore the PBR extension
+    let shininess = (1.0 - material_settings.roughness).powi(2) * 1000.0;
+
+    let mut writer = BufWriter::new(fs::File::create(path)?);
+    writeln!(writer, "# AstroGen asteroid material")?;
+    writeln!(writer, "newmtl {}", MATERIAL_NAME)?;
+    writeln!(writer, "Kd {} {} {}", r, g, b)?;
+    writeln!(writer, "Ka 0 0 0")?;
+    writeln!(writer, "Ks {0} {0} {0}", 0.16 * material_settings.reflectance * material_settings.reflectance)?;
+    writeln!(writer, "Ns {}", shininess)?;
+    writeln!(writer, "Pr {}", material_settings.roughness)?;
+    writeln!(writer, "Pm {}", material_settings.metallic)?;
+    writeln!(writer, "illum 2")?;
+    writer.flush()
+}
@@ -0,0 +1,179 @@
+use std::fs;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+use bevy::app::{App, Plugin};
+use bevy::asset::{Assets, Handle};
+use bevy::color::{Color, ColorToComponents, Srgba};
+use bevy::prelude::{Mesh, Query, Res, ResMut, Trigger, With};
+
+use crate::asteroid_mesh_builder::Asteroid;
+use crate::export_error::io_error;
+use crate::export_mesh::{ExportFormat, ExportMesh};
+use crate::export_task::{ExportButtonClicked, ExportOutput, ExportStatus, ExportTaskPlugin};
+use crate::mass_properties::write_mass_properties;
+use crate::mesh_quality::check_export_mesh;
+use crate::settings::asteroid_settings::AsteroidSettings;
+use crate::settings::mass_settings::MassSettings;
+use crate::settings::mesh_quality_settings::MeshQualitySettings;
+use crate::settings::scale_settings::ScaleSettings;
+
+pub struct PlyExporter;
+
+impl Plugin for PlyExporter {
+    fn build(&self, app: &mut App) {
+        if !app.is_plugin_added::<ExportTaskPlugin>() {
+            app.add_plugins(ExportTaskPlugin);
+        }
+
+        app.init_resource::<ScaleSettings>()
+            .init_resource::<MassSettings>()
+            .init_resource::<MeshQualitySettings>()
+            .observe(export_ply);
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum PlyEncoding {
+    Ascii,
+    BinaryLittleEndian,
+}
+
+fn export_ply(
+    trigger: Trigger<ExportButtonClicked>,
+    asteroid_query: Query<&Handle<Mesh>, With<Asteroid>>,
+    meshes: Res<Assets<Mesh>>,
+    settings: Res<AsteroidSettings>,
+    scale_settings: Res<ScaleSettings>,
+    quality_settings: Res<MeshQualitySettings>,
+    mass_settings: Res<MassSettings>,
+    mut export_status: ResMut<ExportStatus>,
+) {
+    let ev = trigger.event();
+    let encoding = match ev.format {
+        ExportFormat::PlyAscii => PlyEncoding::Ascii,
+        ExportFormat::PlyBinary => PlyEncoding::BinaryLittleEndian,
+        _ => return,
+    };
+
+    let mut export_mesh = match ExportMesh::from_asteroid(&asteroid_query, &meshes) {
+        Ok(export_mesh) => export_mesh,
+        Err(err) => {
+            export_status.fail(err);
+            return;
+        }
+    };
+
+    let path = ev.path.clone();
+    let base_color = settings.material_settings.get_base_color();
+    let scale_settings = scale_settings.clone();
+    let quality_settings = quality_settings.clone();
+    let density = mass_settings.density;
+
+    export_status.spawn(move || {
+        let notes = check_export_mesh(&mut export_mesh, &quality_settings)?;
+        let mass_properties = export_mesh.get_mass_properties(density).scaled(scale_settings.get_scale_in_metres());
+        export_mesh.scale(scale_settings.get_scale());
+        export_mesh.convert_axes(&scale_settings);
+        write_ply(&export_mesh, &path, encoding, base_color, &scale_settings).map_err(io_error(&path))?;
+        let mass_path = write_mass_properties(&mass_properties, &path)?;
+        Ok(ExportOutput::new(vec![path, mass_path]).with_notes(notes))
+    });
+}
+
+/// Writes positions, normals, 8-bit sRGB colors and every custom scalar attribute as vertex properties.
+/// Meshes without vertex colors get the material base color on every vertex.
+pub fn write_ply(
+    export_mesh: &ExportMesh,
+    path: &Path,
+    encoding: PlyEncoding,
+    base_color: Color,
+    scale_settings: &ScaleSettings,
+) -> std::io::Result<()> {
+    let colors: Vec<[u8; 3]> = match &export_mesh.colors {
+        Some(colors) => colors
+            .iter()
+            .map(|&[r, g, b]| to_srgb_u8(Color::linear_rgb(r, g, b)))
+            .collect(),
+        None => vec![to_srgb_u8(base_color); export_mesh.positions.len()],
+    };
+
+    let mut writer = BufWriter::new(fs::File::create(path)?);
+
+    writeln!(writer, "ply")?;
+    match encoding {
+        PlyEncoding::Ascii => writeln!(writer, "format ascii 1.0")?,
+        PlyEncoding::BinaryLittleEndian => writeln!(writer, "format binary_little_endian 1.0")?,
+    }
+    writeln!(writer, "comment AstroGen asteroid")?;
+    writeln!(
+        writer,
+        "comment units: {}, up axis: {:?}, {:?}-handed",
+        scale_settings.unit.label(),
+        scale_settings.up_axis,
+        scale_settings.handedness
+    )?;
+    writeln!(writer, "element vertex {}", export_mesh.positions.len())?;
+    for property in ["x", "y", "z", "nx", "ny", "nz"] {
+        writeln!(writer, "property float {}", property)?;
+    }
+    for property in ["red", "green", "blue"] {
+        writeln!(writer, "property uchar {}", property)?;
+    }
+    for (name, _) in &export_mesh.scalars {
+        writeln!(writer, "property float {}", name)?;
+    }
+    writeln!(writer, "element face {}", export_mesh.triangle_count())?;
+    writeln!(writer, "property list uchar uint vertex_indices")?;
+    writeln!(writer, "end_header")?;
+
+    for i in 0..export_mesh.positions.len() {
+        let [x, y, z] = export_mesh.positions[i];
+        let [nx, ny, nz] = export_mesh.normals[i];
+        let [r, g, b] = colors[i];
+
+        match encoding {
+            PlyEncoding::Ascii => {
+                write!(writer, "{} {} {} {} {} {} {} {} {}", x, y, z, nx, ny, nz, r, g, b)?;
+                for (_, values) in &export_mesh.scalars {
+                    write!(writer, " {}", values[i])?;
+                }
+                writeln!(writer)?;
+            }
+            PlyEncoding::BinaryLittleEndian => {
+                for value in [x, y, z, nx, ny, nz] {
+                    writer.write_all(&value.to_le_bytes())?;
+                }
+                writer.write_all(&[r, g, b])?;
+                for (_, values) in &export_mesh.scalars {
+                    writer.write_all(&values[i].to_le_bytes())?;
+                }
+            }
+        }
+    }
+
+    for triangle in export_mesh.indices.chunks_exact(3) {
+        match encoding {
+            PlyEncoding::Ascii => {
+                writeln!(writer, "3 {} {} {}", triangle[0], triangle[1], triangle[2])?;
+            }
+            PlyEncoding::BinaryLittleEndian => {
+                writer.write_all(&[3u8])?;
+                for &index in triangle {
+                    writer.write_all(&index.to_le_bytes())?;
+                }
+            }
+        }
+    }
+
+    writer.flush()
+}
+
+fn to_srgb_u8(color: Color) -> [u8; 3] {
+    let [r, g, b] = Srgba::from(color).to_f32_array_no_alpha();
+    [
+        (r.clamp(0.0, 1.0) * 255.0).round() as u8,
+        (g.clamp(0.0, 1.0) * 255.0).round() as u8,
+        (b.clamp(0.0, 1.0) * 255.0).round() as u8,
+    ]
+}
@@ -0,0 +1,105 @@
+use std::collections::HashMap;
+
+use bevy::prelude::*;
+
+use crate::asteroid_material::base_material;
+use crate::generation_jobs::{GenerationJobFinished, GenerationJobs, GenerationJobsPlugin, JobId};
+use crate::generator::AsteroidMesh;
+use crate::settings::asteroid_settings::AsteroidSettings;
+
+/// Generates a mesh and material for every `ProceduralAsteroid` entity, again whenever the component changes.
+///
+/// Generation goes through `GenerationJobsPlugin`, which is added with its defaults unless the app added it first.
+pub struct ProceduralAsteroidPlugin;
+
+impl Plugin for ProceduralAsteroidPlugin {
+    fn build(&self, app: &mut App) {
+        if !app.is_plugin_added::<GenerationJobsPlugin>() {
+            app.add_plugins(GenerationJobsPlugin::default());
+        }
+
+        app.insert_resource(AsteroidJobs::default())
+            .observe(attach_generated_asteroid)
+            .add_systems(Update, queue_procedural_asteroids);
+    }
+}
+
+/// Spawn with a `SpatialBundle`, the plugin inserts the mesh and material handles once generated.
+#[derive(Component, Clone)]
+pub struct ProceduralAsteroid {
+    pub settings: AsteroidSettings,
+    pub seed: u64,
+    /// Sphere mesh subdivisions, see `SphereMesh::new`.
+    pub resolution: u32,
+}
+
+/// Entity each running job generates for.
+#[derive(Resource, Default)]
+struct AsteroidJobs(HashMap<JobId, Entity>);
+
+impl AsteroidJobs {
+    /// Cancels the jobs still generating for `entity`.
+    fn cancel(&mut self, entity: Entity, jobs: &mut GenerationJobs) {
+        self.0.retain(|id, owner| {
+            if *owner == entity {
+                jobs.cancel(*id);
+            }
+            *owner != entity
+        });
+    }
+}
+
+fn queue_procedural_asteroids(
+    query: Query<(Entity, &ProceduralAsteroid), Changed<ProceduralAsteroid>>,
+    mut removed: RemovedComponents<ProceduralAsteroid>,
+    mut jobs: ResMut<GenerationJobs>,
+    mut asteroid_jobs: ResMut<AsteroidJobs>,
+) {
+    // Despawned entities or removed components, nothing is left to attach the mesh to
+    for entity in removed.read() {
+        asteroid_jobs.cancel(entity, &mut jobs);
+    }
+
+    for (entity, asteroid) in query.iter() {
+        asteroid_jobs.cancel(entity, &mut jobs);
+
+        let id = jobs.submit(asteroid.settings.clone(), asteroid.seed, asteroid.resolution);
+        asteroid_jobs.0.insert(id, entity);
+    }
+}
+
+fn attach_generated_asteroid(
+    trigger: Trigger<GenerationJobFinished>,
+    mut commands: Commands,
+    mut asteroid_jobs: ResMut<AsteroidJobs>,
+    asteroids: Query<&ProceduralAsteroid>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    let ev = trigger.event();
+    let Some(entity) = asteroid_jobs.0.remove(&ev.id) else {
+        return;
+    };
+
+    if let Ok(asteroid) = asteroids.get(entity) {
+        attach_asteroid(&mut commands, entity, asteroid, &ev.mesh, &mut meshes, &mut materials);
+    }
+}
+
+fn attach_asteroid(
+    commands: &mut Commands,
+    entity: Entity,
+    asteroid: &ProceduralAsteroid,
+    asteroid_mesh: &AsteroidMesh,
+    meshes: &mut Assets<Mesh>,
+    materials: &mut Assets<StandardMaterial>,
+) {
+    let mesh = asteroid_mesh.to_mesh();
+    let mut material_settings = asteroid.settings.material_settings.clone();
+    material_settings.sanitize();
+    let material = base_material(&material_settings);
+
+    if let Some(mut entity_commands) = commands.get_entity(entity) {
+        entity_commands.insert((meshes.add(mesh), materials.add(material)));
+    }
+}
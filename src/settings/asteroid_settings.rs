@@ -1,9 +1,14 @@
 use bevy::prelude::{default, Resource};
+use bevy::reflect::Reflect;
+use serde::{Deserialize, Serialize};
 use crate::settings::crater_settings::CraterSettings;
+use crate::settings::material_settings::MaterialSettings;
 use crate::settings::ridge_noise_settings::RidgeNoiseSettings;
 use crate::settings::simple_noise_settings::SimpleNoiseSettings;
+use crate::settings::validation::ValidationReport;
 
-#[derive(Resource)]
+#[derive(Resource, Reflect, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct AsteroidSettings
 {
     pub peturb_strength: f32,
@@ -11,6 +16,7 @@ pub struct AsteroidSettings
     pub simple_noise_settings: SimpleNoiseSettings,
     pub ridge_noise_settings: RidgeNoiseSettings,
     pub ridge_noise_settings2: RidgeNoiseSettings,
+    pub material_settings: MaterialSettings,
 }
 
 impl Default for AsteroidSettings {
@@ -56,6 +62,108 @@ impl Default for AsteroidSettings {
                 peak_smoothing: 1.5,
                 ..default()
             },
+            material_settings: MaterialSettings::default(),
         }
     }
 }
+
+impl AsteroidSettings {
+    /// Checks every nested settings struct, field paths start at `AsteroidSettings`.
+    pub fn validate(&self) -> ValidationReport {
+        let mut report = ValidationReport::default();
+
+        if !self.peturb_strength.is_finite() {
+            report.error("peturb_strength", "is not a number");
+        } else if !(0.0..=1.0).contains(&self.peturb_strength) {
+            report.warn("peturb_strength", "is outside 0 to 1");
+        }
+        report.extend_nested("crater_settings", self.crater_settings.validate());
+        report.extend_nested("simple_noise_settings", self.simple_noise_settings.validate());
+        report.extend_nested("ridge_noise_settings", self.ridge_noise_settings.validate());
+        report.extend_nested("ridge_noise_settings2", self.ridge_noise_settings2.validate());
+        report.extend_nested("material_settings", self.material_settings.validate());
+        report
+    }
+
+    /// Fixes everything `validate` reports as an error, run before settings reach the GPU or CPU generator.
+    pub fn sanitize(&mut self) {
+        if !self.peturb_strength.is_finite() {
+            self.peturb_strength = 0.0;
+        }
+        self.crater_settings.sanitize();
+        self.simple_noise_settings.sanitize();
+        self.ridge_noise_settings.sanitize();
+        self.ridge_noise_settings2.sanitize();
+        self.material_settings.sanitize();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::settings::crater_settings::{MAX_CRATER, MIN_CRATER_VALUE};
+    use crate::settings::simple_noise_settings::MAX_NOISE_LAYERS;
+    use crate::settings::validation::Severity;
+
+    /// Fields with an error, in report order.
+    fn errors(settings: &AsteroidSettings) -> Vec<String> {
+        settings
+            .validate()
+            .issues
+            .into_iter()
+            .filter(|issue| issue.severity == Severity::Error)
+            .map(|issue| issue.field)
+            .collect()
+    }
+
+    #[test]
+    fn defaults_are_valid() {
+        assert!(errors(&AsteroidSettings::default()).is_empty());
+    }
+
+    #[test]
+    fn nan_is_reported_and_replaced() {
+        let mut settings = AsteroidSettings::default();
+        settings.peturb_strength = f32::NAN;
+        settings.crater_settings.smooth_min = f32::NAN;
+        settings.ridge_noise_settings2.offset_x = f32::INFINITY;
+        assert_eq!(
+            errors(&settings),
+            ["peturb_strength", "crater_settings.smooth_min", "ridge_noise_settings2.offset_x"]
+        );
+
+        settings.sanitize();
+        assert!(errors(&settings).is_empty());
+        assert_eq!(settings.peturb_strength, 0.0);
+        // Zero would divide by zero in the shader, so it ends up at the minimum
+        assert_eq!(settings.crater_settings.smooth_min, MIN_CRATER_VALUE);
+        assert_eq!(settings.ridge_noise_settings2.offset_x, 0.0);
+    }
+
+    #[test]
+    fn too_many_craters_are_capped() {
+        let mut settings = AsteroidSettings::default();
+        settings.crater_settings.num_craters = MAX_CRATER as f32 + 1.0;
+        assert_eq!(errors(&settings), ["crater_settings.num_craters"]);
+
+        settings.sanitize();
+        assert!(errors(&settings).is_empty());
+        assert_eq!(settings.crater_settings.num_craters, MAX_CRATER as f32);
+    }
+
+    #[test]
+    fn noise_layers_are_clamped() {
+        let mut settings = AsteroidSettings::default();
+        settings.simple_noise_settings.num_layers = -1.0;
+        settings.ridge_noise_settings.num_layers = MAX_NOISE_LAYERS + 1.0;
+        assert_eq!(
+            errors(&settings),
+            ["simple_noise_settings.num_layers", "ridge_noise_settings.num_layers"]
+        );
+
+        settings.sanitize();
+        assert!(errors(&settings).is_empty());
+        assert_eq!(settings.simple_noise_settings.num_layers, 0.0);
+        assert_eq!(settings.ridge_noise_settings.num_layers, MAX_NOISE_LAYERS);
+    }
+}
@@ -0,0 +1,101 @@
+use bevy::prelude::Resource;
+use bevy::reflect::Reflect;
+use serde::{Deserialize, Serialize};
+
+use crate::export_mesh::ExportFormat;
+
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq, Reflect, Serialize, Deserialize)]
+pub enum ManifestFormat {
+    #[default]
+    Json,
+    Csv,
+}
+
+impl ManifestFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ManifestFormat::Json => "json",
+            ManifestFormat::Csv => "csv",
+        }
+    }
+}
+
+/// One swept setting, addressed by its reflection path, e.g. `simple_noise_settings.scale`.
+#[derive(Debug, Reflect, Clone, Serialize, Deserialize)]
+pub struct GridAxis {
+    pub path: String,
+    pub min: f32,
+    pub max: f32,
+    pub steps: u32,
+}
+
+impl GridAxis {
+    /// Evenly spaced values from `min` to `max`, both included.
+    pub fn get_values(&self) -> Vec<f32> {
+        match self.steps {
+            0 => Vec::new(),
+            1 => vec![self.min],
+            steps => (0..steps)
+                .map(|i| self.min + (self.max - self.min) * i as f32 / (steps - 1) as f32)
+                .collect(),
+        }
+    }
+}
+
+/// Generates `count` seeds, each exported once per combination of the grid values.
+#[derive(Resource, Debug, Reflect, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BatchSettings {
+    pub output_dir: String,
+    pub format: ExportFormat,
+    pub manifest: ManifestFormat,
+    pub seed_start: u64,
+    pub count: u32,
+    pub grid: Vec<GridAxis>,
+    /// Sphere mesh subdivisions, the viewer uses `SPHERE_RESOLUTION`.
+    pub resolution: u32,
+}
+
+impl Default for BatchSettings {
+    fn default() -> Self {
+        BatchSettings {
+            output_dir: "exports/batch".to_string(),
+            format: ExportFormat::Glb,
+            manifest: ManifestFormat::Json,
+            seed_start: 0,
+            count: 10,
+            grid: Vec::new(),
+            resolution: 100,
+        }
+    }
+}
+
+impl BatchSettings {
+    /// Formats that only need the mesh, the ones batch mode can write.
+    pub const FORMATS: [ExportFormat; 4] = [
+        ExportFormat::Glb,
+        ExportFormat::Obj,
+        ExportFormat::PlyBinary,
+        ExportFormat::Usda,
+    ];
+
+    /// Every combination of grid values, a single empty combination without a grid.
+    pub fn get_grid_combinations(&self) -> Vec<Vec<f32>> {
+        self.grid.iter().fold(vec![Vec::new()], |combinations, axis| {
+            combinations
+                .iter()
+                .flat_map(|combination| {
+                    axis.get_values().into_iter().map(move |value| {
+                        let mut combination = combination.clone();
+                        combination.push(value);
+                        combination
+                    })
+                })
+                .collect()
+        })
+    }
+
+    pub fn get_total(&self) -> usize {
+        self.count as usize * self.get_grid_combinations().len()
+    }
+}
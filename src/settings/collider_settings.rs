@@ -0,0 +1,37 @@
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub enum ColliderOutput {
+    /// Add the colliders as extra nodes next to the asteroid.
+    #[default]
+    GltfNodes,
+    /// Write the colliders to a `_collider` file next to the export.
+    SeparateFile,
+}
+
+#[derive(Debug, Clone)]
+pub struct ColliderSettings {
+    pub convex_hull: bool,
+    pub convex_decomposition: bool,
+    /// Upper bound on the number of convex parts in the decomposition.
+    pub max_convex_hulls: u32,
+    /// Voxel grid resolution used by the decomposition.
+    pub resolution: u32,
+    pub output: ColliderOutput,
+}
+
+impl Default for ColliderSettings {
+    fn default() -> Self {
+        ColliderSettings {
+            convex_hull: false,
+            convex_decomposition: false,
+            max_convex_hulls: 16,
+            resolution: 64,
+            output: ColliderOutput::GltfNodes,
+        }
+    }
+}
+
+impl ColliderSettings {
+    pub fn is_enabled(&self) -> bool {
+        self.convex_hull || self.convex_decomposition
+    }
+}
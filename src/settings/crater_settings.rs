@@ -1,9 +1,9 @@
-use crate::utils::PRNG;
+use crate::settings::validation::{replace_non_finite, ValidationReport};
+use crate::utils::{derive_seed, PRNG};
 use bevy::math::{FloatExt, Vec3};
 use bevy::prelude::{Reflect, Resource};
 use bevy::render::render_resource::ShaderType;
-use rand::rngs::StdRng;
-use rand::SeedableRng;
+use serde::{Deserialize, Serialize};
 
 #[repr(C)]
 #[derive(ShaderType, Clone, Default, Copy, bytemuck::Pod, bytemuck::Zeroable)]
@@ -14,7 +14,8 @@ pub struct Crater {
     pub smoothness: f32,
 }
 
-#[derive(Resource, Default, Debug, Reflect, Clone)]
+#[derive(Resource, Default, Debug, Reflect, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
 pub struct CraterSettings {
     pub num_craters: f32,
     pub crater_size_min: f32,
@@ -28,9 +29,49 @@ pub struct CraterSettings {
 
 
 const CRATER_SEED: u64 = 2;
-pub const MAX_CRATER: usize = 2000;
+pub const MAX_CRATER: usize = 10000;
+/// Lower bound for crater sizes and smoothness, zero divides by zero in `crater.wgsl`.
+pub const MIN_CRATER_VALUE: f32 = 0.001;
 
 impl CraterSettings {
+    pub fn validate(&self) -> ValidationReport {
+        let mut report = ValidationReport::default();
+        report.check_finite(self);
+
+        if self.num_craters < 0.0 {
+            report.error("num_craters", "is negative");
+        } else if self.num_craters as usize > MAX_CRATER {
+            report.error("num_craters", format!("is above the maximum of {}", MAX_CRATER));
+        }
+        for (field, value) in [
+            ("crater_size_min", self.crater_size_min),
+            ("crater_size_max", self.crater_size_max),
+            ("smooth_min", self.smooth_min),
+            ("smooth_max", self.smooth_max),
+        ] {
+            if value < MIN_CRATER_VALUE {
+                report.error(field, format!("must be at least {}", MIN_CRATER_VALUE));
+            }
+        }
+        if self.crater_size_min > self.crater_size_max {
+            report.warn("crater_size_min", "is larger than the maximum size");
+        }
+        if !(0.0..=1.0).contains(&self.size_distribution) {
+            report.warn("size_distribution", "is outside 0 to 1");
+        }
+        report
+    }
+
+    /// Fixes everything `validate` reports as an error.
+    pub fn sanitize(&mut self) {
+        replace_non_finite(self);
+        self.num_craters = self.num_craters.clamp(0.0, MAX_CRATER as f32);
+        self.crater_size_min = self.crater_size_min.max(MIN_CRATER_VALUE);
+        self.crater_size_max = self.crater_size_max.max(MIN_CRATER_VALUE);
+        self.smooth_min = self.smooth_min.max(MIN_CRATER_VALUE);
+        self.smooth_max = self.smooth_max.max(MIN_CRATER_VALUE);
+    }
+
     pub fn get_rim_steepness(&self) -> f32 {
         self.rim_steepness
     }
@@ -41,14 +82,11 @@ impl CraterSettings {
 
     pub fn get_craters(&self, crater_seed: u64) -> Vec<Crater> {
         // Create craters
-        let num_craters = self.num_craters as usize;
+        let num_craters = (self.num_craters as usize).min(MAX_CRATER);
 
         let mut craters = Vec::with_capacity(num_craters);
 
-        let seed = crater_seed + CRATER_SEED;
-        let mut prng = PRNG {
-            rng: StdRng::seed_from_u64(seed),
-        };
+        let mut prng = PRNG::new(derive_seed(crater_seed, CRATER_SEED));
 
         for _ in 0..num_craters {
             let t = prng.value_bias_lower(self.size_distribution);
@@ -0,0 +1,175 @@
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use bevy::prelude::Resource;
+
+use crate::collider::COLLIDER_SUFFIX;
+use crate::export_mesh::ExportFormat;
+use crate::heightmap_exporter;
+use crate::mass_properties::get_mass_properties_path;
+use crate::settings::collider_settings::{ColliderOutput, ColliderSettings};
+
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub enum GltfOutput {
+    /// Output standard glTF, a `.gltf` file next to a `.bin` buffer.
+    Standard,
+    /// Output binary glTF.
+    #[default]
+    Binary,
+}
+
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub enum HeightmapLayout {
+    /// One image, twice as wide as it is tall.
+    #[default]
+    Equirectangular,
+    /// Six square images, one per cube face.
+    CubeMap,
+}
+
+#[derive(Resource, Debug, Clone)]
+pub struct ExportSettings {
+    pub output_dir: String,
+    /// File name without extension. Supports `{seed}`, `{preset}`, `{date}`, `{time}` and `{format}`.
+    pub filename_template: String,
+    pub preset_name: String,
+    pub gltf_output: GltfOutput,
+    /// Store positions, normals and colors as normalized integers with `KHR_mesh_quantization`.
+    pub gltf_quantize: bool,
+    /// Compress buffer views with `EXT_meshopt_compression`, which loaders must support to open the file.
+    pub gltf_meshopt_compression: bool,
+    pub collider_settings: ColliderSettings,
+    pub heightmap_layout: HeightmapLayout,
+    /// Image height for the equirectangular layout, face size for the cube map.
+    pub heightmap_resolution: u32,
+}
+
+impl Default for ExportSettings {
+    fn default() -> Self {
+        ExportSettings {
+            output_dir: "exports".to_string(),
+            filename_template: "asteroid_{seed}_{date}".to_string(),
+            preset_name: "default".to_string(),
+            gltf_output: GltfOutput::Binary,
+            gltf_quantize: false,
+            gltf_meshopt_compression: false,
+            collider_settings: ColliderSettings::default(),
+            heightmap_layout: HeightmapLayout::Equirectangular,
+            heightmap_resolution: 1024,
+        }
+    }
+}
+
+impl ExportSettings {
+    pub fn get_extension(&self, format: ExportFormat) -> &'static str {
+        match (format, self.gltf_output) {
+            (ExportFormat::Glb | ExportFormat::Field, GltfOutput::Binary) => "glb",
+            (ExportFormat::Glb | ExportFormat::Field, GltfOutput::Standard) => "gltf",
+            _ => format.extension(),
+        }
+    }
+
+    pub fn get_file_stem(&self, format: ExportFormat, seed: u64) -> String {
+        let (date, time) = utc_date_time(SystemTime::now());
+        let stem = self
+            .filename_template
+            .replace("{seed}", &seed.to_string())
+            .replace("{preset}", &self.preset_name)
+            .replace("{date}", &date)
+            .replace("{time}", &time)
+            .replace("{format}", self.get_extension(format));
+
+        let stem = sanitize_file_name(&stem);
+        if stem.is_empty() {
+            "asteroid".to_string()
+        } else {
+            stem
+        }
+    }
+
+    pub fn get_path(&self, format: ExportFormat, seed: u64) -> PathBuf {
+        Path::new(&self.output_dir).join(format!(
+            "{}.{}",
+            self.get_file_stem(format, seed),
+            self.get_extension(format)
+        ))
+    }
+
+    /// Every file an export to `path` writes, used to check for existing files before overwriting.
+    pub fn get_written_paths(&self, format: ExportFormat, path: &Path) -> Vec<PathBuf> {
+        if matches!(format, ExportFormat::HeightmapPng | ExportFormat::HeightmapExr) {
+            return heightmap_exporter::get_output_paths(self.heightmap_layout, path);
+        }
+        if format == ExportFormat::Field {
+            return match self.gltf_output {
+                GltfOutput::Binary => vec![path.to_path_buf()],
+                GltfOutput::Standard => vec![path.to_path_buf(), path.with_extension("bin")],
+            };
+        }
+
+        let mut paths = vec![path.to_path_buf(), get_mass_properties_path(path)];
+        if format == ExportFormat::Glb
+            && self.collider_settings.is_enabled()
+            && self.collider_settings.output == ColliderOutput::SeparateFile
+        {
+            paths.push(get_collider_path(path));
+        }
+
+        match (format, self.gltf_output) {
+            (ExportFormat::Glb, GltfOutput::Standard) => {
+                let bin_paths: Vec<PathBuf> = paths.iter().map(|path| path.with_extension("bin")).collect();
+                paths.extend(bin_paths);
+            }
+            (ExportFormat::Obj, _) => paths.push(path.with_extension("mtl")),
+            _ => {}
+        }
+        paths
+    }
+}
+
+/// `asteroid.glb` becomes `asteroid_collider.glb`.
+pub fn get_collider_path(path: &Path) -> PathBuf {
+    let stem = path.file_stem().map(|s| s.to_string_lossy()).unwrap_or_default();
+    let extension = path.extension().map(|s| s.to_string_lossy()).unwrap_or_default();
+    path.with_file_name(format!("{}{}.{}", stem, COLLIDER_SUFFIX, extension))
+}
+
+fn sanitize_file_name(name: &str) -> String {
+    name.chars()
+        .map(|c| match c {
+            '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '_',
+            c if c.is_control() => '_',
+            c => c,
+        })
+        .collect::<String>()
+        .trim()
+        .to_string()
+}
+
+/// Formats a timestamp as `YYYY-MM-DD` and `HH-MM-SS` in UTC, safe to use in file names.
+fn utc_date_time(time: SystemTime) -> (String, String) {
+    let seconds = time.duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0) as i64;
+    let days = seconds.div_euclid(86_400);
+    let seconds_of_day = seconds.rem_euclid(86_400);
+
+    // Civil-from-days conversion, see https://howardhinnant.github.io/date_algorithms.html
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let day_of_era = z.rem_euclid(146_097);
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    (
+        format!("{:04}-{:02}-{:02}", year, month, day),
+        format!(
+            "{:02}-{:02}-{:02}",
+            seconds_of_day / 3600,
+            (seconds_of_day / 60) % 60,
+            seconds_of_day % 60
+        ),
+    )
+}
@@ -0,0 +1,81 @@
+use bevy::prelude::Resource;
+use bevy::reflect::Reflect;
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq, Reflect, Serialize, Deserialize)]
+pub enum FieldLayout {
+    /// Spread evenly over the annulus between the inner and outer radius.
+    #[default]
+    Belt,
+    /// Concentrated around the middle of the annulus.
+    Ring,
+    /// Gaussian blob around the origin, the outer radius is twice the standard deviation.
+    Cluster,
+}
+
+impl FieldLayout {
+    pub fn label(&self) -> &'static str {
+        match self {
+            FieldLayout::Belt => "Belt",
+            FieldLayout::Ring => "Ring",
+            FieldLayout::Cluster => "Cluster",
+        }
+    }
+}
+
+/// Many asteroids sharing a few generated shapes. Distances and sizes are in the scale settings unit.
+#[derive(Resource, Debug, Reflect, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FieldSettings {
+    pub layout: FieldLayout,
+    /// Number of placed asteroids.
+    pub count: u32,
+    /// Number of distinct meshes, each placed asteroid reuses one of them.
+    pub unique_shapes: u32,
+    /// Subdivisions of the sphere mesh for each shape, lower than the viewer to keep generation fast.
+    pub resolution: u32,
+    pub inner_radius: f32,
+    pub outer_radius: f32,
+    /// Standard deviation of the height above the plane, doubled.
+    pub thickness: f32,
+    pub min_size: f32,
+    pub max_size: f32,
+    /// Power law slope of the size distribution, higher values favour small asteroids.
+    pub size_exponent: f32,
+    /// Relative range the noise settings are varied by for each shape.
+    pub noise_variation: f32,
+    /// Relative range the crater count and size are varied by for each shape.
+    pub crater_variation: f32,
+    /// Show the field in the viewer instead of the single asteroid.
+    pub show_in_viewer: bool,
+}
+
+impl Default for FieldSettings {
+    fn default() -> Self {
+        FieldSettings {
+            layout: FieldLayout::Belt,
+            count: 200,
+            unique_shapes: 8,
+            resolution: 60,
+            inner_radius: 400.0,
+            outer_radius: 600.0,
+            thickness: 40.0,
+            min_size: 1.0,
+            max_size: 20.0,
+            size_exponent: 2.5,
+            noise_variation: 0.3,
+            crater_variation: 0.5,
+            show_in_viewer: true,
+        }
+    }
+}
+
+impl FieldSettings {
+    /// Distance from the origin that contains every asteroid in most fields, used to frame the viewer.
+    pub fn get_extent(&self) -> f32 {
+        match self.layout {
+            FieldLayout::Cluster => self.outer_radius * 1.5 + self.max_size,
+            _ => self.outer_radius.max(self.inner_radius) + self.max_size,
+        }
+    }
+}
@@ -0,0 +1,14 @@
+use bevy::prelude::Resource;
+use bevy::reflect::Reflect;
+
+#[derive(Resource, Debug, Reflect, Clone)]
+pub struct MassSettings {
+    /// Uniform density in kg/m³.
+    pub density: f32,
+}
+
+impl Default for MassSettings {
+    fn default() -> Self {
+        MassSettings { density: 2000.0 }
+    }
+}
@@ -0,0 +1,75 @@
+use bevy::color::{Color, ColorToComponents, LinearRgba};
+use bevy::prelude::Resource;
+use bevy::reflect::Reflect;
+use serde::{Deserialize, Serialize};
+
+use crate::settings::validation::{replace_non_finite, ValidationReport};
+
+#[derive(Resource, Debug, Reflect, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MaterialSettings {
+    /// Base color in linear RGB, as edited by the egui color picker.
+    pub base_color: [f32; 3],
+    pub roughness: f32,
+    pub metallic: f32,
+    pub reflectance: f32,
+    pub detail_enabled: bool,
+    pub detail_texture_path: String,
+    pub detail_scale: f32,
+    pub detail_strength: f32,
+    pub detail_sharpness: f32,
+}
+
+impl Default for MaterialSettings {
+    fn default() -> Self {
+        MaterialSettings {
+            base_color: LinearRgba::from(Color::srgb(0.4, 0.4, 0.4)).to_f32_array_no_alpha(),
+            roughness: 0.9,
+            metallic: 0.0,
+            reflectance: 0.5,
+            detail_enabled: false,
+            detail_texture_path: String::new(),
+            detail_scale: 4.0,
+            detail_strength: 0.5,
+            detail_sharpness: 4.0,
+        }
+    }
+}
+
+impl MaterialSettings {
+    pub fn validate(&self) -> ValidationReport {
+        let mut report = ValidationReport::default();
+        report.check_finite(self);
+
+        if self.base_color.iter().any(|channel| !channel.is_finite()) {
+            report.error("base_color", "is not a number");
+        }
+        for (field, value) in [
+            ("roughness", self.roughness),
+            ("metallic", self.metallic),
+            ("reflectance", self.reflectance),
+        ] {
+            if !(0.0..=1.0).contains(&value) {
+                report.warn(field, "is outside 0 to 1");
+            }
+        }
+        report
+    }
+
+    /// Fixes everything `validate` reports as an error.
+    pub fn sanitize(&mut self) {
+        replace_non_finite(self);
+    }
+
+    pub fn get_base_color(&self) -> Color {
+        Color::linear_rgb(self.base_color[0], self.base_color[1], self.base_color[2])
+    }
+
+    /// Index of refraction matching bevy's `reflectance` mapping (f0 = 0.16 * reflectance²),
+    /// used to carry the value through `KHR_materials_ior`.
+    pub fn get_ior(&self) -> f32 {
+        let f0 = 0.16 * self.reflectance * self.reflectance;
+        let sqrt_f0 = f0.sqrt().min(0.99);
+        (1.0 + sqrt_f0) / (1.0 - sqrt_f0)
+    }
+}
@@ -0,0 +1,49 @@
+use bevy::prelude::Resource;
+use bevy::reflect::Reflect;
+
+/// What the exporters do with a mesh that has folded or broken triangles.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Reflect)]
+pub enum ExportQualityAction {
+    /// Write the mesh as is and list its issues.
+    #[default]
+    Warn,
+    /// Don't write the mesh.
+    Block,
+    /// Unfold the mesh with `mesh_validation::repair_mesh` before writing it.
+    Repair,
+}
+
+impl ExportQualityAction {
+    pub const ALL: [ExportQualityAction; 3] = [
+        ExportQualityAction::Warn,
+        ExportQualityAction::Block,
+        ExportQualityAction::Repair,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            ExportQualityAction::Warn => "Warn",
+            ExportQualityAction::Block => "Block",
+            ExportQualityAction::Repair => "Repair",
+        }
+    }
+}
+
+#[derive(Resource, Debug, Reflect, Clone)]
+pub struct MeshQualitySettings {
+    /// Draw the triangles with issues over the asteroid in the viewer.
+    pub highlight: bool,
+    pub on_export: ExportQualityAction,
+    /// Smoothing rounds a repair runs at most.
+    pub max_repair_iterations: u32,
+}
+
+impl Default for MeshQualitySettings {
+    fn default() -> Self {
+        MeshQualitySettings {
+            highlight: true,
+            on_export: ExportQualityAction::Warn,
+            max_repair_iterations: 10,
+        }
+    }
+}
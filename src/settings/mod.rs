@@ -2,3 +2,14 @@ pub mod crater_settings;
 pub mod ridge_noise_settings;
 pub mod simple_noise_settings;
 pub mod asteroid_settings;
+pub mod material_settings;
+pub mod print_settings;
+pub mod export_settings;
+pub mod collider_settings;
+pub mod mass_settings;
+pub mod scale_settings;
+pub mod spin_settings;
+pub mod field_settings;
+pub mod batch_settings;
+pub mod validation;
+pub mod mesh_quality_settings;
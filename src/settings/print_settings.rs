@@ -0,0 +1,24 @@
+use bevy::prelude::Resource;
+use bevy::reflect::Reflect;
+
+#[derive(Resource, Debug, Reflect, Clone)]
+pub struct PrintSettings {
+    /// Size of the largest bounding box dimension of the printed asteroid.
+    pub target_size_mm: f32,
+    pub hollow: bool,
+    pub wall_thickness_mm: f32,
+    pub num_drain_holes: u32,
+    pub drain_hole_diameter_mm: f32,
+}
+
+impl Default for PrintSettings {
+    fn default() -> Self {
+        PrintSettings {
+            target_size_mm: 80.0,
+            hollow: false,
+            wall_thickness_mm: 2.0,
+            num_drain_holes: 1,
+            drain_hole_diameter_mm: 4.0,
+        }
+    }
+}
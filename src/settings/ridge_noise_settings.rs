@@ -1,9 +1,13 @@
+use crate::settings::simple_noise_settings::{validate_num_layers, MAX_NOISE_LAYERS};
+use crate::settings::validation::{replace_non_finite, ValidationReport};
 use crate::utils::PRNG;
 use bevy::math::Vec3;
 use bevy::prelude::Resource;
 use bevy::reflect::Reflect;
+use serde::{Deserialize, Serialize};
 
-#[derive(Resource, Default, Debug, Reflect, Clone)]
+#[derive(Resource, Default, Debug, Reflect, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
 pub struct RidgeNoiseSettings {
     pub num_layers: f32,
     pub lacunarity: f32,
@@ -20,6 +24,19 @@ pub struct RidgeNoiseSettings {
 }
 
 impl RidgeNoiseSettings {
+    pub fn validate(&self) -> ValidationReport {
+        let mut report = ValidationReport::default();
+        report.check_finite(self);
+        validate_num_layers(&mut report, self.num_layers);
+        report
+    }
+
+    /// Fixes everything `validate` reports as an error.
+    pub fn sanitize(&mut self) {
+        replace_non_finite(self);
+        self.num_layers = self.num_layers.clamp(0.0, MAX_NOISE_LAYERS);
+    }
+
     pub fn get_noise_params(&self, mut prng: PRNG) -> Vec<[f32; 4]> {
         let seeded_offset = Vec3::new(prng.get_value(), prng.get_value(), prng.get_value())
             * prng.get_value()
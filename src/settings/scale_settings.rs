@@ -0,0 +1,89 @@
+use bevy::prelude::Resource;
+use bevy::reflect::Reflect;
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq, Reflect, Serialize, Deserialize)]
+pub enum LengthUnit {
+    #[default]
+    Metres,
+    Kilometres,
+}
+
+impl LengthUnit {
+    pub fn label(&self) -> &'static str {
+        match self {
+            LengthUnit::Metres => "m",
+            LengthUnit::Kilometres => "km",
+        }
+    }
+
+    pub fn get_metres(&self) -> f32 {
+        match self {
+            LengthUnit::Metres => 1.0,
+            LengthUnit::Kilometres => 1000.0,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq, Reflect, Serialize, Deserialize)]
+pub enum UpAxis {
+    #[default]
+    Y,
+    Z,
+}
+
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq, Reflect, Serialize, Deserialize)]
+pub enum Handedness {
+    #[default]
+    Right,
+    Left,
+}
+
+/// Real-world size of the body and the axis convention used by exporters other than glTF,
+/// which is always metres, Y-up and right-handed.
+#[derive(Resource, Debug, Reflect, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ScaleSettings {
+    /// Radius of the undisplaced sphere, the generator works on a unit sphere.
+    pub body_radius: f32,
+    pub unit: LengthUnit,
+    pub up_axis: UpAxis,
+    pub handedness: Handedness,
+}
+
+impl Default for ScaleSettings {
+    fn default() -> Self {
+        ScaleSettings {
+            body_radius: 1.0,
+            unit: LengthUnit::Metres,
+            up_axis: UpAxis::Y,
+            handedness: Handedness::Right,
+        }
+    }
+}
+
+impl ScaleSettings {
+    /// Scale from generator units to `unit`.
+    pub fn get_scale(&self) -> f32 {
+        self.body_radius
+    }
+
+    pub fn get_scale_in_metres(&self) -> f32 {
+        self.body_radius * self.unit.get_metres()
+    }
+
+    /// Converts a Y-up, right-handed direction to the configured convention.
+    pub fn convert_axes(&self, [x, y, z]: [f32; 3]) -> [f32; 3] {
+        match (self.up_axis, self.handedness) {
+            (UpAxis::Y, Handedness::Right) => [x, y, z],
+            (UpAxis::Y, Handedness::Left) => [x, y, -z],
+            (UpAxis::Z, Handedness::Right) => [x, -z, y],
+            (UpAxis::Z, Handedness::Left) => [x, z, y],
+        }
+    }
+
+    /// Mirroring into a left-handed system turns counter-clockwise triangles clockwise.
+    pub fn flips_winding(&self) -> bool {
+        self.handedness == Handedness::Left
+    }
+}
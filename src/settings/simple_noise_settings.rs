@@ -1,9 +1,12 @@
+use crate::settings::validation::{replace_non_finite, ValidationReport};
 use crate::utils::PRNG;
 use bevy::math::Vec3;
 use bevy::prelude::Resource;
 use bevy::reflect::Reflect;
+use serde::{Deserialize, Serialize};
 
-#[derive(Resource, Default, Debug, Reflect, Clone)]
+#[derive(Resource, Default, Debug, Reflect, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
 pub struct SimpleNoiseSettings {
     pub num_layers: f32,
     pub lacunarity: f32,
@@ -16,7 +19,23 @@ pub struct SimpleNoiseSettings {
     pub offset_z: f32,
 }
 
+/// Noise octaves are looped per vertex on the GPU, more than this stalls the update.
+pub const MAX_NOISE_LAYERS: f32 = 40.0;
+
 impl SimpleNoiseSettings {
+    pub fn validate(&self) -> ValidationReport {
+        let mut report = ValidationReport::default();
+        report.check_finite(self);
+        validate_num_layers(&mut report, self.num_layers);
+        report
+    }
+
+    /// Fixes everything `validate` reports as an error.
+    pub fn sanitize(&mut self) {
+        replace_non_finite(self);
+        self.num_layers = self.num_layers.clamp(0.0, MAX_NOISE_LAYERS);
+    }
+
     pub fn get_noise_params(&self, mut prng: PRNG) -> Vec<[f32; 4]> {
         let seeded_offset = Vec3::new(prng.get_value(), prng.get_value(), prng.get_value())
             * prng.get_value()
@@ -40,3 +59,11 @@ impl SimpleNoiseSettings {
         noise_params
     }
 }
+
+pub fn validate_num_layers(report: &mut ValidationReport, num_layers: f32) {
+    if num_layers < 0.0 {
+        report.error("num_layers", "is negative");
+    } else if num_layers > MAX_NOISE_LAYERS {
+        report.error("num_layers", format!("is above the maximum of {}", MAX_NOISE_LAYERS));
+    }
+}
@@ -0,0 +1,201 @@
+use std::f32::consts::TAU;
+
+use bevy::math::{Quat, Vec3};
+use bevy::prelude::Resource;
+use bevy::reflect::Reflect;
+use serde::{Deserialize, Serialize};
+
+/// Rotation of the body, shown in the viewer and exported as a looping glTF animation.
+#[derive(Resource, Debug, Reflect, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SpinSettings {
+    pub enabled: bool,
+    /// Seconds per full turn.
+    pub period: f32,
+    /// Angle between the spin axis and +Y, in degrees.
+    pub axis_tilt: f32,
+    /// Direction the axis is tilted towards, in degrees around +Y starting at +X.
+    pub axis_azimuth: f32,
+    /// Non-principal-axis rotation: the body axis is inclined to the spin axis and precesses around it.
+    pub tumbling: bool,
+    /// Seconds per full precession of the body axis.
+    pub precession_period: f32,
+    /// Angle between the body axis and the spin axis, in degrees.
+    pub nutation_angle: f32,
+}
+
+impl Default for SpinSettings {
+    fn default() -> Self {
+        SpinSettings {
+            enabled: false,
+            period: 20.0,
+            axis_tilt: 0.0,
+            axis_azimuth: 0.0,
+            tumbling: false,
+            precession_period: 60.0,
+            nutation_angle: 15.0,
+        }
+    }
+}
+
+impl SpinSettings {
+    /// Unit spin axis, Y-up and right-handed.
+    pub fn get_axis(&self) -> Vec3 {
+        let tilt = self.axis_tilt.to_radians();
+        let azimuth = self.axis_azimuth.to_radians();
+        Vec3::new(tilt.sin() * azimuth.cos(), tilt.cos(), -tilt.sin() * azimuth.sin())
+    }
+
+    /// Length of one seamless loop, the precession period when tumbling.
+    pub fn get_loop_duration(&self) -> f32 {
+        if self.tumbling {
+            self.precession_period.max(f32::EPSILON)
+        } else {
+            self.period.max(f32::EPSILON)
+        }
+    }
+
+    /// When tumbling the spin period is snapped so a whole number of turns fits in one precession.
+    pub fn get_spin_period(&self) -> f32 {
+        if self.tumbling {
+            let turns = (self.get_loop_duration() / self.period.max(f32::EPSILON)).round().max(1.0);
+            self.get_loop_duration() / turns
+        } else {
+            self.period.max(f32::EPSILON)
+        }
+    }
+
+    /// Whole turns about the body axis in one loop.
+    pub fn get_turns_per_loop(&self) -> u32 {
+        (self.get_loop_duration() / self.get_spin_period()).round() as u32
+    }
+
+    /// Orientation `time` seconds into the loop, identity at zero unless tumbling.
+    pub fn rotation_at(&self, time: f32) -> Quat {
+        if !self.enabled {
+            return Quat::IDENTITY;
+        }
+
+        let axis = self.get_axis();
+        let spin_angle = TAU * time / self.get_spin_period();
+        if !self.tumbling {
+            return Quat::from_axis_angle(axis, spin_angle);
+        }
+
+        // Spin about the body axis, incline it by the nutation angle, then precess it around the spin axis
+        let precession_angle = TAU * time / self.get_loop_duration();
+        let inclination_axis = axis.any_orthonormal_vector();
+        Quat::from_axis_angle(axis, precession_angle)
+            * Quat::from_axis_angle(inclination_axis, self.nutation_angle.to_radians())
+            * Quat::from_axis_angle(axis, spin_angle)
+    }
+
+    /// Key times over one loop and the orientations at them, the last key closing the loop. Eight keys per turn
+    /// keep each step well under the half turn where slerp would take the short way round.
+    pub fn get_keyframes(&self) -> (Vec<f32>, Vec<Quat>) {
+        let key_count = self.get_turns_per_loop().max(1) * 8;
+        let duration = self.get_loop_duration();
+        let times: Vec<f32> = (0..=key_count).map(|i| i as f32 / key_count as f32 * duration).collect();
+        let rotations = times.iter().map(|&time| self.rotation_at(time)).collect();
+        (times, rotations)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f32::consts::FRAC_PI_4;
+
+    use super::*;
+
+    fn spinning(tumbling: bool) -> SpinSettings {
+        SpinSettings {
+            enabled: true,
+            axis_tilt: 30.0,
+            axis_azimuth: 45.0,
+            tumbling,
+            ..Default::default()
+        }
+    }
+
+    /// Whether two quaternions describe the same orientation, `q` and `-q` included.
+    fn assert_same_rotation(a: Quat, b: Quat) {
+        assert!(a.dot(b).abs() > 1.0 - 1e-5, "{} is not {}", a, b);
+    }
+
+    #[test]
+    fn axis_from_tilt_and_azimuth() {
+        let mut spin = SpinSettings::default();
+        assert!(spin.get_axis().abs_diff_eq(Vec3::Y, 1e-6));
+
+        spin.axis_tilt = 90.0;
+        assert!(spin.get_axis().abs_diff_eq(Vec3::X, 1e-6));
+        spin.axis_azimuth = 90.0;
+        assert!(spin.get_axis().abs_diff_eq(Vec3::NEG_Z, 1e-6));
+    }
+
+    #[test]
+    fn disabled_spin_is_identity() {
+        let spin = SpinSettings {
+            enabled: false,
+            ..spinning(true)
+        };
+        assert_eq!(spin.rotation_at(7.3), Quat::IDENTITY);
+    }
+
+    #[test]
+    fn spin_turns_about_its_axis() {
+        let spin = spinning(false);
+        let axis = spin.get_axis();
+        let quarter_turn = spin.rotation_at(spin.period / 4.0);
+
+        assert!((quarter_turn * axis).abs_diff_eq(axis, 1e-5));
+        let across = axis.any_orthonormal_vector();
+        assert!((quarter_turn * across).dot(across).abs() < 1e-5);
+    }
+
+    #[test]
+    fn tumbling_snaps_whole_turns_into_the_precession() {
+        let spin = SpinSettings {
+            period: 20.0,
+            precession_period: 65.0,
+            ..spinning(true)
+        };
+        assert_eq!(spin.get_loop_duration(), 65.0);
+        assert_eq!(spin.get_turns_per_loop(), 3);
+        assert!((spin.get_spin_period() - 65.0 / 3.0).abs() < 1e-5);
+
+        // A precession shorter than one turn still turns once
+        let spin = SpinSettings {
+            period: 20.0,
+            precession_period: 5.0,
+            ..spinning(true)
+        };
+        assert_eq!(spin.get_turns_per_loop(), 1);
+        assert_eq!(spin.get_spin_period(), 5.0);
+    }
+
+    #[test]
+    fn loops_are_seamless() {
+        for tumbling in [false, true] {
+            let spin = spinning(tumbling);
+            assert_same_rotation(spin.rotation_at(spin.get_loop_duration()), spin.rotation_at(0.0));
+        }
+    }
+
+    #[test]
+    fn keyframes_cover_the_loop_in_small_steps() {
+        for tumbling in [false, true] {
+            let spin = spinning(tumbling);
+            let (times, rotations) = spin.get_keyframes();
+
+            assert_eq!(times.len() as u32, spin.get_turns_per_loop() * 8 + 1);
+            assert_eq!(times[0], 0.0);
+            assert_eq!(times[times.len() - 1], spin.get_loop_duration());
+            assert_same_rotation(rotations[0], rotations[rotations.len() - 1]);
+            for pair in rotations.windows(2) {
+                // Same hemisphere and under a quarter turn apart, so slerp follows the spin direction
+                assert!(pair[0].dot(pair[1]) > FRAC_PI_4.cos(), "{} to {}", pair[0], pair[1]);
+            }
+        }
+    }
+}
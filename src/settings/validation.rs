@@ -0,0 +1,105 @@
+use std::fmt;
+
+use bevy::reflect::{Reflect, ReflectMut};
+
+use crate::utils::for_each_field;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Severity {
+    /// The value is used as is but is probably a mistake.
+    Warning,
+    /// The value would break generation, `sanitize` replaces it before anything is uploaded.
+    Error,
+}
+
+/// A problem with one settings field. `field` is the reflection path from the validated struct,
+/// e.g. `crater_settings.num_craters` for `AsteroidSettings`.
+#[derive(Clone, Debug)]
+pub struct SettingsIssue {
+    pub field: String,
+    pub severity: Severity,
+    pub message: String,
+}
+
+impl fmt::Display for SettingsIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.field, self.message)
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct ValidationReport {
+    pub issues: Vec<SettingsIssue>,
+}
+
+impl ValidationReport {
+    pub fn has_errors(&self) -> bool {
+        self.issues.iter().any(|issue| issue.severity == Severity::Error)
+    }
+
+    /// Issues of the field at `path` and of any field below it.
+    pub fn get_field_issues<'a>(&'a self, path: &'a str) -> impl Iterator<Item = &'a SettingsIssue> + 'a {
+        self.issues.iter().filter(move |issue| issue.field.starts_with(path))
+    }
+
+    pub fn warn(&mut self, field: &str, message: impl Into<String>) {
+        self.push(field, Severity::Warning, message.into());
+    }
+
+    pub fn error(&mut self, field: &str, message: impl Into<String>) {
+        self.push(field, Severity::Error, message.into());
+    }
+
+    /// Adds the issues of a nested settings struct, prefixing their fields with `prefix`.
+    pub fn extend_nested(&mut self, prefix: &str, report: ValidationReport) {
+        self.issues.extend(report.issues.into_iter().map(|issue| SettingsIssue {
+            field: format!("{}.{}", prefix, issue.field),
+            ..issue
+        }));
+    }
+
+    /// Reports every NaN or infinite `f32` field of `value`.
+    pub fn check_finite(&mut self, value: &dyn Reflect) {
+        for_each_field(value, ".", &mut |path, field| {
+            if field.downcast_ref::<f32>().is_some_and(|value| !value.is_finite()) {
+                self.error(path, "is not a number");
+            }
+        });
+    }
+
+    fn push(&mut self, field: &str, severity: Severity, message: String) {
+        self.issues.push(SettingsIssue {
+            field: field.to_string(),
+            severity,
+            message,
+        });
+    }
+}
+
+/// Sets every NaN or infinite `f32` inside `value` to zero, the guard behind `check_finite`.
+pub fn replace_non_finite(value: &mut dyn Reflect) {
+    if let Some(value) = value.downcast_mut::<f32>() {
+        if !value.is_finite() {
+            *value = 0.0;
+        }
+        return;
+    }
+
+    match value.reflect_mut() {
+        ReflectMut::Struct(fields) => {
+            for i in 0..fields.field_len() {
+                if let Some(field) = fields.field_at_mut(i) {
+                    replace_non_finite(field);
+                }
+            }
+        }
+        ReflectMut::Array(array) => {
+            for i in 0..array.len() {
+                if let Some(item) = array.get_mut(i) {
+                    replace_non_finite(item);
+                }
+            }
+        }
+        _ => {}
+    }
+}
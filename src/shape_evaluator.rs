@@ -0,0 +1,439 @@
+//! CPU port of `compute_asteroid_shape.wgsl` and the shader functions it imports.
+//! Keep in sync with the WGSL so CPU results match the GPU mesh.
+
+use std::thread;
+
+use bevy::math::{Vec2, Vec3, Vec3Swizzles, Vec4, Vec4Swizzles};
+
+use crate::crater_grid::CraterGrid;
+use crate::settings::asteroid_settings::AsteroidSettings;
+use crate::settings::crater_settings::Crater;
+use crate::sphere_mesh::SphereMesh;
+use crate::utils::PRNG;
+
+/// Noise elevations are in hundredths of the radius.
+pub const ELEVATION_MULTIPLIER: f32 = 0.01;
+
+/// All shader inputs for one asteroid, evaluated per direction on the unit sphere.
+pub struct ShapeEvaluator {
+    pub noise_params_shape: [[f32; 4]; 3],
+    pub noise_params_ridge: [[f32; 4]; 3],
+    pub noise_params_ridge2: [[f32; 4]; 3],
+    pub craters: Vec<Crater>,
+    pub crater_grid: CraterGrid,
+    pub rim_steepness: f32,
+    pub rim_width: f32,
+    pub max_strength: f32,
+}
+
+impl ShapeEvaluator {
+    /// Builds the same inputs the compute observers upload. `edge_length` is the length of a
+    /// sphere mesh edge, which scales the perturbation.
+    pub fn new(settings: &AsteroidSettings, seed: u64, edge_length: f32) -> Self {
+        let mut settings = settings.clone();
+        settings.sanitize();
+
+        let crater_settings = &settings.crater_settings;
+        let craters = crater_settings.get_craters(seed);
+
+        ShapeEvaluator {
+            noise_params_shape: to_params(settings.simple_noise_settings.get_noise_params(PRNG::new(seed))),
+            noise_params_ridge: to_params(settings.ridge_noise_settings.get_noise_params(PRNG::new(seed))),
+            noise_params_ridge2: to_params(settings.ridge_noise_settings2.get_noise_params(PRNG::new(seed))),
+            crater_grid: CraterGrid::new(&craters, crater_settings.get_rim_width()),
+            craters,
+            rim_steepness: crater_settings.get_rim_steepness(),
+            rim_width: crater_settings.get_rim_width(),
+            max_strength: settings.peturb_strength * edge_length / 2.,
+        }
+    }
+
+    /// Radial scale applied to a unit sphere point, `finalHeight` in the shader.
+    pub fn height(&self, vertex_pos: Vec3) -> f32 {
+        let crater_depth = self.crater_depth(vertex_pos);
+        let shape_noise = simple_noise(vertex_pos, &self.noise_params_shape);
+        let ridge_noise = smoothed_ridgid_noise(vertex_pos, &self.noise_params_ridge);
+        let ridge2 = smoothed_ridgid_noise(vertex_pos, &self.noise_params_ridge2);
+
+        let noise_sum = (shape_noise + ridge_noise + ridge2) * ELEVATION_MULTIPLIER;
+        1.0 + crater_depth + noise_sum
+    }
+
+    /// Final vertex position for a sphere mesh vertex, `new_vertices[index]` in the shader.
+    pub fn vertex(&self, vertex_pos: Vec3) -> Vec3 {
+        let height = vertex_pos.length();
+        let offset = perturb(vertex_pos);
+        let new_pos = (vertex_pos + offset * self.max_strength).normalize() * height;
+        new_pos * self.height(vertex_pos)
+    }
+
+    /// Displaces every sphere mesh vertex on the CPU, splitting the work across all available cores.
+    /// Returns the positions and normals the compute passes would produce.
+    pub fn evaluate_mesh(&self, sphere_mesh: &SphereMesh) -> (Vec<Vec3>, Vec<Vec3>) {
+        let mut vertices = sphere_mesh.vertices.clone();
+        let threads = thread::available_parallelism().map(|n| n.get()).unwrap_or(1);
+        let chunk_size = vertices.len().div_ceil(threads).max(1);
+
+        thread::scope(|scope| {
+            for chunk in vertices.chunks_mut(chunk_size) {
+                scope.spawn(move || {
+                    for vertex in chunk {
+                        *vertex = self.vertex(*vertex);
+                    }
+                });
+            }
+        });
+
+        let normals = compute_normals(&vertices, &sphere_mesh.indices);
+        (vertices, normals)
+    }
+
+    pub fn crater_depth(&self, vertex_pos: Vec3) -> f32 {
+        let mut crater_height = 0.0;
+        for &index in self.crater_grid.get_craters(vertex_pos) {
+            let crater = &self.craters[index as usize];
+            crater_height += crater_shape(vertex_pos, crater, self.rim_steepness, self.rim_width);
+        }
+        crater_height
+    }
+}
+
+/// Sum of the unit normals of the faces around each vertex, as in `compute_normals.wgsl`.
+pub fn compute_normals(vertices: &[Vec3], indices: &[u32]) -> Vec<Vec3> {
+    let mut normals = vec![Vec3::ZERO; vertices.len()];
+    for triangle in indices.chunks_exact(3) {
+        let [i0, i1, i2] = [0, 1, 2].map(|i| triangle[i] as usize);
+        let normal = (vertices[i1] - vertices[i0]).cross(vertices[i2] - vertices[i0]).normalize_or_zero();
+        normals[i0] += normal;
+        normals[i1] += normal;
+        normals[i2] += normal;
+    }
+    normals.iter().map(|normal| normal.normalize_or_zero()).collect()
+}
+
+fn to_params(params: Vec<[f32; 4]>) -> [[f32; 4]; 3] {
+    [params[0], params[1], params[2]]
+}
+
+pub fn crater_shape(vertex_pos: Vec3, crater: &Crater, rim_steepness: f32, rim_width: f32) -> f32 {
+    let x = (vertex_pos - crater.centre).length() / crater.radius;
+
+    let cavity = x * x - 1.0;
+    let rim_x = (x - 1.0 - rim_width).min(0.0);
+    let rim = rim_steepness * rim_x * rim_x;
+
+    let mut crater_shape = smooth_max(cavity, crater.floor_height, crater.smoothness);
+    crater_shape = smooth_min(crater_shape, rim, crater.smoothness);
+    crater_shape * crater.radius
+}
+
+pub fn smooth_min(a: f32, b: f32, k: f32) -> f32 {
+    let c = k.max(0.0);
+    let h = ((b - a + c) / (2.0 * c)).clamp(0.0, 1.0);
+    a * h + b * (1.0 - h) - c * h * (1.0 - h)
+}
+
+pub fn smooth_max(a: f32, b: f32, k: f32) -> f32 {
+    let c = (-k).min(0.0);
+    let h = ((b - a + c) / (2.0 * c)).clamp(0.0, 1.0);
+    a * h + b * (1.0 - h) - c * h * (1.0 - h)
+}
+
+pub fn simple_noise(pos: Vec3, params: &[[f32; 4]; 3]) -> f32 {
+    let offset = Vec3::new(params[0][0], params[0][1], params[0][2]);
+    let num_layers = params[0][3] as i32;
+    let persistence = params[1][0];
+    let lacunarity = params[1][1];
+    let scale = params[1][2];
+    let multiplier = params[1][3];
+    let vertical_shift = params[2][0];
+
+    let mut noise_sum = 0.0;
+    let mut amplitude = 1.0;
+    let mut frequency = scale;
+    for _ in 0..num_layers {
+        noise_sum += simplex_noise_3d(pos * frequency + offset) * amplitude;
+        amplitude *= persistence;
+        frequency *= lacunarity;
+    }
+    noise_sum * multiplier + vertical_shift
+}
+
+pub fn smoothed_ridgid_noise(pos: Vec3, params: &[[f32; 4]; 3]) -> f32 {
+    let sphere_normal = pos.normalize();
+    let axis_a = sphere_normal.cross(Vec3::Y);
+    let axis_b = sphere_normal.cross(axis_a);
+    let offset_dst = params[2][3] * 0.01;
+    let sample0 = ridgid_noise(pos, params);
+    let sample1 = ridgid_noise(pos - axis_a * offset_dst, params);
+    let sample2 = ridgid_noise(pos + axis_a * offset_dst, params);
+    let sample3 = ridgid_noise(pos - axis_b * offset_dst, params);
+    let sample4 = ridgid_noise(pos + axis_b * offset_dst, params);
+    (sample0 + sample1 + sample2 + sample3 + sample4) / 5.0
+}
+
+pub fn ridgid_noise(pos: Vec3, params: &[[f32; 4]; 3]) -> f32 {
+    let offset = Vec3::new(params[0][0], params[0][1], params[0][2]);
+    let num_layers = params[0][3] as i32;
+    let persistence = params[1][0];
+    let lacunarity = params[1][1];
+    let scale = params[1][2];
+    let multiplier = params[1][3];
+    let power = params[2][0];
+    let gain = params[2][1];
+    let vertical_shift = params[2][2];
+
+    let mut noise_sum = 0.0;
+    let mut amplitude = 1.0;
+    let mut frequency = scale;
+    let mut ridge_weight = 1.0;
+    for _ in 0..num_layers {
+        let mut noise_val = 1.0 - simplex_noise_3d(pos * frequency + offset).abs();
+        noise_val = noise_val.abs().powf(power);
+        noise_val *= ridge_weight;
+        ridge_weight = (noise_val * gain).clamp(0.0, 1.0);
+        noise_sum += noise_val * amplitude;
+        amplitude *= persistence;
+        frequency *= lacunarity;
+    }
+    noise_sum * multiplier + vertical_shift
+}
+
+pub fn perturb(pos: Vec3) -> Vec3 {
+    let noise = fractal_noise_grad(pos, 4, 25.0, 0.5, 2.0).xyz();
+    smoothstep(Vec3::splat(-1.0), Vec3::splat(1.0), noise) * 2.0 - 1.0
+}
+
+fn smoothstep(low: Vec3, high: Vec3, x: Vec3) -> Vec3 {
+    let t = ((x - low) / (high - low)).clamp(Vec3::ZERO, Vec3::ONE);
+    t * t * (3.0 - 2.0 * t)
+}
+
+/// WGSL `step(edge, x)`, 1.0 where `edge <= x`.
+fn step3(edge: Vec3, x: Vec3) -> Vec3 {
+    Vec3::select(edge.cmple(x), Vec3::ONE, Vec3::ZERO)
+}
+
+fn step4(edge: Vec4, x: Vec4) -> Vec4 {
+    Vec4::select(edge.cmple(x), Vec4::ONE, Vec4::ZERO)
+}
+
+fn permute_four(x: Vec4) -> Vec4 {
+    ((x * 34. + 1.) * x) % Vec4::splat(289.)
+}
+
+fn taylor_inv_sqrt_four(r: Vec4) -> Vec4 {
+    1.79284291400159 - 0.85373472095314 * r
+}
+
+pub fn simplex_noise_3d(v: Vec3) -> f32 {
+    let c = Vec2::new(1. / 6., 1. / 3.);
+    let d = Vec4::new(0., 0.5, 1., 2.);
+
+    // First corner
+    let mut i = (v + v.dot(Vec3::splat(c.y))).floor();
+    let x0 = v - i + i.dot(Vec3::splat(c.x));
+
+    // Other corners
+    let g = step3(x0.yzx(), x0);
+    let l = 1.0 - g;
+    let i1 = g.min(l.zxy());
+    let i2 = g.max(l.zxy());
+
+    let x1 = x0 - i1 + 1. * c.x;
+    let x2 = x0 - i2 + 2. * c.x;
+    let x3 = x0 - 1. + 3. * c.x;
+
+    // Permutations
+    i = i % Vec3::splat(289.);
+    let p = permute_four(
+        permute_four(
+            permute_four(i.z + Vec4::new(0., i1.z, i2.z, 1.)) + i.y + Vec4::new(0., i1.y, i2.y, 1.),
+        ) + i.x
+            + Vec4::new(0., i1.x, i2.x, 1.),
+    );
+
+    // Gradients (NxN points uniformly over a square, mapped onto an octahedron.)
+    let n_ = 1. / 7.;
+    let ns = n_ * d.wyz() - d.xzx();
+
+    let j = p - 49. * (p * ns.z * ns.z).floor();
+
+    let x_ = (j * ns.z).floor();
+    let y_ = (j - 7.0 * x_).floor();
+
+    let x = x_ * ns.x + Vec4::splat(ns.y);
+    let y = y_ * ns.x + Vec4::splat(ns.y);
+    let h = 1.0 - x.abs() - y.abs();
+
+    let b0 = Vec4::new(x.x, x.y, y.x, y.y);
+    let b1 = Vec4::new(x.z, x.w, y.z, y.w);
+
+    let s0 = b0.floor() * 2.0 + 1.0;
+    let s1 = b1.floor() * 2.0 + 1.0;
+    let sh = -step4(h, Vec4::ZERO);
+
+    let a0 = b0.xzyw() + s0.xzyw() * sh.xxyy();
+    let a1 = b1.xzyw() + s1.xzyw() * sh.zzww();
+
+    let mut p0 = Vec3::new(a0.x, a0.y, h.x);
+    let mut p1 = Vec3::new(a0.z, a0.w, h.y);
+    let mut p2 = Vec3::new(a1.x, a1.y, h.z);
+    let mut p3 = Vec3::new(a1.z, a1.w, h.w);
+
+    // Normalise gradients
+    let norm = taylor_inv_sqrt_four(Vec4::new(p0.dot(p0), p1.dot(p1), p2.dot(p2), p3.dot(p3)));
+    p0 *= norm.x;
+    p1 *= norm.y;
+    p2 *= norm.z;
+    p3 *= norm.w;
+
+    // Mix final noise value
+    let mut m = 0.6 - Vec4::new(x0.dot(x0), x1.dot(x1), x2.dot(x2), x3.dot(x3));
+    m = m.max(Vec4::ZERO);
+    m = m * m;
+    42. * (m * m).dot(Vec4::new(p0.dot(x0), p1.dot(x1), p2.dot(x2), p3.dot(x3)))
+}
+
+fn mod289_vec3(x: Vec3) -> Vec3 {
+    x - (x * (1.0 / 289.0)).floor() * 289.0
+}
+
+fn mod289_vec4(x: Vec4) -> Vec4 {
+    x - (x * (1.0 / 289.0)).floor() * 289.0
+}
+
+fn permute(x: Vec4) -> Vec4 {
+    mod289_vec4((x * 34.0 + 1.0) * x)
+}
+
+pub fn snoise_grad(v: Vec3) -> Vec4 {
+    let c = Vec2::new(1.0 / 6.0, 1.0 / 3.0);
+    // First corner
+    let mut i = (v + v.dot(Vec3::splat(c.y))).floor();
+    let x0 = v - i + i.dot(Vec3::splat(c.x));
+    // Other corners
+    let g = step3(x0.yzx(), x0);
+    let l = Vec3::ONE - g;
+    let i1 = g.min(l.zxy());
+    let i2 = g.max(l.zxy());
+    let x1 = x0 - i1 + Vec3::splat(c.x);
+    let x2 = x0 - i2 + Vec3::splat(c.y);
+    let x3 = x0 - Vec3::splat(0.5);
+    // Permutations
+    i = mod289_vec3(i);
+    let p = permute(
+        permute(permute(i.z + Vec4::new(0.0, i1.z, i2.z, 1.0)) + i.y + Vec4::new(0.0, i1.y, i2.y, 1.0))
+            + i.x
+            + Vec4::new(0.0, i1.x, i2.x, 1.0),
+    );
+    // Gradients: 7x7 points over a square, mapped onto an octahedron.
+    let j = p - 49.0 * (p * (1.0 / 49.0)).floor();
+    let x_ = (j * (1.0 / 7.0)).floor();
+    let y_ = (j - 7.0 * x_).floor();
+    let x = (x_ * 2.0 + 0.5) / 7.0 - 1.0;
+    let y = (y_ * 2.0 + 0.5) / 7.0 - 1.0;
+    let h = 1.0 - x.abs() - y.abs();
+    let b0 = Vec4::new(x.x, x.y, y.x, y.y);
+    let b1 = Vec4::new(x.z, x.w, y.z, y.w);
+    let s0 = b0.floor() * 2.0 + 1.0;
+    let s1 = b1.floor() * 2.0 + 1.0;
+    let sh = -step4(h, Vec4::ZERO);
+    let a0 = b0.xzyw() + s0.xzyw() * sh.xxyy();
+    let a1 = b1.xzyw() + s1.xzyw() * sh.zzww();
+    let mut g0 = Vec3::new(a0.x, a0.y, h.x);
+    let mut g1 = Vec3::new(a0.z, a0.w, h.y);
+    let mut g2 = Vec3::new(a1.x, a1.y, h.z);
+    let mut g3 = Vec3::new(a1.z, a1.w, h.w);
+    // Normalise gradients
+    let norm = taylor_inv_sqrt_four(Vec4::new(g0.dot(g0), g1.dot(g1), g2.dot(g2), g3.dot(g3)));
+    g0 *= norm.x;
+    g1 *= norm.y;
+    g2 *= norm.z;
+    g3 *= norm.w;
+    // Compute noise and gradient at P
+    let m = (0.6 - Vec4::new(x0.dot(x0), x1.dot(x1), x2.dot(x2), x3.dot(x3))).max(Vec4::ZERO);
+    let m2 = m * m;
+    let m3 = m2 * m;
+    let m4 = m2 * m2;
+    let grad = -6.0 * m3.x * x0 * x0.dot(g0) + m4.x * g0
+        + -6.0 * m3.y * x1 * x1.dot(g1) + m4.y * g1
+        + -6.0 * m3.z * x2 * x2.dot(g2) + m4.z * g2
+        + -6.0 * m3.w * x3 * x3.dot(g3) + m4.w * g3;
+    let px = Vec4::new(x0.dot(g0), x1.dot(g1), x2.dot(g2), x3.dot(g3));
+    42.0 * grad.extend(m4.dot(px))
+}
+
+pub fn fractal_noise_grad(pos: Vec3, num_layers: i32, scale: f32, persistence: f32, lacunarity: f32) -> Vec4 {
+    let mut noise = Vec4::ZERO;
+    let mut amplitude = 1.0;
+    let mut frequency = scale;
+
+    for _ in 0..num_layers {
+        noise += snoise_grad(pos * frequency) * amplitude;
+        amplitude *= persistence;
+        frequency *= lacunarity;
+    }
+
+    noise
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crater_grid::{CRATER_GRID_CAPACITY, CRATER_GRID_CELLS, CRATER_GRID_SIZE};
+    use crate::settings::crater_settings::MAX_CRATER;
+
+    fn evaluator(num_craters: usize, crater_size_min: f32, crater_size_max: f32, seed: u64) -> ShapeEvaluator {
+        let mut settings = AsteroidSettings::default();
+        settings.crater_settings.num_craters = num_craters as f32;
+        settings.crater_settings.crater_size_min = crater_size_min;
+        settings.crater_settings.crater_size_max = crater_size_max;
+        ShapeEvaluator::new(&settings, seed, 0.01)
+    }
+
+    /// `crater_depth` without the grid, in the same order.
+    fn full_crater_depth(evaluator: &ShapeEvaluator, vertex_pos: Vec3) -> f32 {
+        evaluator.craters.iter().fold(0.0, |height, crater| {
+            height + crater_shape(vertex_pos, crater, evaluator.rim_steepness, evaluator.rim_width)
+        })
+    }
+
+    #[test]
+    fn crater_grid_matches_full_loop() {
+        let mut prng = PRNG::new(7);
+        let mut directions: Vec<Vec3> = (0..500).map(|_| prng.random_on_unit_sphere()).collect();
+        // Cell borders, where the binning is most likely to miss a crater
+        for step in 0..=CRATER_GRID_SIZE {
+            let uv = step as f32 * 2.0 / CRATER_GRID_SIZE as f32 - 1.0;
+            directions.push(Vec3::new(1.0, uv, 0.3).normalize());
+            directions.push(Vec3::new(uv, -1.0, uv).normalize());
+        }
+
+        for (num_craters, crater_size_min, crater_size_max) in [(100, 0.01, 0.14), (MAX_CRATER, 0.01, 0.14), (500, 0.1, 0.6)] {
+            for seed in 0..3 {
+                let shape = evaluator(num_craters, crater_size_min, crater_size_max, seed);
+                for &direction in &directions {
+                    assert_eq!(
+                        shape.crater_depth(direction),
+                        full_crater_depth(&shape, direction),
+                        "{} craters, seed {}, at {}",
+                        num_craters,
+                        seed,
+                        direction
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn oversized_crater_grid_is_not_uploaded() {
+        let small_craters = evaluator(MAX_CRATER, 0.01, 0.14, 0).crater_grid;
+        assert_eq!(small_craters.get_buffer_data(), small_craters.data);
+
+        let large_craters = evaluator(MAX_CRATER, 0.1, 1.0, 0).crater_grid;
+        assert!(large_craters.data.len() > CRATER_GRID_CAPACITY);
+        assert_eq!(large_craters.get_buffer_data(), [0; CRATER_GRID_CELLS + 1]);
+    }
+}
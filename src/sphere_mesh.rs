@@ -85,6 +85,11 @@ impl SphereMesh {
         SphereMesh { vertices, indices }
     }
 
+    /// Length of the first edge, used to scale the vertex perturbation to the mesh density.
+    pub fn edge_length(&self) -> f32 {
+        (self.vertices[self.indices[0] as usize] - self.vertices[self.indices[1] as usize]).length()
+    }
+
     fn create_face(
         vertices: &mut Vec<Vec3>,
         indices: &mut Vec<u32>,
@@ -0,0 +1,260 @@
+use std::collections::HashSet;
+use std::f32::consts::PI;
+use std::fs;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+use bevy::app::{App, Plugin};
+use bevy::asset::{Assets, Handle};
+use bevy::math::Vec3;
+use bevy::prelude::{Mesh, Query, Res, ResMut, Resource, Trigger, With};
+
+use crate::asteroid_mesh_builder::Asteroid;
+use crate::export_error::{io_error, ExportError};
+use crate::export_mesh::{ExportFormat, ExportMesh};
+use crate::export_task::{ExportButtonClicked, ExportOutput, ExportStatus, ExportTaskPlugin};
+use crate::mass_properties::write_mass_properties;
+use crate::mesh_quality::check_export_mesh;
+use crate::mesh_validation::{analyze_mesh_quality, validate_mesh, MeshValidationReport};
+use crate::settings::print_settings::PrintSettings;
+use crate::settings::mass_settings::MassSettings;
+use crate::settings::mesh_quality_settings::MeshQualitySettings;
+use crate::settings::scale_settings::ScaleSettings;
+
+pub struct StlExporter;
+
+/// Outcome of the last STL export, shown in the settings window.
+#[derive(Resource, Default, Debug)]
+pub struct PrintReport {
+    pub validation: Option<MeshValidationReport>,
+    /// Triangles of a hollow print that cut through its other shell or themselves, usually from a wall
+    /// thicker than the surface detail.
+    pub self_intersecting_triangles: usize,
+    pub warnings: Vec<String>,
+}
+
+impl PrintReport {
+    pub fn is_printable(&self) -> bool {
+        self.validation.as_ref().is_some_and(|validation| validation.is_valid()) && self.self_intersecting_triangles == 0
+    }
+}
+
+impl Plugin for StlExporter {
+    fn build(&self, app: &mut App) {
+        if !app.is_plugin_added::<ExportTaskPlugin>() {
+            app.add_plugins(ExportTaskPlugin);
+        }
+
+        app.insert_resource(PrintSettings::default())
+            .insert_resource(PrintReport::default())
+            .init_resource::<ScaleSettings>()
+            .init_resource::<MassSettings>()
+            .init_resource::<MeshQualitySettings>()
+            .observe(export_stl);
+    }
+}
+
+/// Triangle mesh in millimetres, ready to be written as STL.
+pub struct PrintMesh {
+    pub positions: Vec<Vec3>,
+    pub indices: Vec<u32>,
+}
+
+fn export_stl(
+    trigger: Trigger<ExportButtonClicked>,
+    asteroid_query: Query<&Handle<Mesh>, With<Asteroid>>,
+    meshes: Res<Assets<Mesh>>,
+    print_settings: Res<PrintSettings>,
+    scale_settings: Res<ScaleSettings>,
+    quality_settings: Res<MeshQualitySettings>,
+    mass_settings: Res<MassSettings>,
+    mut export_status: ResMut<ExportStatus>,
+) {
+    let ev = trigger.event();
+    if ev.format != ExportFormat::Stl {
+        return;
+    }
+
+    let mut export_mesh = match ExportMesh::from_asteroid(&asteroid_query, &meshes) {
+        Ok(export_mesh) => export_mesh,
+        Err(err) => {
+            export_status.fail(err);
+            return;
+        }
+    };
+
+    let path = ev.path.clone();
+    let print_settings = print_settings.clone();
+    let scale_settings = scale_settings.clone();
+    let quality_settings = quality_settings.clone();
+    let density = mass_settings.density;
+
+    export_status.spawn(move || {
+        let notes = check_export_mesh(&mut export_mesh, &quality_settings)?;
+        let mass_properties = export_mesh.get_mass_properties(density).scaled(scale_settings.get_scale_in_metres());
+        // Prints are sized by the target size, so only the axis convention applies
+        export_mesh.convert_axes(&scale_settings);
+
+        let mut warnings = Vec::new();
+        let print_mesh = build_print_mesh(&export_mesh, &print_settings, &mut warnings);
+        let validation = validate_mesh(&print_mesh.positions, &print_mesh.indices);
+        let self_intersecting_triangles = if print_settings.hollow {
+            analyze_mesh_quality(&print_mesh.positions, &print_mesh.indices).self_intersecting_triangles
+        } else {
+            0
+        };
+        let print_report = PrintReport {
+            validation: Some(validation),
+            self_intersecting_triangles,
+            warnings,
+        };
+
+        if !print_report.is_printable() {
+            return Err(ExportError::NotPrintable(print_report));
+        }
+
+        write_stl(&print_mesh, &path).map_err(io_error(&path))?;
+        let mass_path = write_mass_properties(&mass_properties, &path)?;
+        Ok(ExportOutput {
+            paths: vec![path, mass_path],
+            print_report: Some(print_report),
+            notes,
+        })
+    });
+}
+
+/// Scales the asteroid to the target size and optionally hollows it out with drain holes.
+pub fn build_print_mesh(export_mesh: &ExportMesh, print_settings: &PrintSettings, warnings: &mut Vec<String>) -> PrintMesh {
+    let outer: Vec<Vec3> = export_mesh.positions.iter().map(|&p| Vec3::from(p)).collect();
+
+    let (min, max) = outer.iter().fold(
+        (Vec3::splat(f32::MAX), Vec3::splat(f32::MIN)),
+        |(min, max), &p| (min.min(p), max.max(p)),
+    );
+    let extent = (max - min).max_element();
+    let scale = if extent > 0.0 { print_settings.target_size_mm / extent } else { 1.0 };
+
+    let outer: Vec<Vec3> = outer.iter().map(|&p| p * scale).collect();
+
+    if !print_settings.hollow {
+        return PrintMesh {
+            positions: outer,
+            indices: export_mesh.indices.clone(),
+        };
+    }
+
+    let wall = print_settings.wall_thickness_mm;
+    if wall * 2.0 >= print_settings.target_size_mm {
+        warnings.push("Wall thickness is too large for the target size, printing solid".to_string());
+        return PrintMesh {
+            positions: outer,
+            indices: export_mesh.indices.clone(),
+        };
+    }
+
+    let vertex_count = outer.len() as u32;
+    let inner: Vec<Vec3> = outer
+        .iter()
+        .zip(&export_mesh.normals)
+        .map(|(&p, &n)| p - Vec3::from(n).normalize_or_zero() * wall)
+        .collect();
+
+    // Triangles inside a drain hole are removed from both shells
+    let hole_radius = print_settings.drain_hole_diameter_mm * 0.5;
+    let hole_directions = get_drain_hole_directions(print_settings.num_drain_holes);
+
+    let mut removed: HashSet<usize> = HashSet::new();
+    for &direction in &hole_directions {
+        let before = removed.len();
+        for (triangle_index, triangle) in export_mesh.indices.chunks_exact(3).enumerate() {
+            let centroid = (outer[triangle[0] as usize] + outer[triangle[1] as usize] + outer[triangle[2] as usize]) / 3.0;
+            let distance_to_axis = (centroid - direction * centroid.dot(direction)).length();
+            if centroid.dot(direction) > 0.0 && distance_to_axis < hole_radius {
+                removed.insert(triangle_index);
+            }
+        }
+        if removed.len() == before {
+            warnings.push("Drain hole is smaller than a triangle and was skipped".to_string());
+        }
+    }
+
+    let mut indices = Vec::with_capacity(export_mesh.indices.len() * 2);
+    for (triangle_index, triangle) in export_mesh.indices.chunks_exact(3).enumerate() {
+        if removed.contains(&triangle_index) {
+            continue;
+        }
+        let (a, b, c) = (triangle[0], triangle[1], triangle[2]);
+        indices.extend_from_slice(&[a, b, c]);
+        // Inner shell faces inwards, so its winding is reversed
+        indices.extend_from_slice(&[a + vertex_count, c + vertex_count, b + vertex_count]);
+    }
+
+    // Stitch each hole rim of the outer shell to the matching rim of the inner shell.
+    // An outer edge a -> b without a twin b -> a lies on a rim.
+    let mut outer_edges: HashSet<(u32, u32)> = HashSet::new();
+    for (triangle_index, triangle) in export_mesh.indices.chunks_exact(3).enumerate() {
+        if removed.contains(&triangle_index) {
+            continue;
+        }
+        for (from, to) in [(triangle[0], triangle[1]), (triangle[1], triangle[2]), (triangle[2], triangle[0])] {
+            outer_edges.insert((from, to));
+        }
+    }
+    for &(a, b) in &outer_edges {
+        if outer_edges.contains(&(b, a)) {
+            continue;
+        }
+        let (inner_a, inner_b) = (a + vertex_count, b + vertex_count);
+        indices.extend_from_slice(&[b, a, inner_a]);
+        indices.extend_from_slice(&[b, inner_a, inner_b]);
+    }
+
+    let mut positions = outer;
+    positions.extend(inner);
+
+    PrintMesh { positions, indices }
+}
+
+/// Spreads `count` drain holes from the bottom to the top of the asteroid along a Fibonacci spiral, so one hole
+/// sits at the bottom and two sit at the bottom and the top.
+fn get_drain_hole_directions(count: u32) -> Vec<Vec3> {
+    let golden_angle = PI * (3.0 - 5f32.sqrt());
+    (0..count)
+        .map(|i| {
+            let y = if count == 1 { -1.0 } else { -1.0 + 2.0 * i as f32 / (count - 1) as f32 };
+            let radius = (1.0 - y * y).max(0.0).sqrt();
+            let angle = golden_angle * i as f32;
+            Vec3::new(radius * angle.cos(), y, radius * angle.sin())
+        })
+        .collect()
+}
+
+/// Writes a binary STL in millimetres.
+pub fn write_stl(print_mesh: &PrintMesh, path: &Path) -> std::io::Result<()> {
+    let mut writer = BufWriter::new(fs::File::create(path)?);
+
+    let mut header = [0u8; 80];
+    let title = b"AstroGen asteroid, units: mm";
+    header[..title.len()].copy_from_slice(title);
+    writer.write_all(&header)?;
+
+    let triangle_count = (print_mesh.indices.len() / 3) as u32;
+    writer.write_all(&triangle_count.to_le_bytes())?;
+
+    for triangle in print_mesh.indices.chunks_exact(3) {
+        let v0 = print_mesh.positions[triangle[0] as usize];
+        let v1 = print_mesh.positions[triangle[1] as usize];
+        let v2 = print_mesh.positions[triangle[2] as usize];
+        let normal = (v1 - v0).cross(v2 - v0).normalize_or_zero();
+
+        for vector in [normal, v0, v1, v2] {
+            for value in vector.to_array() {
+                writer.write_all(&value.to_le_bytes())?;
+            }
+        }
+        // Attribute byte count, unused
+        writer.write_all(&0u16.to_le_bytes())?;
+    }
+
+    writer.flush()
+}
@@ -1,4 +1,6 @@
+use std::fs;
 use std::ops::RangeInclusive;
+use std::path::PathBuf;
 
 use bevy::app::{App, Plugin};
 use bevy::diagnostic::{DiagnosticsStore, FrameTimeDiagnosticsPlugin};
@@ -7,41 +9,39 @@ use bevy::window::WindowMode;
 use bevy_egui::{egui, EguiContexts};
 use bevy_egui::egui::{FontId, RichText};
 
-use crate::compute_events::{CraterSettingsChanged, PerturbStrengthChanged, RidgeNoiseSettingsChanged, SimpleNoiseSettingsChanged};
+use crate::asteroid_field::{AsteroidField, FieldShapeJobs, GenerateFieldClicked};
+use crate::asteroid_material::DetailTexture;
+use crate::compute_events::{CraterSettingsChanged, MaterialSettingsChanged, PerturbStrengthChanged, RidgeNoiseSettingsChanged, SimpleNoiseSettingsChanged, ValueChanged};
+use crate::export_error::ExportError;
+use crate::export_mesh::ExportFormat;
+use crate::export_task::{ExportButtonClicked, ExportMessage, ExportStatus};
+use crate::generation_jobs::GenerationJobs;
+use crate::gltf_importer::{ImportStatus, OpenButtonClicked};
+use crate::mass_properties::AsteroidMassProperties;
+use crate::mesh_quality::MeshQuality;
+use crate::RngSeed;
+use crate::shape_evaluator::ELEVATION_MULTIPLIER;
 use crate::settings::asteroid_settings::AsteroidSettings;
+use crate::settings::collider_settings::ColliderOutput;
+use crate::settings::crater_settings::{MAX_CRATER, MIN_CRATER_VALUE};
+use crate::settings::field_settings::{FieldLayout, FieldSettings};
+use crate::settings::export_settings::{ExportSettings, GltfOutput, HeightmapLayout};
+use crate::settings::mass_settings::MassSettings;
+use crate::settings::mesh_quality_settings::{ExportQualityAction, MeshQualitySettings};
+use crate::settings::scale_settings::{Handedness, LengthUnit, ScaleSettings, UpAxis};
+use crate::settings::simple_noise_settings::MAX_NOISE_LAYERS;
+use crate::settings::spin_settings::SpinSettings;
+use crate::settings::validation::Severity;
+use crate::settings::print_settings::PrintSettings;
+use crate::stl_exporter::PrintReport;
 
 pub struct UIAsteroidSettings;
-#[derive(Event)]
-pub struct ExportButtonClicked;
-
-
-#[derive(Resource)]
-struct ValueChanged {
-    pub perturb_strength: bool,
-    pub crater_settings: bool,
-    pub simple_noise_settings: bool,
-    pub ridge_noise_settings: bool,
-    pub ridge_noise_settings2: bool,
-}
-
-impl Default for ValueChanged {
-    fn default() -> Self {
-        ValueChanged {
-            perturb_strength: true,
-            crater_settings: true,
-            simple_noise_settings: true,
-            ridge_noise_settings: true,
-            ridge_noise_settings2: true,
-        }
-    }
-}
 
 impl Plugin for UIAsteroidSettings {
     fn build(&self, app: &mut App) {
-        app
-            .insert_resource(ValueChanged::default())
-            .add_event::<ExportButtonClicked>()
-            .add_systems(Update, show_ui);
+        app.init_resource::<ExportSettings>()
+            .init_resource::<ScaleSettings>()
+            .add_systems(Update, (show_ui, show_field_ui, show_mesh_quality_ui, show_print_ui, show_mass_ui));
     }
 }
 
@@ -50,7 +50,15 @@ fn show_ui(mut contexts: EguiContexts,
            mut settings: ResMut<AsteroidSettings>,
            mut commands: Commands,
            mut value_changed: ResMut<ValueChanged>,
-           mut status_changed: Local<String>,
+           mut export_status: ResMut<ExportStatus>,
+           mut export_format: Local<ExportFormat>,
+           mut pending_overwrite: Local<Option<(ExportFormat, PathBuf)>>,
+           mut export_settings: ResMut<ExportSettings>,
+           seed: Res<RngSeed>,
+           import_status: Res<ImportStatus>,
+           detail_texture: Res<DetailTexture>,
+           mut scale_settings: ResMut<ScaleSettings>,
+           mut spin_settings: ResMut<SpinSettings>,
            mut window: Query<&mut Window>,
 ) {
     if let Some(ctx) = contexts.try_ctx_mut() {
@@ -73,17 +81,50 @@ fn show_ui(mut contexts: EguiContexts,
                         ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
                             ui.add_space(10.);
                             let export_button = egui::Button::new(
-                                RichText::new("Export glb")
+                                RichText::new("Export")
                                     .strong()
                                     .font(FontId::proportional(20.0))
                                     .color(egui::Color32::WHITE))
                                 .fill(egui::Color32::from_rgb(99, 181, 74));
 
                             if ui.add(export_button).clicked() {
-                                commands.trigger(ExportButtonClicked);
-                                *status_changed = "Saved to asteroid.glb".to_string();
+                                let path = export_settings.get_path(*export_format, seed.0);
+                                let exists = export_settings
+                                    .get_written_paths(*export_format, &path)
+                                    .iter()
+                                    .any(|path| path.exists());
+
+                                if exists {
+                                    *pending_overwrite = Some((*export_format, path));
+                                } else {
+                                    request_export(&mut commands, &mut export_status, *export_format, path);
+                                }
+                            }
+                            egui::ComboBox::from_id_source("export_format")
+                                .selected_text(export_format.label())
+                                .show_ui(ui, |ui| {
+                                    for format in ExportFormat::ALL {
+                                        ui.selectable_value(&mut *export_format, format, format.label());
+                                    }
+                                });
+                            if export_status.is_running() {
+                                ui.spinner();
+                                ui.label("Exporting…");
+                            }
+                        });
+                        ui.add_space(5.);
+                        ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                            ui.add_space(10.);
+                            if ui.button(RichText::new("Open glb…").font(FontId::proportional(15.0))).clicked() {
+                                if let Some(path) = rfd::FileDialog::new()
+                                    .add_filter("glTF", &["glb", "gltf"])
+                                    .set_directory(&export_settings.output_dir)
+                                    .pick_file()
+                                {
+                                    commands.trigger(OpenButtonClicked(path));
+                                }
                             }
-                            ui.label(&*status_changed);
+                            ui.label(&import_status.0);
                         });
                         ui.add_space(5.);
                         ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
@@ -106,20 +147,155 @@ fn show_ui(mut contexts: EguiContexts,
                     });
                 });
 
-                let slider = |ui: &mut egui::Ui, label: &str, value: &mut f32, step: f64, range: RangeInclusive<f32>, changed: &mut bool| {
-                    ui.style_mut().spacing.slider_width = 200.;
-
-                    let response = ui.add(
-                        egui::Slider::new(value, range)
-                            .text(label)
-                            .step_by(step)
-                            .clamp_to_range(true),
-                    );
-                    if response.changed() {
-                        *changed = true;
+                for message in &export_status.messages {
+                    match message {
+                        ExportMessage::Saved { path, bytes } => {
+                            ui.label(format!("Saved {} ({})", path.display(), format_bytes(*bytes)));
+                        }
+                        ExportMessage::Failed(err) => {
+                            ui.colored_label(egui::Color32::from_rgb(220, 80, 80), err);
+                        }
+                        ExportMessage::Info(info) => {
+                            ui.label(info);
+                        }
+                    }
+                }
+
+                egui::CollapsingHeader::new(RichText::new("Export Settings").font(FontId::proportional(20.0)))
+                    .default_open(false)
+                    .show(ui, |ui| {
+                        ui.horizontal(|ui| {
+                            ui.label("Output directory:");
+                            ui.text_edit_singleline(&mut export_settings.output_dir);
+                            if ui.button("Browse…").clicked() {
+                                if let Some(dir) = rfd::FileDialog::new().set_directory(&export_settings.output_dir).pick_folder() {
+                                    export_settings.output_dir = dir.display().to_string();
+                                }
+                            }
+                        });
+                        ui.horizontal(|ui| {
+                            ui.label("File name:");
+                            ui.text_edit_singleline(&mut export_settings.filename_template);
+                        });
+                        ui.horizontal(|ui| {
+                            ui.label("Preset:");
+                            ui.text_edit_singleline(&mut export_settings.preset_name);
+                        });
+                        ui.label(RichText::new("Placeholders: {seed} {preset} {date} {time} {format}").weak());
+                        ui.horizontal(|ui| {
+                            ui.label("glTF output:");
+                            ui.radio_value(&mut export_settings.gltf_output, GltfOutput::Binary, ".glb");
+                            ui.radio_value(&mut export_settings.gltf_output, GltfOutput::Standard, ".gltf + .bin");
+                        });
+                        ui.horizontal(|ui| {
+                            ui.checkbox(&mut export_settings.gltf_quantize, "Quantize");
+                            ui.checkbox(&mut export_settings.gltf_meshopt_compression, "meshopt compression");
+                        });
+                        let collider_settings = &mut export_settings.collider_settings;
+                        ui.horizontal(|ui| {
+                            ui.label("Colliders:");
+                            ui.checkbox(&mut collider_settings.convex_hull, "Convex hull");
+                            ui.checkbox(&mut collider_settings.convex_decomposition, "Convex decomposition");
+                        });
+                        ui.add_enabled_ui(collider_settings.convex_decomposition, |ui| {
+                            ui.add(egui::Slider::new(&mut collider_settings.max_convex_hulls, 1..=64).text("Max convex hulls"));
+                            ui.add(egui::Slider::new(&mut collider_settings.resolution, 16..=256).text("Voxel resolution"));
+                        });
+                        ui.add_enabled_ui(collider_settings.is_enabled(), |ui| {
+                            ui.horizontal(|ui| {
+                                ui.label("Collider output:");
+                                ui.radio_value(&mut collider_settings.output, ColliderOutput::GltfNodes, "glTF nodes");
+                                ui.radio_value(&mut collider_settings.output, ColliderOutput::SeparateFile, "Separate file");
+                            });
+                        });
+                        ui.horizontal(|ui| {
+                            ui.label("Heightmap layout:");
+                            ui.radio_value(&mut export_settings.heightmap_layout, HeightmapLayout::Equirectangular, "Equirectangular");
+                            ui.radio_value(&mut export_settings.heightmap_layout, HeightmapLayout::CubeMap, "Cube map");
+                        });
+                        let resolution_label = match export_settings.heightmap_layout {
+                            HeightmapLayout::Equirectangular => "Heightmap height (px)",
+                            HeightmapLayout::CubeMap => "Heightmap face size (px)",
+                        };
+                        ui.add(egui::Slider::new(&mut export_settings.heightmap_resolution, 64..=8192).logarithmic(true).text(resolution_label));
+                        ui.label(format!("Next export: {}", export_settings.get_path(*export_format, seed.0).display()));
+                    });
+
+                egui::CollapsingHeader::new(RichText::new("Scale").font(FontId::proportional(20.0)))
+                    .default_open(false)
+                    .show(ui, |ui| {
+                        ui.horizontal(|ui| {
+                            ui.label("Body radius:");
+                            ui.add(egui::DragValue::new(&mut scale_settings.body_radius).speed(0.1).range(0.001..=f32::MAX));
+                            ui.radio_value(&mut scale_settings.unit, LengthUnit::Metres, "m");
+                            ui.radio_value(&mut scale_settings.unit, LengthUnit::Kilometres, "km");
+                        });
+                        ui.label(RichText::new("glTF is always written in metres, Y-up and right-handed").weak());
+                        ui.horizontal(|ui| {
+                            ui.label("Up axis:");
+                            ui.radio_value(&mut scale_settings.up_axis, UpAxis::Y, "Y");
+                            ui.radio_value(&mut scale_settings.up_axis, UpAxis::Z, "Z");
+                        });
+                        ui.horizontal(|ui| {
+                            ui.label("Handedness:");
+                            ui.radio_value(&mut scale_settings.handedness, Handedness::Right, "Right");
+                            ui.radio_value(&mut scale_settings.handedness, Handedness::Left, "Left");
+                        });
+                    });
+
+                egui::CollapsingHeader::new(RichText::new("Spin").font(FontId::proportional(20.0)))
+                    .default_open(false)
+                    .show(ui, |ui| {
+                        ui.checkbox(&mut spin_settings.enabled, "Spin (viewer and glTF animation)");
+                        ui.add_enabled_ui(spin_settings.enabled, |ui| {
+                            ui.horizontal(|ui| {
+                                ui.label("Period (s):");
+                                ui.add(egui::DragValue::new(&mut spin_settings.period).speed(0.1).range(0.1..=f32::MAX));
+                            });
+                            ui.add(egui::Slider::new(&mut spin_settings.axis_tilt, 0.0..=180.0).text("Axis tilt (°)"));
+                            ui.add(egui::Slider::new(&mut spin_settings.axis_azimuth, 0.0..=360.0).text("Axis azimuth (°)"));
+                            let [x, y, z] = spin_settings.get_axis().to_array();
+                            ui.label(RichText::new(format!("Axis: ({:.2}, {:.2}, {:.2})", x, y, z)).weak());
+
+                            ui.checkbox(&mut spin_settings.tumbling, "Tumbling (non-principal-axis)");
+                            ui.add_enabled_ui(spin_settings.tumbling, |ui| {
+                                ui.horizontal(|ui| {
+                                    ui.label("Precession period (s):");
+                                    ui.add(egui::DragValue::new(&mut spin_settings.precession_period).speed(0.1).range(0.1..=f32::MAX));
+                                });
+                                ui.add(egui::Slider::new(&mut spin_settings.nutation_angle, 0.0..=90.0).text("Nutation angle (°)"));
+                                ui.label(RichText::new(format!(
+                                    "Spin period snapped to {:.2} s so the animation loops",
+                                    spin_settings.get_spin_period()
+                                )).weak());
+                            });
+                        });
+                    });
+
+                // Converts a fraction of the radius to the body units for display
+                let scale = scale_settings.get_scale();
+                let unit = scale_settings.unit.label();
+                let in_units = |ui: &mut egui::Ui, label: &str, value: f32| {
+                    ui.label(RichText::new(format!("{}: {:.3} {}", label, value * scale, unit)).weak());
+                };
+
+                // Validation problems, shown under the field they belong to
+                let report = settings.validate();
+                let issues = |ui: &mut egui::Ui, field: &str| {
+                    for issue in report.get_field_issues(field) {
+                        let color = match issue.severity {
+                            Severity::Warning => egui::Color32::from_rgb(230, 180, 60),
+                            Severity::Error => egui::Color32::from_rgb(220, 80, 60),
+                        };
+                        ui.colored_label(color, &issue.message);
                     }
                 };
 
+                let setting_slider = |ui: &mut egui::Ui, label: &str, field: &str, value: &mut f32, step: f64, range: RangeInclusive<f32>, changed: &mut bool| {
+                    slider(ui, label, value, step, range, changed);
+                    issues(ui, field);
+                };
+
                 let drag_value = |ui: &mut egui::Ui, label: &str, value: &mut f32, changed: &mut bool| {
                     ui.horizontal(|ui| {
                         ui.label(label);
@@ -144,7 +320,7 @@ fn show_ui(mut contexts: EguiContexts,
                     });
                 };
                 ui.add_space(10.);
-                slider(ui, "Perturb Strength", &mut settings.peturb_strength, 0.01f64, 0.0..=1., &mut value_changed.perturb_strength);
+                setting_slider(ui, "Perturb Strength", "peturb_strength", &mut settings.peturb_strength, 0.01f64, 0.0..=1., &mut value_changed.perturb_strength);
                 ui.add_space(10.);
 
                 if value_changed.perturb_strength
@@ -161,14 +337,16 @@ fn show_ui(mut contexts: EguiContexts,
                 egui::CollapsingHeader::new(RichText::new("Crater Settings").font(FontId::proportional(20.0)))
                     .default_open(true)
                     .show(ui, |ui| {
-                        slider(ui, "Number of Craters", &mut crater_settings.num_craters, 10f64, 0.0..=2000., &mut value_changed.crater_settings);
-                        slider(ui, "Crater size min", &mut crater_settings.crater_size_min, 0.01f64, 0.0..=1., &mut value_changed.crater_settings);
-                        slider(ui, "Crater size max", &mut crater_settings.crater_size_max, 0.01f64, 0.1..=1., &mut value_changed.crater_settings);
-                        slider(ui, "Rim steepness", &mut crater_settings.rim_steepness, 0.01f64, 0.0..=2., &mut value_changed.crater_settings);
-                        slider(ui, "Rim Width", &mut crater_settings.rim_width, 0.01f64, 0.0..=5., &mut value_changed.crater_settings);
-                        slider(ui, "Smooth min", &mut crater_settings.smooth_min, 0.01f64, 0.0..=1., &mut value_changed.crater_settings);
-                        slider(ui, "Smooth max", &mut crater_settings.smooth_max, 0.01f64, 0.1..=2., &mut value_changed.crater_settings);
-                        slider(ui, "Size distribution", &mut crater_settings.size_distribution, 0.01f64, 0.0..=1., &mut value_changed.crater_settings);
+                        setting_slider(ui, "Number of Craters", "crater_settings.num_craters", &mut crater_settings.num_craters, 10f64, 0.0..=MAX_CRATER as f32, &mut value_changed.crater_settings);
+                        setting_slider(ui, "Crater size min", "crater_settings.crater_size_min", &mut crater_settings.crater_size_min, 0.01f64, MIN_CRATER_VALUE..=1., &mut value_changed.crater_settings);
+                        setting_slider(ui, "Crater size max", "crater_settings.crater_size_max", &mut crater_settings.crater_size_max, 0.01f64, 0.1..=1., &mut value_changed.crater_settings);
+                        setting_slider(ui, "Rim steepness", "crater_settings.rim_steepness", &mut crater_settings.rim_steepness, 0.01f64, 0.0..=2., &mut value_changed.crater_settings);
+                        setting_slider(ui, "Rim Width", "crater_settings.rim_width", &mut crater_settings.rim_width, 0.01f64, 0.0..=5., &mut value_changed.crater_settings);
+                        setting_slider(ui, "Smooth min", "crater_settings.smooth_min", &mut crater_settings.smooth_min, 0.01f64, MIN_CRATER_VALUE..=1., &mut value_changed.crater_settings);
+                        setting_slider(ui, "Smooth max", "crater_settings.smooth_max", &mut crater_settings.smooth_max, 0.01f64, 0.1..=2., &mut value_changed.crater_settings);
+                        setting_slider(ui, "Size distribution", "crater_settings.size_distribution", &mut crater_settings.size_distribution, 0.01f64, 0.0..=1., &mut value_changed.crater_settings);
+                        in_units(ui, "Smallest crater radius", crater_settings.crater_size_min);
+                        in_units(ui, "Largest crater radius", crater_settings.crater_size_max);
                     });
                 ui.add_space(spacing);
 
@@ -184,15 +362,17 @@ fn show_ui(mut contexts: EguiContexts,
                 egui::CollapsingHeader::new(RichText::new("Simple Noise Settings").font(FontId::proportional(20.0)))
                     .default_open(true)
                     .show(ui, |ui| {
-                        slider(ui, "Number of layers", &mut simple_noise_settings.num_layers, 1f64, 0.0..=40., &mut value_changed.simple_noise_settings);
-                        slider(ui, "Lacunarity", &mut simple_noise_settings.lacunarity, 0.1f64, 0.0..=5., &mut value_changed.simple_noise_settings);
-                        slider(ui, "Persistence", &mut simple_noise_settings.persistence, 0.1f64, 0.0..=5., &mut value_changed.simple_noise_settings);
-                        slider(ui, "Scale", &mut simple_noise_settings.scale, 0.1f64, 0.0..=10., &mut value_changed.simple_noise_settings);
-                        slider(ui, "Elevation", &mut simple_noise_settings.elevation, 0.1f64, 0.0..=5., &mut value_changed.simple_noise_settings);
-                        slider(ui, "Vertical Shift", &mut simple_noise_settings.vertical_shift, 0.1f64, 0.0..=5., &mut value_changed.simple_noise_settings);
+                        setting_slider(ui, "Number of layers", "simple_noise_settings.num_layers", &mut simple_noise_settings.num_layers, 1f64, 0.0..=MAX_NOISE_LAYERS, &mut value_changed.simple_noise_settings);
+                        setting_slider(ui, "Lacunarity", "simple_noise_settings.lacunarity", &mut simple_noise_settings.lacunarity, 0.1f64, 0.0..=5., &mut value_changed.simple_noise_settings);
+                        setting_slider(ui, "Persistence", "simple_noise_settings.persistence", &mut simple_noise_settings.persistence, 0.1f64, 0.0..=5., &mut value_changed.simple_noise_settings);
+                        setting_slider(ui, "Scale", "simple_noise_settings.scale", &mut simple_noise_settings.scale, 0.1f64, 0.0..=10., &mut value_changed.simple_noise_settings);
+                        setting_slider(ui, "Elevation", "simple_noise_settings.elevation", &mut simple_noise_settings.elevation, 0.1f64, 0.0..=5., &mut value_changed.simple_noise_settings);
+                        in_units(ui, "Elevation scale", simple_noise_settings.elevation.abs() * ELEVATION_MULTIPLIER);
+                        setting_slider(ui, "Vertical Shift", "simple_noise_settings.vertical_shift", &mut simple_noise_settings.vertical_shift, 0.1f64, 0.0..=5., &mut value_changed.simple_noise_settings);
 
                         ui.label("Offset:");
                         offset(ui, &mut simple_noise_settings.offset_x, &mut simple_noise_settings.offset_y, &mut simple_noise_settings.offset_z, &mut value_changed.simple_noise_settings);
+                        issues(ui, "simple_noise_settings.offset_");
                     });
                 ui.add_space(spacing);
 
@@ -208,18 +388,20 @@ fn show_ui(mut contexts: EguiContexts,
                 egui::CollapsingHeader::new(RichText::new("Ridge Noise Settings").font(FontId::proportional(20.0)))
                     .default_open(true)
                     .show(ui, |ui| {
-                        slider(ui, "Number of layers", &mut ridge_noise_settings.num_layers, 1f64, 0.0..=40., &mut value_changed.ridge_noise_settings);
-                        slider(ui, "Lacunarity", &mut ridge_noise_settings.lacunarity, 0.1f64, 0.0..=5., &mut value_changed.ridge_noise_settings);
-                        slider(ui, "Persistence", &mut ridge_noise_settings.persistence, 0.1f64, 0.0..=5., &mut value_changed.ridge_noise_settings);
-                        slider(ui, "Scale", &mut ridge_noise_settings.scale, 0.1f64, 0.0..=5., &mut value_changed.ridge_noise_settings);
-                        slider(ui, "Power", &mut ridge_noise_settings.power, 0.1f64, 0.0..=5., &mut value_changed.ridge_noise_settings);
-                        slider(ui, "Elevation", &mut ridge_noise_settings.elevation, 0.1f64, -5.0..=5., &mut value_changed.ridge_noise_settings);
-                        slider(ui, "Gain", &mut ridge_noise_settings.gain, 0.1f64, 0.0..=10., &mut value_changed.ridge_noise_settings);
-                        slider(ui, "Vertical Shift", &mut ridge_noise_settings.vertical_shift, 0.1f64, 0.0..=5., &mut value_changed.ridge_noise_settings);
-                        slider(ui, "Peak Smoothing", &mut ridge_noise_settings.peak_smoothing, 0.1f64, 0.0..=5., &mut value_changed.ridge_noise_settings);
+                        setting_slider(ui, "Number of layers", "ridge_noise_settings.num_layers", &mut ridge_noise_settings.num_layers, 1f64, 0.0..=MAX_NOISE_LAYERS, &mut value_changed.ridge_noise_settings);
+                        setting_slider(ui, "Lacunarity", "ridge_noise_settings.lacunarity", &mut ridge_noise_settings.lacunarity, 0.1f64, 0.0..=5., &mut value_changed.ridge_noise_settings);
+                        setting_slider(ui, "Persistence", "ridge_noise_settings.persistence", &mut ridge_noise_settings.persistence, 0.1f64, 0.0..=5., &mut value_changed.ridge_noise_settings);
+                        setting_slider(ui, "Scale", "ridge_noise_settings.scale", &mut ridge_noise_settings.scale, 0.1f64, 0.0..=5., &mut value_changed.ridge_noise_settings);
+                        setting_slider(ui, "Power", "ridge_noise_settings.power", &mut ridge_noise_settings.power, 0.1f64, 0.0..=5., &mut value_changed.ridge_noise_settings);
+                        setting_slider(ui, "Elevation", "ridge_noise_settings.elevation", &mut ridge_noise_settings.elevation, 0.1f64, -5.0..=5., &mut value_changed.ridge_noise_settings);
+                        in_units(ui, "Elevation scale", ridge_noise_settings.elevation.abs() * ELEVATION_MULTIPLIER);
+                        setting_slider(ui, "Gain", "ridge_noise_settings.gain", &mut ridge_noise_settings.gain, 0.1f64, 0.0..=10., &mut value_changed.ridge_noise_settings);
+                        setting_slider(ui, "Vertical Shift", "ridge_noise_settings.vertical_shift", &mut ridge_noise_settings.vertical_shift, 0.1f64, 0.0..=5., &mut value_changed.ridge_noise_settings);
+                        setting_slider(ui, "Peak Smoothing", "ridge_noise_settings.peak_smoothing", &mut ridge_noise_settings.peak_smoothing, 0.1f64, 0.0..=5., &mut value_changed.ridge_noise_settings);
 
                         ui.label("Offset:");
                         offset(ui, &mut ridge_noise_settings.offset_x, &mut ridge_noise_settings.offset_y, &mut ridge_noise_settings.offset_z, &mut value_changed.ridge_noise_settings);
+                        issues(ui, "ridge_noise_settings.offset_");
                     });
                 ui.add_space(spacing);
 
@@ -236,18 +418,20 @@ fn show_ui(mut contexts: EguiContexts,
                 egui::CollapsingHeader::new(RichText::new("Ridge Noise Settings 2").font(FontId::proportional(20.0)))
                     .default_open(true)
                     .show(ui, |ui| {
-                        slider(ui, "Number of layers", &mut ridge_noise_settings2.num_layers, 1f64, 0.0..=40., &mut value_changed.ridge_noise_settings2);
-                        slider(ui, "Lacunarity", &mut ridge_noise_settings2.lacunarity, 0.1f64, 0.0..=5., &mut value_changed.ridge_noise_settings2);
-                        slider(ui, "Persistence", &mut ridge_noise_settings2.persistence, 0.1f64, 0.0..=5., &mut value_changed.ridge_noise_settings2);
-                        slider(ui, "Scale", &mut ridge_noise_settings2.scale, 0.1f64, 0.0..=5., &mut value_changed.ridge_noise_settings2);
-                        slider(ui, "Power", &mut ridge_noise_settings2.power, 0.1f64, 0.0..=5., &mut value_changed.ridge_noise_settings2);
-                        slider(ui, "Elevation", &mut ridge_noise_settings2.elevation, 0.1f64, -5.0..=5., &mut value_changed.ridge_noise_settings2);
-                        slider(ui, "Gain", &mut ridge_noise_settings2.gain, 0.1f64, 0.0..=10., &mut value_changed.ridge_noise_settings2);
-                        slider(ui, "Vertical Shift", &mut ridge_noise_settings2.vertical_shift, 0.1f64, 0.0..=5., &mut value_changed.ridge_noise_settings2);
-                        slider(ui, "Peak Smoothing", &mut ridge_noise_settings2.peak_smoothing, 0.1f64, 0.0..=5., &mut value_changed.ridge_noise_settings2);
+                        setting_slider(ui, "Number of layers", "ridge_noise_settings2.num_layers", &mut ridge_noise_settings2.num_layers, 1f64, 0.0..=MAX_NOISE_LAYERS, &mut value_changed.ridge_noise_settings2);
+                        setting_slider(ui, "Lacunarity", "ridge_noise_settings2.lacunarity", &mut ridge_noise_settings2.lacunarity, 0.1f64, 0.0..=5., &mut value_changed.ridge_noise_settings2);
+                        setting_slider(ui, "Persistence", "ridge_noise_settings2.persistence", &mut ridge_noise_settings2.persistence, 0.1f64, 0.0..=5., &mut value_changed.ridge_noise_settings2);
+                        setting_slider(ui, "Scale", "ridge_noise_settings2.scale", &mut ridge_noise_settings2.scale, 0.1f64, 0.0..=5., &mut value_changed.ridge_noise_settings2);
+                        setting_slider(ui, "Power", "ridge_noise_settings2.power", &mut ridge_noise_settings2.power, 0.1f64, 0.0..=5., &mut value_changed.ridge_noise_settings2);
+                        setting_slider(ui, "Elevation", "ridge_noise_settings2.elevation", &mut ridge_noise_settings2.elevation, 0.1f64, -5.0..=5., &mut value_changed.ridge_noise_settings2);
+                        in_units(ui, "Elevation scale", ridge_noise_settings2.elevation.abs() * ELEVATION_MULTIPLIER);
+                        setting_slider(ui, "Gain", "ridge_noise_settings2.gain", &mut ridge_noise_settings2.gain, 0.1f64, 0.0..=10., &mut value_changed.ridge_noise_settings2);
+                        setting_slider(ui, "Vertical Shift", "ridge_noise_settings2.vertical_shift", &mut ridge_noise_settings2.vertical_shift, 0.1f64, 0.0..=5., &mut value_changed.ridge_noise_settings2);
+                        setting_slider(ui, "Peak Smoothing", "ridge_noise_settings2.peak_smoothing", &mut ridge_noise_settings2.peak_smoothing, 0.1f64, 0.0..=5., &mut value_changed.ridge_noise_settings2);
 
                         ui.label("Offset:");
                         offset(ui, &mut ridge_noise_settings2.offset_x, &mut ridge_noise_settings2.offset_y, &mut ridge_noise_settings2.offset_z, &mut value_changed.ridge_noise_settings2);
+                        issues(ui, "ridge_noise_settings2.offset_");
                     });
 
                 if value_changed.ridge_noise_settings2 {
@@ -258,6 +442,317 @@ fn show_ui(mut contexts: EguiContexts,
                 }
 
                 value_changed.ridge_noise_settings2 = false;
+                ui.add_space(spacing);
+
+                let material_settings = &mut settings.material_settings;
+                egui::CollapsingHeader::new(RichText::new("Material Settings").font(FontId::proportional(20.0)))
+                    .default_open(true)
+                    .show(ui, |ui| {
+                        ui.horizontal(|ui| {
+                            ui.label("Base color");
+                            if ui.color_edit_button_rgb(&mut material_settings.base_color).changed() {
+                                value_changed.material_settings = true;
+                            }
+                        });
+                        issues(ui, "material_settings.base_color");
+                        setting_slider(ui, "Roughness", "material_settings.roughness", &mut material_settings.roughness, 0.01f64, 0.089..=1., &mut value_changed.material_settings);
+                        setting_slider(ui, "Metallic", "material_settings.metallic", &mut material_settings.metallic, 0.01f64, 0.0..=1., &mut value_changed.material_settings);
+                        setting_slider(ui, "Reflectance", "material_settings.reflectance", &mut material_settings.reflectance, 0.01f64, 0.0..=1., &mut value_changed.material_settings);
+
+                        if ui.checkbox(&mut material_settings.detail_enabled, "Triplanar detail texture").changed() {
+                            value_changed.material_settings = true;
+                        }
+                        ui.add_enabled_ui(material_settings.detail_enabled, |ui| {
+                            ui.horizontal(|ui| {
+                                ui.label("Texture path:");
+                                let response = ui.text_edit_singleline(&mut material_settings.detail_texture_path);
+                                if response.lost_focus() {
+                                    value_changed.material_settings = true;
+                                }
+                            });
+                            if let Some(err) = detail_texture.get_error() {
+                                ui.colored_label(egui::Color32::from_rgb(220, 80, 80), format!("Could not load the texture: {}", err));
+                            }
+                            setting_slider(ui, "Detail scale", "material_settings.detail_scale", &mut material_settings.detail_scale, 0.1f64, 0.1..=20., &mut value_changed.material_settings);
+                            setting_slider(ui, "Detail strength", "material_settings.detail_strength", &mut material_settings.detail_strength, 0.01f64, 0.0..=1., &mut value_changed.material_settings);
+                            setting_slider(ui, "Blend sharpness", "material_settings.detail_sharpness", &mut material_settings.detail_sharpness, 0.1f64, 1.0..=16., &mut value_changed.material_settings);
+                        });
+                    });
+
+                if value_changed.material_settings {
+                    commands.trigger(MaterialSettingsChanged(
+                        material_settings.clone()
+                    ));
+                }
+
+                value_changed.material_settings = false;
+            });
+
+        if let Some((format, path)) = pending_overwrite.clone() {
+            let mut confirmed = None;
+            egui::Window::new("File already exists")
+                .collapsible(false)
+                .resizable(false)
+                .anchor(egui::Align2::CENTER_CENTER, [0., 0.])
+                .show(ctx, |ui| {
+                    ui.label(format!("{} already exists. Overwrite it?", path.display()));
+                    ui.horizontal(|ui| {
+                        if ui.button("Overwrite").clicked() {
+                            confirmed = Some(true);
+                        }
+                        if ui.button("Cancel").clicked() {
+                            confirmed = Some(false);
+                        }
+                    });
+                });
+
+            match confirmed {
+                Some(true) => {
+                    request_export(&mut commands, &mut export_status, format, path);
+                    *pending_overwrite = None;
+                }
+                Some(false) => {
+                    export_status.clear();
+                    export_status.messages.push(ExportMessage::Info("Export cancelled".to_string()));
+                    *pending_overwrite = None;
+                }
+                None => {}
+            }
+        }
+    }
+}
+
+fn show_field_ui(
+    mut contexts: EguiContexts,
+    mut commands: Commands,
+    mut field_settings: ResMut<FieldSettings>,
+    scale_settings: Res<ScaleSettings>,
+    field: Res<AsteroidField>,
+    field_jobs: Res<FieldShapeJobs>,
+    jobs: Res<GenerationJobs>,
+) {
+    let Some(ctx) = contexts.try_ctx_mut() else {
+        return;
+    };
+
+    egui::Window::new("Asteroid Field")
+        .default_open(false)
+        .default_width(420.)
+        .show(ctx, |ui| {
+            let unit = scale_settings.unit.label();
+            ui.horizontal(|ui| {
+                ui.label("Layout:");
+                for layout in [FieldLayout::Belt, FieldLayout::Ring, FieldLayout::Cluster] {
+                    ui.radio_value(&mut field_settings.layout, layout, layout.label());
+                }
+            });
+            ui.horizontal(|ui| {
+                ui.label("Asteroids:");
+                ui.add(egui::DragValue::new(&mut field_settings.count).range(1..=100_000));
+                ui.label("Unique shapes:");
+                ui.add(egui::DragValue::new(&mut field_settings.unique_shapes).range(1..=64));
+            });
+            ui.horizontal(|ui| {
+                ui.label("Shape resolution:");
+                ui.add(egui::DragValue::new(&mut field_settings.resolution).range(4..=200));
+            });
+            ui.horizontal(|ui| {
+                ui.label(format!("Radius ({}):", unit));
+                ui.add(egui::DragValue::new(&mut field_settings.inner_radius).speed(1.0).range(0.0..=f32::MAX));
+                ui.label("to");
+                ui.add(egui::DragValue::new(&mut field_settings.outer_radius).speed(1.0).range(0.0..=f32::MAX));
+            });
+            ui.horizontal(|ui| {
+                ui.label(format!("Thickness ({}):", unit));
+                ui.add(egui::DragValue::new(&mut field_settings.thickness).speed(1.0).range(0.0..=f32::MAX));
+            });
+            ui.horizontal(|ui| {
+                ui.label(format!("Size ({}):", unit));
+                ui.add(egui::DragValue::new(&mut field_settings.min_size).speed(0.1).range(0.001..=f32::MAX));
+                ui.label("to");
+                ui.add(egui::DragValue::new(&mut field_settings.max_size).speed(0.1).range(0.001..=f32::MAX));
+            });
+            ui.add(egui::Slider::new(&mut field_settings.size_exponent, 0.0..=5.0).text("Size exponent"));
+            ui.add(egui::Slider::new(&mut field_settings.noise_variation, 0.0..=1.0).text("Noise variation"));
+            ui.add(egui::Slider::new(&mut field_settings.crater_variation, 0.0..=1.0).text("Crater variation"));
+            ui.checkbox(&mut field_settings.show_in_viewer, "Show field in viewer");
+
+            if ui.add_enabled(!field_jobs.is_running(), egui::Button::new("Generate field")).clicked() {
+                commands.trigger(GenerateFieldClicked);
+            }
+            if field_jobs.is_running() {
+                let progress = jobs.get_progress();
+                ui.add(
+                    egui::ProgressBar::new(progress.get_fraction())
+                        .text(format!("{} / {} generated", progress.finished, progress.submitted)),
+                );
+            } else if !field.is_empty() {
+                let usage = field.get_shape_usage();
+                ui.label(RichText::new(format!(
+                    "{} asteroids from {} shapes, used {:?} times",
+                    field.instances.len(),
+                    field.shapes.len(),
+                    usage
+                )).weak());
+                ui.label(RichText::new("Export with the asteroid field (glTF) format").weak());
+            }
+        });
+}
+
+fn show_mesh_quality_ui(
+    mut contexts: EguiContexts,
+    mut quality_settings: ResMut<MeshQualitySettings>,
+    mesh_quality: Res<MeshQuality>,
+) {
+    let Some(ctx) = contexts.try_ctx_mut() else {
+        return;
+    };
+
+    egui::Window::new("Mesh Quality")
+        .default_open(false)
+        .default_width(420.)
+        .show(ctx, |ui| {
+            ui.checkbox(&mut quality_settings.highlight, "Highlight problem triangles");
+            ui.horizontal(|ui| {
+                ui.label("On export:");
+                for action in ExportQualityAction::ALL {
+                    ui.radio_value(&mut quality_settings.on_export, action, action.label());
+                }
             });
+            ui.add_enabled_ui(quality_settings.on_export == ExportQualityAction::Repair, |ui| {
+                ui.add(egui::Slider::new(&mut quality_settings.max_repair_iterations, 1..=50).text("Repair iterations"));
+            });
+
+            if mesh_quality.is_running() {
+                ui.horizontal(|ui| {
+                    ui.spinner();
+                    ui.label("Analysing…");
+                });
+            }
+            if let Some(report) = &mesh_quality.report {
+                if report.is_clean() {
+                    ui.colored_label(egui::Color32::from_rgb(99, 181, 74), "No folded or broken triangles");
+                }
+                for issue in report.issues() {
+                    ui.colored_label(egui::Color32::from_rgb(230, 180, 60), issue);
+                }
+            }
+        });
+}
+
+/// Export-only settings, they never trigger a recompute.
+fn show_print_ui(mut contexts: EguiContexts, mut print_settings: ResMut<PrintSettings>, print_report: Res<PrintReport>) {
+    let Some(ctx) = contexts.try_ctx_mut() else {
+        return;
+    };
+
+    egui::Window::new("3D Print (STL)")
+        .default_open(false)
+        .default_width(420.)
+        .show(ctx, |ui| {
+            let mut print_changed = false;
+            slider(ui, "Target size (mm)", &mut print_settings.target_size_mm, 1f64, 10.0..=300., &mut print_changed);
+            ui.checkbox(&mut print_settings.hollow, "Hollow");
+            ui.add_enabled_ui(print_settings.hollow, |ui| {
+                slider(ui, "Wall thickness (mm)", &mut print_settings.wall_thickness_mm, 0.1f64, 0.4..=20., &mut print_changed);
+                ui.add(egui::Slider::new(&mut print_settings.num_drain_holes, 0..=8).text("Drain holes"));
+                slider(ui, "Drain hole diameter (mm)", &mut print_settings.drain_hole_diameter_mm, 0.5f64, 1.0..=20., &mut print_changed);
+            });
+
+            if let Some(validation) = &print_report.validation {
+                if validation.is_valid() {
+                    ui.colored_label(egui::Color32::from_rgb(99, 181, 74), "Watertight and manifold");
+                }
+                for issue in validation.issues() {
+                    ui.colored_label(egui::Color32::from_rgb(220, 80, 60), issue);
+                }
+            }
+            if print_report.self_intersecting_triangles > 0 {
+                ui.colored_label(
+                    egui::Color32::from_rgb(220, 80, 60),
+                    format!("{} triangles of the hollow shell intersect, try a thinner wall", print_report.self_intersecting_triangles),
+                );
+            }
+            for warning in &print_report.warnings {
+                ui.colored_label(egui::Color32::from_rgb(230, 180, 60), warning);
+            }
+        });
+}
+
+fn show_mass_ui(
+    mut contexts: EguiContexts,
+    mut mass_settings: ResMut<MassSettings>,
+    mass_properties: Res<AsteroidMassProperties>,
+    scale_settings: Res<ScaleSettings>,
+) {
+    let Some(ctx) = contexts.try_ctx_mut() else {
+        return;
+    };
+
+    egui::Window::new("Mass Properties")
+        .default_open(false)
+        .default_width(420.)
+        .show(ctx, |ui| {
+            ui.horizontal(|ui| {
+                ui.label("Density (kg/m³):");
+                ui.add(egui::DragValue::new(&mut mass_settings.density).speed(0.01).range(0.001..=f32::MAX));
+            });
+
+            if let Some(properties) = &mass_properties.0 {
+                let properties = properties
+                    .scaled(scale_settings.get_scale_in_metres())
+                    .with_density(mass_settings.density);
+                let [x, y, z] = properties.center_of_mass;
+                ui.label(format!("Volume: {:.5e} m³", properties.volume));
+                ui.label(format!("Surface area: {:.5e} m²", properties.surface_area));
+                ui.label(format!("Mass: {:.5e} kg", properties.mass));
+                ui.label(format!("Centre of mass: ({:.5}, {:.5}, {:.5}) m", x, y, z));
+                ui.label("Inertia tensor (kg·m²):");
+                for [a, b, c] in properties.inertia_tensor {
+                    ui.monospace(format!("{:>12.4e} {:>12.4e} {:>12.4e}", a, b, c));
+                }
+            }
+        });
+}
+
+fn slider(ui: &mut egui::Ui, label: &str, value: &mut f32, step: f64, range: RangeInclusive<f32>, changed: &mut bool) {
+    ui.style_mut().spacing.slider_width = 200.;
+
+    let response = ui.add(
+        egui::Slider::new(value, range)
+            .text(label)
+            .step_by(step)
+            .clamp_to_range(true),
+    );
+    if response.changed() {
+        *changed = true;
+    }
+}
+
+fn request_export(commands: &mut Commands, export_status: &mut ExportStatus, format: ExportFormat, path: PathBuf) {
+    export_status.clear();
+    if let Some(parent) = path.parent() {
+        if let Err(err) = fs::create_dir_all(parent) {
+            export_status.fail(ExportError::Io(parent.to_path_buf(), err));
+            return;
+        }
+    }
+
+    commands.trigger(ExportButtonClicked { format, path });
+}
+
+/// File sizes in the largest unit that keeps the value above one.
+fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["B", "KB", "MB", "GB"];
+    let mut value = bytes as f64;
+    let mut unit = 0;
+    while value >= 1024. && unit < UNITS.len() - 1 {
+        value /= 1024.;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} {}", bytes, UNITS[0])
+    } else {
+        format!("{:.1} {}", value, UNITS[unit])
     }
 }
@@ -0,0 +1,265 @@
+use std::f32::consts::PI;
+use std::fs;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+use bevy::app::{App, Plugin};
+use bevy::asset::{Assets, Handle};
+use bevy::math::Vec3;
+use bevy::prelude::{Mesh, Query, Res, ResMut, Trigger, With};
+use bevy::reflect::Reflect;
+
+use crate::asteroid_mesh_builder::Asteroid;
+use crate::export_error::io_error;
+use crate::export_mesh::{ExportFormat, ExportMesh};
+use crate::export_task::{ExportButtonClicked, ExportOutput, ExportStatus, ExportTaskPlugin};
+use crate::mass_properties::write_mass_properties;
+use crate::mesh_quality::check_export_mesh;
+use crate::settings::asteroid_settings::AsteroidSettings;
+use crate::settings::mass_settings::MassSettings;
+use crate::settings::mesh_quality_settings::MeshQualitySettings;
+use crate::settings::scale_settings::{Handedness, ScaleSettings, UpAxis};
+use crate::utils::for_each_field;
+use crate::RngSeed;
+
+pub struct UsdExporter;
+
+impl Plugin for UsdExporter {
+    fn build(&self, app: &mut App) {
+        if !app.is_plugin_added::<ExportTaskPlugin>() {
+            app.add_plugins(ExportTaskPlugin);
+        }
+
+        app.init_resource::<ScaleSettings>()
+            .init_resource::<MassSettings>()
+            .init_resource::<MeshQualitySettings>()
+            .observe(export_usd);
+    }
+}
+
+fn export_usd(
+    trigger: Trigger<ExportButtonClicked>,
+    asteroid_query: Query<&Handle<Mesh>, With<Asteroid>>,
+    meshes: Res<Assets<Mesh>>,
+    settings: Res<AsteroidSettings>,
+    scale_settings: Res<ScaleSettings>,
+    quality_settings: Res<MeshQualitySettings>,
+    mass_settings: Res<MassSettings>,
+    seed: Res<RngSeed>,
+    mut export_status: ResMut<ExportStatus>,
+) {
+    let ev = trigger.event();
+    if ev.format != ExportFormat::Usda {
+        return;
+    }
+
+    let mut export_mesh = match ExportMesh::from_asteroid(&asteroid_query, &meshes) {
+        Ok(export_mesh) => export_mesh,
+        Err(err) => {
+            export_status.fail(err);
+            return;
+        }
+    };
+
+    let path = ev.path.clone();
+    let settings = settings.clone();
+    let scale_settings = scale_settings.clone();
+    let seed = seed.0;
+    let quality_settings = quality_settings.clone();
+    let density = mass_settings.density;
+
+    export_status.spawn(move || {
+        let notes = check_export_mesh(&mut export_mesh, &quality_settings)?;
+        let mass_properties = export_mesh.get_mass_properties(density).scaled(scale_settings.get_scale_in_metres());
+        export_mesh.scale(scale_settings.get_scale());
+        write_usda(&export_mesh, &path, &settings, &scale_settings, seed).map_err(io_error(&path))?;
+        let mass_path = write_mass_properties(&mass_properties, &path)?;
+        Ok(ExportOutput::new(vec![path, mass_path]).with_notes(notes))
+    });
+}
+
+/// Writes a `UsdGeomMesh` bound to a `UsdPreviewSurface` material.
+/// The generation settings are stored as `astrogen:` custom attributes on the root prim.
+///
+/// `export_mesh` is still Y-up and right-handed. The axes are converted here, after the texture coordinates are
+/// projected, and a left-handed mesh keeps its winding and is declared through `orientation` instead.
+pub fn write_usda(
+    export_mesh: &ExportMesh,
+    path: &Path,
+    settings: &AsteroidSettings,
+    scale_settings: &ScaleSettings,
+    seed: u64,
+) -> std::io::Result<()> {
+    let mut writer = BufWriter::new(fs::File::create(path)?);
+
+    writeln!(writer, "#usda 1.0")?;
+    writeln!(writer, "(")?;
+    writeln!(writer, "    defaultPrim = \"Asteroid\"")?;
+    writeln!(writer, "    doc = \"Generated by AstroGen {}\"", env!("CARGO_PKG_VERSION"))?;
+    writeln!(writer, "    metersPerUnit = {}", scale_settings.unit.get_metres())?;
+    let up_axis = match scale_settings.up_axis {
+        UpAxis::Y => "Y",
+        UpAxis::Z => "Z",
+    };
+    writeln!(writer, "    upAxis = \"{}\"", up_axis)?;
+    writeln!(writer, ")")?;
+    writeln!(writer)?;
+
+    writeln!(writer, "def Xform \"Asteroid\" (")?;
+    writeln!(writer, "    kind = \"component\"")?;
+    writeln!(writer, ")")?;
+    writeln!(writer, "{{")?;
+
+    writeln!(writer, "    custom uint64 astrogen:seed = {}", seed)?;
+    writeln!(writer, "    custom float astrogen:body_radius = {}", scale_settings.body_radius)?;
+    let mut attribute_result = Ok(());
+    for_each_field(settings, ":", &mut |name, value| {
+        if attribute_result.is_ok() {
+            attribute_result = write_custom_attribute(&mut writer, name, value);
+        }
+    });
+    attribute_result?;
+    writeln!(writer)?;
+
+    write_mesh(&mut writer, export_mesh, settings, scale_settings)?;
+    writeln!(writer)?;
+    write_material(&mut writer, settings)?;
+
+    writeln!(writer, "}}")?;
+    writer.flush()
+}
+
+fn write_custom_attribute(writer: &mut impl Write, name: &str, value: &dyn Reflect) -> std::io::Result<()> {
+    if let Some(value) = value.downcast_ref::<f32>() {
+        writeln!(writer, "    custom float astrogen:{} = {}", name, value)
+    } else if let Some(value) = value.downcast_ref::<u32>() {
+        writeln!(writer, "    custom uint astrogen:{} = {}", name, value)
+    } else if let Some(value) = value.downcast_ref::<bool>() {
+        writeln!(writer, "    custom bool astrogen:{} = {}", name, *value as u8)
+    } else if let Some(value) = value.downcast_ref::<String>() {
+        writeln!(writer, "    custom string astrogen:{} = \"{}\"", name, value.replace('\\', "\\\\").replace('"', "\\\""))
+    } else if let Some([x, y, z]) = value.downcast_ref::<[f32; 3]>() {
+        writeln!(writer, "    custom float3 astrogen:{} = ({}, {}, {})", name, x, y, z)
+    } else {
+        Ok(())
+    }
+}
+
+fn write_mesh(
+    writer: &mut impl Write,
+    export_mesh: &ExportMesh,
+    settings: &AsteroidSettings,
+    scale_settings: &ScaleSettings,
+) -> std::io::Result<()> {
+    // Mirroring only swaps the handedness of the axes, the winding stays as generated
+    let positions: Vec<[f32; 3]> = export_mesh.positions.iter().map(|&p| scale_settings.convert_axes(p)).collect();
+    let normals: Vec<[f32; 3]> = export_mesh.normals.iter().map(|&n| scale_settings.convert_axes(n)).collect();
+    let orientation = match scale_settings.handedness {
+        Handedness::Right => "rightHanded",
+        Handedness::Left => "leftHanded",
+    };
+
+    let (min, max) = positions.iter().fold(
+        (Vec3::splat(f32::MAX), Vec3::splat(f32::MIN)),
+        |(min, max), &p| (min.min(Vec3::from(p)), max.max(Vec3::from(p))),
+    );
+
+    writeln!(writer, "    def Mesh \"AsteroidMesh\" (")?;
+    writeln!(writer, "        prepend apiSchemas = [\"MaterialBindingAPI\"]")?;
+    writeln!(writer, "    )")?;
+    writeln!(writer, "    {{")?;
+    writeln!(writer, "        float3[] extent = [({}, {}, {}), ({}, {}, {})]", min.x, min.y, min.z, max.x, max.y, max.z)?;
+    writeln!(writer, "        uniform token orientation = \"{}\"", orientation)?;
+    writeln!(writer, "        uniform token subdivisionScheme = \"none\"")?;
+
+    write!(writer, "        int[] faceVertexCounts = [")?;
+    write_list(writer, 0..export_mesh.triangle_count(), |writer, _| write!(writer, "3"))?;
+    writeln!(writer, "]")?;
+
+    write!(writer, "        int[] faceVertexIndices = [")?;
+    write_list(writer, export_mesh.indices.iter(), |writer, index| write!(writer, "{}", index))?;
+    writeln!(writer, "]")?;
+
+    write!(writer, "        point3f[] points = [")?;
+    write_list(writer, positions.iter(), |writer, [x, y, z]| write!(writer, "({}, {}, {})", x, y, z))?;
+    writeln!(writer, "]")?;
+
+    write!(writer, "        normal3f[] normals = [")?;
+    write_list(writer, normals.iter(), |writer, [x, y, z]| write!(writer, "({}, {}, {})", x, y, z))?;
+    writeln!(writer, "] (")?;
+    writeln!(writer, "            interpolation = \"vertex\"")?;
+    writeln!(writer, "        )")?;
+
+    // Spherical projection around the generator's Y axis, the asteroid has no authored UVs
+    write!(writer, "        texCoord2f[] primvars:st = [")?;
+    write_list(writer, export_mesh.positions.iter(), |writer, &p| {
+        let [u, v] = spherical_uv(Vec3::from(p));
+        write!(writer, "({}, {})", u, v)
+    })?;
+    writeln!(writer, "] (")?;
+    writeln!(writer, "            interpolation = \"vertex\"")?;
+    writeln!(writer, "        )")?;
+
+    match &export_mesh.colors {
+        Some(colors) => {
+            write!(writer, "        color3f[] primvars:displayColor = [")?;
+            write_list(writer, colors.iter(), |writer, [r, g, b]| write!(writer, "({}, {}, {})", r, g, b))?;
+            writeln!(writer, "] (")?;
+            writeln!(writer, "            interpolation = \"vertex\"")?;
+            writeln!(writer, "        )")?;
+        }
+        None => {
+            let [r, g, b] = settings.material_settings.base_color;
+            writeln!(writer, "        color3f[] primvars:displayColor = [({}, {}, {})] (", r, g, b)?;
+            writeln!(writer, "            interpolation = \"constant\"")?;
+            writeln!(writer, "        )")?;
+        }
+    }
+
+    writeln!(writer, "        rel material:binding = </Asteroid/Materials/AsteroidMaterial>")?;
+    writeln!(writer, "    }}")
+}
+
+fn write_material(writer: &mut impl Write, settings: &AsteroidSettings) -> std::io::Result<()> {
+    let material_settings = &settings.material_settings;
+    let [r, g, b] = material_settings.base_color;
+
+    writeln!(writer, "    def Scope \"Materials\"")?;
+    writeln!(writer, "    {{")?;
+    writeln!(writer, "        def Material \"AsteroidMaterial\"")?;
+    writeln!(writer, "        {{")?;
+    writeln!(writer, "            token outputs:surface.connect = </Asteroid/Materials/AsteroidMaterial/PreviewSurface.outputs:surface>")?;
+    writeln!(writer)?;
+    writeln!(writer, "            def Shader \"PreviewSurface\"")?;
+    writeln!(writer, "            {{")?;
+    writeln!(writer, "                uniform token info:id = \"UsdPreviewSurface\"")?;
+    writeln!(writer, "                color3f inputs:diffuseColor = ({}, {}, {})", r, g, b)?;
+    writeln!(writer, "                float inputs:roughness = {}", material_settings.roughness)?;
+    writeln!(writer, "                float inputs:metallic = {}", material_settings.metallic)?;
+    writeln!(writer, "                float inputs:ior = {}", material_settings.get_ior())?;
+    writeln!(writer, "                token outputs:surface")?;
+    writeln!(writer, "            }}")?;
+    writeln!(writer, "        }}")?;
+    writeln!(writer, "    }}")
+}
+
+fn write_list<W: Write, T>(
+    writer: &mut W,
+    items: impl Iterator<Item = T>,
+    mut write_item: impl FnMut(&mut W, T) -> std::io::Result<()>,
+) -> std::io::Result<()> {
+    for (i, item) in items.enumerate() {
+        if i > 0 {
+            write!(writer, ", ")?;
+        }
+        write_item(writer, item)?;
+    }
+    Ok(())
+}
+
+fn spherical_uv(position: Vec3) -> [f32; 2] {
+    let direction = position.normalize_or_zero();
+    let u = 0.5 + direction.z.atan2(direction.x) / (2.0 * PI);
+    let v = 0.5 + direction.y.clamp(-1.0, 1.0).asin() / PI;
+    [u, v]
+}
@@ -1,23 +1,49 @@
 use bevy::math::Vec3;
-use rand::distributions::{Distribution, Uniform};
-use rand::prelude::StdRng;
-use rand::{Rng, SeedableRng};
+use bevy::reflect::{Reflect, ReflectRef};
 
+/// Xoshiro256** seeded with SplitMix64. The generator and every sampling routine are written out here
+/// rather than taken from `rand`, whose `StdRng` may change between versions, so a seed produces the
+/// same asteroid on every platform and release. `tests/prng_golden.rs` pins the output.
 pub struct PRNG {
-    pub rng: StdRng,
+    state: [u64; 4],
 }
 
 impl PRNG {
     pub fn new(seed: u64) -> Self {
         PRNG {
-            rng: StdRng::seed_from_u64(seed),
+            state: [0, 1, 2, 3].map(|index| derive_seed(seed, index)),
         }
     }
 
+    pub fn next_u64(&mut self) -> u64 {
+        let [s0, s1, s2, s3] = &mut self.state;
+        let result = s1.wrapping_mul(5).rotate_left(7).wrapping_mul(9);
+        let t = *s1 << 17;
+
+        *s2 ^= *s0;
+        *s3 ^= *s1;
+        *s1 ^= *s2;
+        *s0 ^= *s3;
+        *s2 ^= t;
+        *s3 = s3.rotate_left(45);
+
+        result
+    }
+
+    /// Random value in [0, 1], both ends included. Uses 24 bits, which f32 represents exactly.
     pub fn get_value(&mut self) -> f32 {
-        let uniform = Uniform::new_inclusive(0.0, 1.0);
+        (self.next_u64() >> 40) as f32 / ((1u32 << 24) - 1) as f32
+    }
 
-        uniform.sample(&mut self.rng)
+    /// Random value in [min, max).
+    pub fn range(&mut self, min: f32, max: f32) -> f32 {
+        let t = (self.next_u64() >> 40) as f32 / (1u32 << 24) as f32;
+        min + (max - min) * t
+    }
+
+    /// Random index below `len`, by the multiply-shift method.
+    pub fn index(&mut self, len: usize) -> usize {
+        ((self.next_u64() as u128 * len as u128) >> 64) as usize
     }
 
     pub fn value_bias_lower(&mut self, bias_strength: f32) -> f32 {
@@ -36,19 +62,59 @@ impl PRNG {
         ((t + t * k) / (t * k + 1.0)).clamp(0.0, 1.0)
     }
 
+    /// Approximately standard normal sample: the sum of 12 uniform values, which has mean 6 and variance 1,
+    /// shifted to mean 0, so samples stay within ±6. Box-Muller would need `ln` and `cos`, which aren't
+    /// correctly rounded and may differ in the last bit between platforms, additions always round the same.
+    pub fn gaussian(&mut self) -> f32 {
+        (0..12).map(|_| self.get_value()).sum::<f32>() - 6.0
+    }
+
     pub fn random_on_unit_sphere(&mut self) -> Vec3 {
         loop {
             // Generate random points in a cube
-            let x: f32 = self.rng.gen_range(-1.0..1.0);
-            let y: f32 = self.rng.gen_range(-1.0..1.0);
-            let z: f32 = self.rng.gen_range(-1.0..1.0);
+            let x = self.range(-1.0, 1.0);
+            let y = self.range(-1.0, 1.0);
+            let z = self.range(-1.0, 1.0);
 
             let point = Vec3::new(x, y, z);
 
-            // Check if the point is on the unit sphere
-            if point.length_squared() <= 1.0 {
+            // Keep points inside the unit ball, skipping the centre which has no direction
+            if point.length_squared() <= 1.0 && point.length_squared() > 0.0 {
                 return point.normalize();
             }
         }
     }
 }
+
+/// Independent seed for the `index`-th item generated from `seed`, using the SplitMix64 finalizer.
+pub fn derive_seed(seed: u64, index: u64) -> u64 {
+    let mut z = seed.wrapping_add(index.wrapping_add(1).wrapping_mul(0x9E37_79B9_7F4A_7C15));
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
+
+/// Visits every leaf field of a reflected settings struct, depth first.
+/// Nested field names are joined with `separator`, e.g. `crater_settings:num_craters`.
+pub fn for_each_field(value: &dyn Reflect, separator: &str, visitor: &mut impl FnMut(&str, &dyn Reflect)) {
+    visit_field(value, "", separator, visitor);
+}
+
+fn visit_field(value: &dyn Reflect, path: &str, separator: &str, visitor: &mut impl FnMut(&str, &dyn Reflect)) {
+    match value.reflect_ref() {
+        ReflectRef::Struct(fields) => {
+            for i in 0..fields.field_len() {
+                let (Some(name), Some(field)) = (fields.name_at(i), fields.field_at(i)) else {
+                    continue;
+                };
+                let field_path = if path.is_empty() {
+                    name.to_string()
+                } else {
+                    format!("{}{}{}", path, separator, name)
+                };
+                visit_field(field, &field_path, separator, visitor);
+            }
+        }
+        _ => visitor(path, value),
+    }
+}
@@ -0,0 +1,31 @@
+//! Plugins of the AstroGen app, see `main.rs` for the order they're added in. The generation itself needs
+//! `AppComputePlugin` from `bevy_easy_compute`.
+//!
+//! The events and resources the settings window works with are exported too, so the viewer can also be driven
+//! without the `gui` feature.
+
+pub use crate::asteroid_field::{AsteroidFieldPlugin, FieldShapeJobs, GenerateFieldClicked};
+pub use crate::asteroid_material::{AsteroidMaterialPlugin, DetailTexture};
+pub use crate::asteroid_mesh_builder::AsteroidMeshBuilderPlugin;
+pub use crate::batch::run_cli;
+#[cfg(feature = "gui")]
+pub use crate::batch_ui::BatchPlugin;
+pub use crate::compute::ComputePlugin;
+pub use crate::compute_events::{
+    CraterSettingsChanged, MaterialSettingsChanged, MeshDataAfterCompute, PerturbStrengthChanged,
+    RidgeNoiseSettingsChanged, SimpleNoiseSettingsChanged, ValueChanged,
+};
+pub use crate::export_task::{ExportButtonClicked, ExportMessage, ExportOutput, ExportStatus, ExportTaskPlugin};
+pub use crate::gltf_exporter::GlTFExporter;
+pub use crate::gltf_importer::{GlTFImporter, ImportStatus, OpenButtonClicked};
+pub use crate::heightmap_exporter::HeightmapExporter;
+pub use crate::light::LightPlugin;
+pub use crate::main_camera::MainCameraPlugin;
+pub use crate::mass_properties::{AsteroidMassProperties, MassPropertiesPlugin};
+pub use crate::mesh_quality::{MeshQuality, MeshQualityPlugin};
+pub use crate::obj_exporter::ObjExporter;
+pub use crate::ply_exporter::PlyExporter;
+pub use crate::stl_exporter::StlExporter;
+#[cfg(feature = "gui")]
+pub use crate::ui_asteroid_settings::UIAsteroidSettings;
+pub use crate::usd_exporter::UsdExporter;
@@ -0,0 +1,69 @@
+//! Golden values for the seeded random streams. A failure here means existing seeds would produce
+//! different asteroids, so update the values only for an intentional, announced break.
+
+use astrogen::{AsteroidSettings, PRNG};
+use bevy::math::Vec3;
+
+#[test]
+fn xoshiro_stream_is_stable() {
+    let mut prng = PRNG::new(0);
+    let values: Vec<u64> = (0..4).map(|_| prng.next_u64()).collect();
+    assert_eq!(
+        values,
+        [11091344671253066420, 13793997310169335082, 1900383378846508768, 7684712102626143532]
+    );
+}
+
+#[test]
+fn sampling_routines_are_stable() {
+    let mut prng = PRNG::new(42);
+    let values: Vec<f32> = (0..4).map(|_| prng.get_value()).collect();
+    assert_eq!(values, [0.08386297, 0.37898025, 0.68004346, 0.924693]);
+
+    let mut prng = PRNG::new(42);
+    assert_eq!([prng.range(-1.0, 1.0), prng.range(-1.0, 1.0)], [-0.8322741, -0.24203956]);
+
+    let mut prng = PRNG::new(42);
+    let indices: Vec<usize> = (0..4).map(|_| prng.index(10)).collect();
+    assert_eq!(indices, [0, 3, 6, 9]);
+
+    let mut prng = PRNG::new(7);
+    let values: Vec<f32> = (0..3).map(|_| prng.value_bias_lower(0.3)).collect();
+    assert_eq!(values, [0.44522563, 0.117047735, 0.6423169]);
+
+    let mut prng = PRNG::new(7);
+    assert_eq!(prng.random_on_unit_sphere(), Vec3::new(0.44350928, -0.48921952, 0.7509752));
+
+    let mut prng = PRNG::new(42);
+    let values: Vec<f32> = (0..4).map(|_| prng.gaussian()).collect();
+    assert_eq!(values, [1.7162442, 0.9049692, 1.2638121, 1.2727375]);
+}
+
+#[test]
+fn craters_are_stable() {
+    let craters = AsteroidSettings::default().crater_settings.get_craters(2);
+    assert_eq!(craters.len(), 100);
+
+    let first = &craters[0];
+    assert_eq!(first.centre, Vec3::new(0.42246097, 0.87529117, 0.23535547));
+    assert_eq!(first.radius, 0.05148179);
+    assert_eq!(first.floor_height, -0.48630977);
+    assert_eq!(first.smoothness, 0.6770364);
+
+    let second = &craters[1];
+    assert_eq!(second.centre, Vec3::new(-0.20100398, 0.63158673, -0.74879605));
+    assert_eq!(second.radius, 0.11768277);
+    assert_eq!(second.floor_height, 0.14166248);
+    assert_eq!(second.smoothness, 0.54463446);
+}
+
+#[test]
+fn noise_offsets_are_stable() {
+    let noise_params = AsteroidSettings::default()
+        .simple_noise_settings
+        .get_noise_params(PRNG::new(2));
+    let offset_y = AsteroidSettings::default().simple_noise_settings.offset_y;
+    assert_eq!(noise_params[0][0], 764.1488);
+    assert_eq!(noise_params[0][1], 5425.798 + offset_y);
+    assert_eq!(noise_params[0][2], 1375.7671);
+}
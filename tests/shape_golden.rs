@@ -0,0 +1,48 @@
+//! Golden heights for the CPU shape evaluator. A failure here means existing seeds would produce
+//! different asteroids, so update the values only for an intentional, announced break. The ridge
+//! noise goes through `powf`, which may differ in the last bit between platforms, hence the tolerance.
+
+use astrogen::{AsteroidSettings, ShapeEvaluator};
+use bevy::math::Vec3;
+
+const TOLERANCE: f32 = 1e-6;
+
+fn assert_close(actual: f32, expected: f32, direction: Vec3) {
+    assert!(
+        (actual - expected).abs() <= TOLERANCE,
+        "at {}: {} != {}",
+        direction,
+        actual,
+        expected
+    );
+}
+
+#[test]
+fn heights_are_stable() {
+    let shape = ShapeEvaluator::new(&AsteroidSettings::default(), 2, 0.01);
+    let expected = [
+        (Vec3::X, 0.92734593),
+        (Vec3::Y, 0.8739721),
+        (Vec3::Z, 0.85397744),
+        (Vec3::NEG_X, 1.0141329),
+        (Vec3::NEG_Y, 0.80333936),
+        (Vec3::NEG_Z, 0.9708298),
+    ];
+    for (direction, height) in expected {
+        assert_close(shape.height(direction), height, direction);
+    }
+}
+
+#[test]
+fn craters_and_perturbation_are_stable() {
+    let shape = ShapeEvaluator::new(&AsteroidSettings::default(), 2, 0.01);
+
+    // Centre of the first crater for seed 2, see `craters_are_stable` in `prng_golden.rs`
+    let centre = Vec3::new(0.42246097, 0.87529117, 0.23535547);
+    assert_close(shape.crater_depth(centre), -0.010707779, centre);
+    assert_close(shape.height(centre), 0.9191527, centre);
+
+    let vertex = shape.vertex(Vec3::Y);
+    let expected = Vec3::new(-0.001970379, 0.87396777, 0.001970379);
+    assert!(vertex.abs_diff_eq(expected, TOLERANCE), "{} != {}", vertex, expected);
+}